@@ -3,6 +3,7 @@
 //! Run with: `cargo bench`
 
 use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use virtual_audio_cable::simd::{self, scalar};
 use virtual_audio_cable::{AudioFormat, AudioProcessor, RingBuffer, TripleRingBuffer};
 
 fn benchmark_ring_buffer_write(c: &mut Criterion) {
@@ -42,6 +43,32 @@ fn benchmark_ring_buffer_read(c: &mut Criterion) {
     group.finish();
 }
 
+fn benchmark_ring_buffer_slices(c: &mut Criterion) {
+    let mut group = c.benchmark_group("ring_buffer_slices");
+
+    for size in [256, 512, 1024, 2048, 4096].iter() {
+        group.bench_with_input(BenchmarkId::from_parameter(size), size, |b, &size| {
+            let mut buffer = RingBuffer::<f32>::new(size);
+            let mut sum = 0.0f32;
+
+            b.iter(|| {
+                let (first, second) = buffer.write_slices();
+                first.fill(1.0);
+                second.fill(1.0);
+                let written = first.len() + second.len();
+                buffer.commit_write(written);
+
+                let (first, second) = buffer.read_slices();
+                sum += first.iter().chain(second).sum::<f32>();
+                buffer.commit_read(written);
+            });
+            black_box(sum);
+        });
+    }
+
+    group.finish();
+}
+
 fn benchmark_triple_buffer(c: &mut Criterion) {
     let mut group = c.benchmark_group("triple_buffer");
 
@@ -75,7 +102,7 @@ fn benchmark_format_conversion(c: &mut Criterion) {
     for (name, format) in formats {
         for &size in &sizes {
             group.bench_with_input(BenchmarkId::new(name, size), &size, |b, &size| {
-                let processor = AudioProcessor::new(48000, 48000, 2, AudioFormat::F32LE);
+                let mut processor = AudioProcessor::new(48000, 48000, 2, AudioFormat::F32LE);
                 let input = vec![0.5f32; size];
 
                 b.iter(|| {
@@ -103,12 +130,14 @@ fn benchmark_format_conversion_back(c: &mut Criterion) {
     for (name, format) in formats {
         for &size in &sizes {
             group.bench_with_input(BenchmarkId::new(name, size), &size, |b, &size| {
-                let processor = AudioProcessor::new(48000, 48000, 2, AudioFormat::F32LE);
+                let mut processor = AudioProcessor::new(48000, 48000, 2, AudioFormat::F32LE);
                 let input = vec![0.5f32; size];
                 let bytes = processor.convert_format(&input, format);
 
                 b.iter(|| {
-                    processor.bytes_to_samples(black_box(&bytes), format);
+                    processor
+                        .bytes_to_samples(black_box(&bytes), format)
+                        .unwrap();
                 });
             });
         }
@@ -117,12 +146,31 @@ fn benchmark_format_conversion_back(c: &mut Criterion) {
     group.finish();
 }
 
+fn benchmark_decode_into_ring(c: &mut Criterion) {
+    let mut group = c.benchmark_group("decode_into_ring");
+
+    for size in [256, 512, 1024, 2048].iter() {
+        group.bench_with_input(BenchmarkId::from_parameter(size), size, |b, &size| {
+            let mut processor = AudioProcessor::new(48000, 48000, 2, AudioFormat::F32LE);
+            let bytes = processor.convert_format(&vec![0.5f32; size], AudioFormat::S16LE);
+            let mut ring = RingBuffer::<f32>::new(size);
+
+            b.iter(|| {
+                processor.decode_into_ring(black_box(&bytes), AudioFormat::S16LE, &mut ring);
+                ring.clear();
+            });
+        });
+    }
+
+    group.finish();
+}
+
 fn benchmark_audio_processor_passthrough(c: &mut Criterion) {
     let mut group = c.benchmark_group("audio_processor_passthrough");
 
     for size in [256, 512, 1024, 2048, 4096].iter() {
         group.bench_with_input(BenchmarkId::from_parameter(size), size, |b, &size| {
-            let processor = AudioProcessor::new(48000, 48000, 2, AudioFormat::F32LE);
+            let mut processor = AudioProcessor::new(48000, 48000, 2, AudioFormat::F32LE);
             let input = vec![0.5f32; size];
             let mut output = vec![0.0f32; size];
 
@@ -144,10 +192,10 @@ fn benchmark_resampling_up(c: &mut Criterion) {
     for (ratio_name, ratio) in ratios {
         for &size in &input_sizes {
             group.bench_with_input(BenchmarkId::new(ratio_name, size), &size, |b, &size| {
-                let resampler =
+                let mut resampler =
                     AudioProcessor::new(48000, (48000.0 * ratio) as u32, 2, AudioFormat::F32LE);
                 let input = vec![0.5f32; size];
-                let mut output = vec![0.0f32; (size as f64 * ratio) as usize];
+                let mut output = vec![0.0f32; resampler.max_output_len(size)];
 
                 b.iter(|| {
                     resampler.process(&input, &mut output).unwrap();
@@ -168,10 +216,10 @@ fn benchmark_resampling_down(c: &mut Criterion) {
     for (ratio_name, ratio) in ratios {
         for &size in &input_sizes {
             group.bench_with_input(BenchmarkId::new(ratio_name, size), &size, |b, &size| {
-                let resampler =
+                let mut resampler =
                     AudioProcessor::new(48000, (48000.0 * ratio) as u32, 2, AudioFormat::F32LE);
                 let input = vec![0.5f32; size];
-                let mut output = vec![0.0f32; (size as f64 * ratio) as usize];
+                let mut output = vec![0.0f32; resampler.max_output_len(size)];
 
                 b.iter(|| {
                     resampler.process(&input, &mut output).unwrap();
@@ -183,15 +231,74 @@ fn benchmark_resampling_down(c: &mut Criterion) {
     group.finish();
 }
 
+fn benchmark_simd_kernels(c: &mut Criterion) {
+    let mut group = c.benchmark_group("simd_kernels");
+    let size = 4096;
+    let input: Vec<f32> = (0..size).map(|i| (i as f32 * 0.01).sin()).collect();
+
+    let encoders: [(&str, simd::EncodeKernel, simd::EncodeKernel, usize); 3] = [
+        ("f32_to_s16le", scalar::f32_to_s16le, simd::f32_to_s16le, 2),
+        ("f32_to_s24le", scalar::f32_to_s24le, simd::f32_to_s24le, 3),
+        ("f32_to_s32le", scalar::f32_to_s32le, simd::f32_to_s32le, 4),
+    ];
+    for (name, reference, kernel, bytes) in encoders {
+        let mut output = vec![0u8; size * bytes];
+        group.bench_function(BenchmarkId::new(name, "scalar"), |b| {
+            b.iter(|| reference(black_box(&input), &mut output))
+        });
+        group.bench_function(BenchmarkId::new(name, "simd"), |b| {
+            b.iter(|| kernel(black_box(&input), &mut output))
+        });
+    }
+
+    let decoders: [(&str, simd::DecodeKernel, simd::DecodeKernel, usize); 3] = [
+        ("s16le_to_f32", scalar::s16le_to_f32, simd::s16le_to_f32, 2),
+        ("s24le_to_f32", scalar::s24le_to_f32, simd::s24le_to_f32, 3),
+        ("s32le_to_f32", scalar::s32le_to_f32, simd::s32le_to_f32, 4),
+    ];
+    let mut output = vec![0.0f32; size];
+    for (name, reference, kernel, bytes) in decoders {
+        let bytes = vec![0x5Au8; size * bytes];
+        group.bench_function(BenchmarkId::new(name, "scalar"), |b| {
+            b.iter(|| reference(black_box(&bytes), &mut output))
+        });
+        group.bench_function(BenchmarkId::new(name, "simd"), |b| {
+            b.iter(|| kernel(black_box(&bytes), &mut output))
+        });
+    }
+
+    let (left, right) = input.split_at(size / 2);
+    let mut interleaved = vec![0.0f32; size];
+    group.bench_function(BenchmarkId::new("interleave_stereo", "scalar"), |b| {
+        b.iter(|| scalar::interleave(black_box(&[left, right]), &mut interleaved))
+    });
+    group.bench_function(BenchmarkId::new("interleave_stereo", "simd"), |b| {
+        b.iter(|| simd::interleave(black_box(&[left, right]), &mut interleaved))
+    });
+
+    let mut mix = vec![0.0f32; size];
+    group.bench_function(BenchmarkId::new("mix_into", "scalar"), |b| {
+        b.iter(|| scalar::mix_into(&mut mix, black_box(&input), 0.5))
+    });
+    group.bench_function(BenchmarkId::new("mix_into", "simd"), |b| {
+        b.iter(|| simd::mix_into(&mut mix, black_box(&input), 0.5))
+    });
+
+    group.finish();
+}
+
 criterion_group!(
     benches,
     benchmark_ring_buffer_write,
     benchmark_ring_buffer_read,
+    benchmark_ring_buffer_slices,
     benchmark_triple_buffer,
     benchmark_format_conversion,
     benchmark_format_conversion_back,
+    benchmark_decode_into_ring,
     benchmark_audio_processor_passthrough,
     benchmark_resampling_up,
     benchmark_resampling_down,
+    benchmark_simd_kernels,
 );
 criterion_main!(benches);
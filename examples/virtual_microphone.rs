@@ -28,6 +28,7 @@ async fn main() -> Result<()> {
         buffer_size: 1024,          // Tamaño del buffer (ajustar según latencia deseada)
         format: AudioFormat::F32LE, // Formato de punto flotante de 32 bits
         device_name: "Micrófono Virtual".to_string(),
+        ..Default::default()
     };
 
     info!("⚙️  Configuración del Micrófono:");
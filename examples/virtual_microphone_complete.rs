@@ -28,6 +28,7 @@ async fn main() -> Result<()> {
         buffer_size: 2048,          // Tamaño del buffer (mayor = más latencia, más estabilidad)
         format: AudioFormat::F32LE, // Formato: F32LE, S16LE, S24LE, S32LE
         device_name: "Mi Micrófono Virtual".to_string(),
+        ..Default::default()
     };
 
     info!("⚙️  Configuración:");
@@ -191,6 +192,7 @@ fn create_preset_config(preset: &str) -> CableConfig {
             buffer_size: 4096,
             format: AudioFormat::F32LE,
             device_name: "High Quality Mic".to_string(),
+            ..Default::default()
         },
         "low_latency" => CableConfig {
             sample_rate: 48000,
@@ -198,6 +200,7 @@ fn create_preset_config(preset: &str) -> CableConfig {
             buffer_size: 512,
             format: AudioFormat::S16LE,
             device_name: "Low Latency Mic".to_string(),
+            ..Default::default()
         },
         _ => CableConfig::default(),
     }
@@ -3,9 +3,51 @@
 //! This module provides audio processing capabilities including
 //! resampling, format conversion, and audio effects.
 
+use crate::buffer::{RingBuffer, SampleBuffer};
+use crate::effect::AudioEffect;
+use crate::sample::{with_sample_format, SampleSlice, SampleSliceMut};
+use crate::simd;
 use crate::AudioFormat;
 use crate::Error;
 
+use atomic_float::AtomicF32;
+use std::any::Any;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+pub(crate) mod biquad;
+pub(crate) mod g711;
+mod loudness;
+
+pub use loudness::{measure_file, LoudnessMeter, LoudnessReading};
+
+/// Samples decoded per step by [`AudioProcessor::decode_into_buffer`].
+const DECODE_CHUNK_SAMPLES: usize = 64;
+
+/// Frames remixed per step when an [`AudioProcessor`] changes the channel
+/// count.
+const MIX_BLOCK_FRAMES: usize = 256;
+
+/// Resampling quality preset.
+///
+/// Everything except [`ResampleQuality::Linear`] needs the `rubato` feature;
+/// without it those presets fall back to the built-in linear interpolation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ResampleQuality {
+    /// Cubic polynomial interpolation without anti-aliasing (rubato)
+    Fast,
+
+    /// Built-in streaming linear interpolation
+    #[default]
+    Linear,
+
+    /// Windowed sinc with a short filter (rubato)
+    Medium,
+
+    /// Windowed sinc with a long filter and cubic interpolation (rubato)
+    Best,
+}
+
 /// Audio processor for handling sample rate conversion and format conversion.
 pub struct AudioProcessor {
     /// Input sample rate
@@ -20,8 +62,17 @@ pub struct AudioProcessor {
     /// Audio format
     pub format: AudioFormat,
 
-    /// Resampling factor (output_rate / input_rate)
-    resample_factor: f64,
+    /// Streaming resampler state, kept between calls
+    resampler: Resampler,
+
+    /// Channel mixer applied before resampling, if the channel count changes
+    mixer: Option<ChannelMixer>,
+
+    /// Scratch space for one mixed block, allocated with the mixer
+    mix_buffer: Vec<f32>,
+
+    /// Float to integer conversion state for [`AudioProcessor::convert_format`]
+    quantizer: Quantizer,
 }
 
 impl AudioProcessor {
@@ -39,21 +90,106 @@ impl AudioProcessor {
         channels: u16,
         format: AudioFormat,
     ) -> Self {
-        let resample_factor = output_sample_rate as f64 / input_sample_rate as f64;
-
         Self {
             input_sample_rate,
             output_sample_rate,
             channels,
             format,
-            resample_factor,
+            resampler: Resampler::new(input_sample_rate, output_sample_rate, channels),
+            mixer: None,
+            mix_buffer: Vec::new(),
+            quantizer: Quantizer::new(DitherMode::default(), channels),
+        }
+    }
+
+    /// Selects the resampling quality.
+    ///
+    /// Falls back to [`ResampleQuality::Linear`] if the preset is not
+    /// available or the rates are equal; see [`Resampler::with_quality`].
+    pub fn with_quality(mut self, quality: ResampleQuality) -> Self {
+        self.resampler = Resampler::with_quality(
+            self.input_sample_rate,
+            self.output_sample_rate,
+            self.output_channels(),
+            quality,
+        );
+        self
+    }
+
+    /// Returns the resampling quality actually in use.
+    pub fn quality(&self) -> ResampleQuality {
+        self.resampler.quality()
+    }
+
+    /// Remixes to `output_channels` with the standard matrix of
+    /// [`ChannelMixer::new`].
+    pub fn with_output_channels(self, output_channels: u16) -> Self {
+        let mixer = ChannelMixer::new(self.channels, output_channels);
+        self.with_mixer(mixer)
+            .expect("standard mixer matches the input channel count")
+    }
+
+    /// Remixes with a custom [`ChannelMixer`] before resampling.
+    ///
+    /// The mixer's input channel count must match `channels`.
+    pub fn with_mixer(mut self, mixer: ChannelMixer) -> Result<Self, Error> {
+        if mixer.input_channels() != (self.channels as usize).max(1) {
+            return Err(Error::AudioError(format!(
+                "Mixer expects {} input channels, processor has {}",
+                mixer.input_channels(),
+                self.channels
+            )));
         }
+        let quality = self.quality();
+        self.quantizer = Quantizer::new(self.dither(), mixer.output_channels() as u16);
+        self.mixer = if mixer == ChannelMixer::new(self.channels, self.channels) {
+            None
+        } else {
+            Some(mixer)
+        };
+        self.mix_buffer = match &self.mixer {
+            Some(mixer) => vec![0.0; MIX_BLOCK_FRAMES * mixer.output_channels()],
+            None => Vec::new(),
+        };
+        Ok(self.with_quality(quality))
+    }
+
+    /// Returns the number of channels in the processed output.
+    pub fn output_channels(&self) -> u16 {
+        self.mixer
+            .as_ref()
+            .map_or(self.channels, |mixer| mixer.output_channels() as u16)
+    }
+
+    /// Selects how [`AudioProcessor::convert_format`] reduces samples to
+    /// 16 and 24 bit integers.
+    pub fn with_dither(mut self, mode: DitherMode) -> Self {
+        self.quantizer = Quantizer::new(mode, self.output_channels());
+        self
+    }
+
+    /// Returns the dither mode used for integer output.
+    pub fn dither(&self) -> DitherMode {
+        self.quantizer.mode()
+    }
+
+    /// Returns the number of samples outside -1.0..=1.0 that were clipped
+    /// while converting to integer formats.
+    pub fn clipped_samples(&self) -> u64 {
+        self.quantizer.clipped_samples()
+    }
+
+    /// Returns the channel mixer, if the channel count changes.
+    pub fn mixer(&self) -> Option<&ChannelMixer> {
+        self.mixer.as_ref()
     }
 
-    /// Processes audio samples.
+    /// Processes the next block of interleaved audio samples.
     ///
-    /// This method performs resampling if input and output sample rates differ.
-    /// For now, it's a simple pass-through implementation.
+    /// This method performs streaming resampling if input and output sample
+    /// rates differ, continuing the phase of the previous call. Input that
+    /// does not fit in `output` is dropped; size `output` with
+    /// [`AudioProcessor::max_output_len`] to keep the whole stream.
     ///
     /// # Arguments
     ///
@@ -63,34 +199,41 @@ impl AudioProcessor {
     /// # Returns
     ///
     /// Number of samples written to output
-    pub fn process(&self, input: &[f32], output: &mut [f32]) -> Result<usize, Error> {
-        let to_process = input.len().min(output.len());
-
+    pub fn process(&mut self, input: &[f32], output: &mut [f32]) -> Result<usize, Error> {
+        if self.mixer.is_some() {
+            let (_, produced) = self.process_stage(input, output)?;
+            return Ok(produced);
+        }
         if self.input_sample_rate == self.output_sample_rate {
             // Pass-through when sample rates match
+            let to_process = input.len().min(output.len());
             output[..to_process].copy_from_slice(&input[..to_process]);
             return Ok(to_process);
         }
 
-        // Simple resampling (linear interpolation - placeholder)
-        // In production, use rubato or similar library
-        let output_len = ((input.len() as f64) * self.resample_factor) as usize;
-        let actual_output = output_len.min(output.len());
+        let (_, produced) = self.resampler.process_stage(input, output)?;
+        Ok(produced)
+    }
 
-        for (i, out) in output.iter_mut().enumerate().take(actual_output) {
-            let src_idx = (i as f64 / self.resample_factor) as usize;
-            if src_idx < input.len() {
-                *out = input[src_idx];
-            }
+    /// Returns an output length, in samples, large enough for one
+    /// [`AudioProcessor::process`] call with `input_len` input samples.
+    pub fn max_output_len(&self, input_len: usize) -> usize {
+        let input_len = self
+            .mixer
+            .as_ref()
+            .map_or(input_len, |mixer| mixer.output_len(input_len));
+        if !self.needs_resampling() {
+            return input_len;
         }
-
-        Ok(actual_output)
+        self.resampler.max_output_len(input_len)
     }
 
     /// Converts audio samples between formats.
     ///
-    /// Currently supports F32 to S16 conversion.
-    /// More conversions will be added in the future.
+    /// Integer formats use the full code range, with -1.0 mapping to the
+    /// most negative code. 16 and 24 bit output is rounded or dithered as
+    /// selected by [`AudioProcessor::with_dither`]; out-of-range samples are
+    /// saturated and counted in [`AudioProcessor::clipped_samples`].
     ///
     /// # Arguments
     ///
@@ -100,39 +243,29 @@ impl AudioProcessor {
     /// # Returns
     ///
     /// Vector of bytes in the target format
-    pub fn convert_format(&self, input: &[f32], output_format: AudioFormat) -> Vec<u8> {
-        match output_format {
-            AudioFormat::F32LE => {
-                let bytes: &[u8] = unsafe {
-                    std::slice::from_raw_parts(input.as_ptr() as *const u8, input.len() * 4)
-                };
-                bytes.to_vec()
-            }
-            AudioFormat::S16LE => {
-                let mut output = Vec::with_capacity(input.len() * 2);
-                for &sample in input {
-                    let s16 = (sample.clamp(-1.0, 1.0) * 32767.0) as i16;
-                    output.extend_from_slice(&s16.to_le_bytes());
-                }
-                output
-            }
-            AudioFormat::S24LE => {
-                let mut output = Vec::with_capacity(input.len() * 3);
-                for &sample in input {
-                    let s24 = (sample.clamp(-1.0, 1.0) * 8388607.0) as i32;
-                    output.extend_from_slice(&s24.to_le_bytes()[..3]);
-                }
-                output
-            }
-            AudioFormat::S32LE => {
-                let mut output = Vec::with_capacity(input.len() * 4);
-                for &sample in input {
-                    let s32 = (sample.clamp(-1.0, 1.0) * 2147483647.0) as i32;
-                    output.extend_from_slice(&s32.to_le_bytes());
-                }
-                output
-            }
+    pub fn convert_format(&mut self, input: &[f32], output_format: AudioFormat) -> Vec<u8> {
+        let mut output = vec![0u8; input.len() * output_format.bytes_per_sample()];
+        // Plain rounding to little-endian integers has vectorized kernels,
+        // and F32LE is a copy
+        let kernel: Option<simd::EncodeKernel> = match output_format {
+            AudioFormat::F32LE => Some(simd::f32_to_f32le),
+            _ if self.quantizer.mode() != DitherMode::Round => None,
+            AudioFormat::S16LE => Some(simd::f32_to_s16le),
+            AudioFormat::S24LE => Some(simd::f32_to_s24le),
+            AudioFormat::S32LE => Some(simd::f32_to_s32le),
+            _ => None,
+        };
+        if let Some(kernel) = kernel {
+            let clipped = kernel(input, &mut output);
+            self.quantizer.add_clipped(clipped);
+            return output;
         }
+        with_sample_format!(output_format, F => {
+            SampleSliceMut::<F>::split_whole(&mut output)
+                .0
+                .encode_from(input, &mut self.quantizer)
+        });
+        output
     }
 
     /// Converts bytes to f32 samples.
@@ -144,58 +277,178 @@ impl AudioProcessor {
     ///
     /// # Returns
     ///
-    /// Vector of f32 samples
-    pub fn bytes_to_samples(&self, input: &[u8], input_format: AudioFormat) -> Vec<f32> {
+    /// Vector of f32 samples, or an error if `input` ends with a partial
+    /// sample
+    pub fn bytes_to_samples(
+        &self,
+        input: &[u8],
+        input_format: AudioFormat,
+    ) -> Result<Vec<f32>, Error> {
+        with_sample_format!(input_format, F => Ok(SampleSlice::<F>::new(input)?.to_vec()))
+    }
+
+    /// Decodes bytes into an existing f32 buffer without allocating.
+    ///
+    /// Only whole samples are decoded; the bytes of a trailing partial sample
+    /// are left for the caller, e.g. to prepend to the next block.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - Input bytes in the specified format
+    /// * `input_format` - Format of the input bytes
+    /// * `output` - Destination for the decoded samples
+    ///
+    /// # Returns
+    ///
+    /// Number of samples written to output
+    pub fn decode_into(
+        &self,
+        input: &[u8],
+        input_format: AudioFormat,
+        output: &mut [f32],
+    ) -> usize {
+        let kernel: Option<simd::DecodeKernel> = match input_format {
+            AudioFormat::F32LE => Some(simd::f32le_to_f32),
+            AudioFormat::S16LE => Some(simd::s16le_to_f32),
+            AudioFormat::S24LE => Some(simd::s24le_to_f32),
+            AudioFormat::S32LE => Some(simd::s32le_to_f32),
+            _ => None,
+        };
+        if let Some(kernel) = kernel {
+            let samples = output
+                .len()
+                .min(input.len() / input_format.bytes_per_sample());
+            kernel(input, &mut output[..samples]);
+            return samples;
+        }
+        with_sample_format!(input_format, F => {
+            SampleSlice::<F>::split_whole(input).0.decode_into(output)
+        })
+    }
+
+    /// Decodes bytes straight into the free space of a ring buffer.
+    ///
+    /// Only whole samples that fit are decoded and committed; nothing is
+    /// copied through an intermediate buffer.
+    ///
+    /// # Returns
+    ///
+    /// Number of samples written to the ring buffer
+    pub fn decode_into_ring(
+        &self,
+        input: &[u8],
+        input_format: AudioFormat,
+        ring: &mut RingBuffer<f32>,
+    ) -> usize {
         let bytes_per_sample = input_format.bytes_per_sample();
-        let num_samples = input.len() / bytes_per_sample;
+        let (first, second) = ring.write_slices();
 
-        let mut output = Vec::with_capacity(num_samples);
+        let head = self.decode_into(input, input_format, first);
+        let tail = if head == first.len() {
+            self.decode_into(&input[head * bytes_per_sample..], input_format, second)
+        } else {
+            0
+        };
 
-        match input_format {
-            AudioFormat::F32LE => {
-                let samples: &[f32] = unsafe {
-                    std::slice::from_raw_parts(input.as_ptr() as *const f32, num_samples)
-                };
-                output.extend_from_slice(samples);
-            }
-            AudioFormat::S16LE => {
-                for i in 0..num_samples {
-                    let start = i * 2;
-                    let s16 = i16::from_le_bytes([input[start], input[start + 1]]);
-                    output.push(s16 as f32 / 32767.0);
-                }
+        ring.commit_write(head + tail);
+        head + tail
+    }
+
+    /// Decodes bytes straight into any [`SampleBuffer`] without allocating.
+    ///
+    /// Samples are decoded through a small stack buffer, so this works with
+    /// fixed-capacity buffers such as
+    /// [`HeaplessRingBuffer`](crate::buffer::HeaplessRingBuffer).
+    ///
+    /// # Returns
+    ///
+    /// Number of samples written to the buffer
+    pub fn decode_into_buffer<B: SampleBuffer<f32>>(
+        &self,
+        input: &[u8],
+        input_format: AudioFormat,
+        buffer: &mut B,
+    ) -> usize {
+        let bytes_per_sample = input_format.bytes_per_sample();
+        let mut chunk = [0.0f32; DECODE_CHUNK_SAMPLES];
+        let mut written = 0;
+
+        for bytes in input.chunks(DECODE_CHUNK_SAMPLES * bytes_per_sample) {
+            let len = self
+                .decode_into(bytes, input_format, &mut chunk)
+                .min(buffer.free_space());
+            if len == 0 {
+                break;
             }
-            AudioFormat::S24LE => {
-                for i in 0..num_samples {
-                    let start = i * 3;
-                    let mut bytes = [0u8; 4];
-                    bytes[0] = input[start];
-                    bytes[1] = input[start + 1];
-                    bytes[2] = input[start + 2];
-                    let s24 = i32::from_le_bytes(bytes);
-                    output.push(s24 as f32 / 8388607.0);
+            written += buffer.write(&chunk[..len]);
+        }
+        written
+    }
+
+    /// Returns true if resampling is needed.
+    pub fn needs_resampling(&self) -> bool {
+        self.input_sample_rate != self.output_sample_rate
+    }
+
+    /// Resamples (or copies) audio that already has the output channel count.
+    fn convert_stage(
+        &mut self,
+        input: &[f32],
+        output: &mut [f32],
+    ) -> Result<(usize, usize), Error> {
+        if !self.needs_resampling() {
+            let channels = self.resampler.channels() as usize;
+            let len = input.len().min(output.len()) / channels * channels;
+            output[..len].copy_from_slice(&input[..len]);
+            return Ok((len, len));
+        }
+        self.resampler.process_stage(input, output)
+    }
+}
+
+impl ProcessingStage for AudioProcessor {
+    fn process_stage(
+        &mut self,
+        input: &[f32],
+        output: &mut [f32],
+    ) -> Result<(usize, usize), Error> {
+        let Some(mixer) = self.mixer.take() else {
+            return self.convert_stage(input, output);
+        };
+        let (inputs, outputs) = (mixer.input_channels(), mixer.output_channels());
+
+        // Remix through the fixed scratch buffer, one block at a time
+        let mut mixed = std::mem::take(&mut self.mix_buffer);
+        let (mut consumed, mut produced) = (0, 0);
+        let mut result = Ok(());
+        for block in input.chunks(MIX_BLOCK_FRAMES * inputs) {
+            let len = mixer.process(block, &mut mixed) * outputs;
+            match self.convert_stage(&mixed[..len], &mut output[produced..]) {
+                Ok((used, made)) => {
+                    consumed += used / outputs * inputs;
+                    produced += made;
+                    if used < len {
+                        break;
+                    }
                 }
-            }
-            AudioFormat::S32LE => {
-                for i in 0..num_samples {
-                    let start = i * 4;
-                    let s32 = i32::from_le_bytes([
-                        input[start],
-                        input[start + 1],
-                        input[start + 2],
-                        input[start + 3],
-                    ]);
-                    output.push(s32 as f32 / 2147483647.0);
+                Err(e) => {
+                    result = Err(e);
+                    break;
                 }
             }
         }
+        self.mix_buffer = mixed;
+        self.mixer = Some(mixer);
 
-        output
+        result.map(|()| (consumed, produced))
     }
 
-    /// Returns true if resampling is needed.
-    pub fn needs_resampling(&self) -> bool {
-        self.input_sample_rate != self.output_sample_rate
+    fn input_channels(&self) -> usize {
+        (self.channels as usize).max(1)
+    }
+
+    fn output_channels(&self) -> usize {
+        (AudioProcessor::output_channels(self) as usize).max(1)
     }
 }
 
@@ -205,112 +458,1840 @@ impl Default for AudioProcessor {
     }
 }
 
-/// Resampler for sample rate conversion.
+/// How float samples are reduced to 8, 16 or 24 bit integers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DitherMode {
+    /// Drop the fraction (rounds towards zero)
+    Truncate,
+
+    /// Round to the nearest code, ties to even
+    #[default]
+    Round,
+
+    /// Add triangular (TPDF) dither of ±1 LSB before rounding
+    Tpdf,
+
+    /// TPDF dither with second-order noise shaping, which moves the
+    /// quantization noise towards high frequencies
+    NoiseShaped,
+}
+
+/// Converts float samples to integer codes with optional dither.
 ///
-/// This is a placeholder for integration with rubato library.
-#[allow(dead_code)] // channels will be used with rubato integration
-pub struct Resampler {
-    input_rate: u32,
-    output_rate: u32,
-    channels: u16,
+/// Samples are scaled by a power of two (32768 for 16 bit), so -1.0 maps to
+/// the most negative code and the full range is used; values at or above
+/// 1.0 saturate to the most positive code. Noise shaping keeps its error
+/// history per channel of the interleaved stream.
+#[derive(Debug, Clone)]
+pub struct Quantizer {
+    mode: DitherMode,
+    channels: usize,
+    /// Channel of the next sample in the interleaved stream
+    channel: usize,
+    /// Last two quantization errors per channel, in LSBs
+    errors: Vec<[f64; 2]>,
+    rng: u32,
+    clipped: u64,
 }
 
-impl Resampler {
-    /// Creates a new resampler.
-    pub fn new(input_rate: u32, output_rate: u32, channels: u16) -> Self {
+impl Quantizer {
+    /// Creates a quantizer for an interleaved stream.
+    pub fn new(mode: DitherMode, channels: u16) -> Self {
+        let channels = (channels as usize).max(1);
         Self {
-            input_rate,
-            output_rate,
+            mode,
             channels,
+            channel: 0,
+            errors: vec![[0.0; 2]; channels],
+            rng: 0x9E37_79B9,
+            clipped: 0,
+        }
+    }
+
+    /// Returns the dither mode.
+    pub fn mode(&self) -> DitherMode {
+        self.mode
+    }
+
+    /// Returns the number of input samples outside -1.0..=1.0 so far.
+    pub fn clipped_samples(&self) -> u64 {
+        self.clipped
+    }
+
+    /// Quantizes one sample to a signed integer of `bits` bits (at most 32).
+    pub fn quantize(&mut self, sample: f32, bits: u32) -> i32 {
+        let channel = self.channel;
+        self.channel = (channel + 1) % self.channels;
+        if !(-1.0..=1.0).contains(&sample) {
+            self.clipped += 1;
+        }
+
+        let max = (1i64 << (bits.clamp(2, 32) - 1)) as f64;
+        let value = f64::from(sample.clamp(-1.0, 1.0)) * max;
+        // An f32 source holds no detail below 24 bits, so 32 bit is only rounded
+        let mode = if bits > 24 {
+            DitherMode::Round
+        } else {
+            self.mode
+        };
+        let code = match mode {
+            DitherMode::Truncate => value.trunc(),
+            DitherMode::Round => value.round_ties_even(),
+            DitherMode::Tpdf => (value + self.tpdf()).round(),
+            DitherMode::NoiseShaped => {
+                // Error filter (1 - z^-1)^2 pushes the noise up in frequency
+                let [e1, e2] = self.errors[channel];
+                let shaped = value - 2.0 * e1 + e2;
+                let code = (shaped + self.tpdf()).round();
+                let error = (code - shaped).clamp(-2.0, 2.0);
+                self.errors[channel] = [error, e1];
+                code
+            }
+        };
+        code.clamp(-max, max - 1.0) as i32
+    }
+
+    /// Counts samples clipped by a conversion that bypassed [`Quantizer::quantize`].
+    pub(crate) fn add_clipped(&mut self, samples: u64) {
+        self.clipped += samples;
+    }
+
+    /// Forgets the noise shaping history, e.g. at a stream restart.
+    pub fn reset(&mut self) {
+        self.channel = 0;
+        self.errors.fill([0.0; 2]);
+    }
+
+    /// Returns triangular noise in -1.0..1.0 LSB.
+    fn tpdf(&mut self) -> f64 {
+        self.uniform() - self.uniform()
+    }
+
+    /// Returns uniform noise in 0.0..1.0 from a xorshift generator.
+    fn uniform(&mut self) -> f64 {
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 17;
+        self.rng ^= self.rng << 5;
+        f64::from(self.rng) / 4_294_967_296.0
+    }
+}
+
+/// Gain of the centre and surround channels in an ITU-R BS.775 downmix (-3 dB).
+const ITU_DOWNMIX_GAIN: f32 = std::f32::consts::FRAC_1_SQRT_2;
+
+/// Remixes interleaved audio from one channel count to another.
+///
+/// Each output channel is a weighted sum of the input channels. The weights
+/// form a matrix with one row per output channel, so `matrix[out * inputs +
+/// in]` is the gain from input channel `in` to output channel `out`.
+///
+/// Multichannel layouts follow the WAVE order: L, R, C, LFE, Ls, Rs for 5.1
+/// and L, R, C, LFE, Lb, Rb, Ls, Rs for 7.1.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChannelMixer {
+    input_channels: usize,
+    output_channels: usize,
+    matrix: Vec<f32>,
+}
+
+impl ChannelMixer {
+    /// Creates a mixer with the standard matrix for the two channel counts.
+    ///
+    /// * mono to stereo (or more) copies the signal to L and R
+    /// * stereo to mono averages L and R
+    /// * 5.1 and 7.1 to stereo use the ITU-R BS.775 downmix: centre and
+    ///   surrounds at -3 dB, LFE dropped; to mono they fold that downmix
+    /// * stereo to more channels feeds L and R only
+    ///
+    /// Other combinations keep the channels both layouts share and leave
+    /// the rest silent. The ITU downmix is not normalised, so loud
+    /// multichannel material can exceed full scale.
+    pub fn new(input_channels: u16, output_channels: u16) -> Self {
+        let (inputs, outputs) = (
+            (input_channels as usize).max(1),
+            (output_channels as usize).max(1),
+        );
+        let mut matrix = vec![0.0; inputs * outputs];
+        let mut set = |out: usize, input: usize, gain: f32| matrix[out * inputs + input] = gain;
+
+        match (inputs, outputs) {
+            (1, _) => {
+                set(0, 0, 1.0);
+                if outputs > 1 {
+                    set(1, 0, 1.0);
+                }
+            }
+            (2, 1) => {
+                set(0, 0, 0.5);
+                set(0, 1, 0.5);
+            }
+            (6 | 8, 1 | 2) => {
+                let (left, right) = Self::itu_downmix(inputs);
+                for (input, (&l, &r)) in left.iter().zip(&right).enumerate() {
+                    if outputs == 1 {
+                        set(0, input, 0.5 * (l + r));
+                    } else {
+                        set(0, input, l);
+                        set(1, input, r);
+                    }
+                }
+            }
+            (_, 1) => {
+                for input in 0..inputs {
+                    set(0, input, 1.0 / inputs as f32);
+                }
+            }
+            _ => {
+                for channel in 0..inputs.min(outputs) {
+                    set(channel, channel, 1.0);
+                }
+            }
+        }
+
+        Self {
+            input_channels: inputs,
+            output_channels: outputs,
+            matrix,
         }
     }
 
-    /// Resamples audio from input rate to output rate.
+    /// Creates a mixer from a custom matrix.
     ///
-    /// This is a simple linear interpolation implementation.
-    /// In production, use rubato library for high-quality resampling.
-    pub fn process(&self, input: &[f32]) -> Result<Vec<f32>, Error> {
-        if self.input_rate == self.output_rate {
-            return Ok(input.to_vec());
+    /// `matrix` holds one row of `input_channels` gains per output channel.
+    pub fn from_matrix(
+        input_channels: u16,
+        output_channels: u16,
+        matrix: Vec<f32>,
+    ) -> Result<Self, Error> {
+        let (inputs, outputs) = (input_channels as usize, output_channels as usize);
+        if inputs == 0 || outputs == 0 || matrix.len() != inputs * outputs {
+            return Err(Error::AudioError(format!(
+                "Mix matrix for {} to {} channels needs {} gains, got {}",
+                inputs,
+                outputs,
+                inputs * outputs,
+                matrix.len()
+            )));
+        }
+        Ok(Self {
+            input_channels: inputs,
+            output_channels: outputs,
+            matrix,
+        })
+    }
+
+    /// Creates a mixer that reorders (or duplicates) channels.
+    ///
+    /// Output channel `i` carries input channel `map[i]`, so `[1, 0]` swaps
+    /// the channels of a stereo stream.
+    pub fn from_channel_map(input_channels: u16, map: &[usize]) -> Result<Self, Error> {
+        let inputs = input_channels as usize;
+        if let Some(&bad) = map.iter().find(|&&input| input >= inputs) {
+            return Err(Error::AudioError(format!(
+                "Channel map refers to channel {} of a {} channel input",
+                bad, inputs
+            )));
+        }
+        let mut matrix = vec![0.0; inputs * map.len()];
+        for (out, &input) in map.iter().enumerate() {
+            matrix[out * inputs + input] = 1.0;
         }
+        Self::from_matrix(input_channels, map.len() as u16, matrix)
+    }
+
+    /// Returns the L and R rows of the ITU-R BS.775 downmix of 5.1 or 7.1.
+    fn itu_downmix(inputs: usize) -> (Vec<f32>, Vec<f32>) {
+        let h = ITU_DOWNMIX_GAIN;
+        if inputs == 6 {
+            (
+                vec![1.0, 0.0, h, 0.0, h, 0.0],
+                vec![0.0, 1.0, h, 0.0, 0.0, h],
+            )
+        } else {
+            (
+                vec![1.0, 0.0, h, 0.0, h, 0.0, h, 0.0],
+                vec![0.0, 1.0, h, 0.0, 0.0, h, 0.0, h],
+            )
+        }
+    }
 
-        let ratio = self.output_rate as f64 / self.input_rate as f64;
-        let output_len = ((input.len() as f64) * ratio) as usize;
-        let mut output = Vec::with_capacity(output_len);
+    /// Returns the number of input channels.
+    pub fn input_channels(&self) -> usize {
+        self.input_channels
+    }
+
+    /// Returns the number of output channels.
+    pub fn output_channels(&self) -> usize {
+        self.output_channels
+    }
+
+    /// Returns the mix matrix, one row per output channel.
+    pub fn matrix(&self) -> &[f32] {
+        &self.matrix
+    }
+
+    /// Returns the gain from input channel `input` to output channel `output`.
+    pub fn gain(&self, output: usize, input: usize) -> f32 {
+        self.matrix[output * self.input_channels + input]
+    }
 
-        for i in 0..output_len {
-            let src_idx = (i as f64 / ratio) as usize;
-            let frac = (i as f64 / ratio) - src_idx as f64;
+    /// Returns the output length, in samples, for `input_len` input samples.
+    pub fn output_len(&self, input_len: usize) -> usize {
+        input_len / self.input_channels * self.output_channels
+    }
 
-            if src_idx + 1 < input.len() {
-                // Linear interpolation
-                let y0 = input[src_idx];
-                let y1 = input[src_idx + 1];
-                let sample = y0 + (y1 - y0) * (frac as f32);
-                output.push(sample);
-            } else if src_idx < input.len() {
-                output.push(input[src_idx]);
+    /// Mixes interleaved `input` into `output`.
+    ///
+    /// Returns the number of frames mixed, limited by whichever slice holds
+    /// fewer whole frames.
+    pub fn process(&self, input: &[f32], output: &mut [f32]) -> usize {
+        let frames = input.chunks_exact(self.input_channels);
+        let out_frames = output.chunks_exact_mut(self.output_channels);
+        let mut mixed = 0;
+        for (in_frame, out_frame) in frames.zip(out_frames) {
+            for (out, row) in out_frame
+                .iter_mut()
+                .zip(self.matrix.chunks_exact(self.input_channels))
+            {
+                *out = row.iter().zip(in_frame).map(|(gain, s)| gain * s).sum();
             }
+            mixed += 1;
         }
+        mixed
+    }
+}
 
-        Ok(output)
+impl ProcessingStage for ChannelMixer {
+    fn process_stage(
+        &mut self,
+        input: &[f32],
+        output: &mut [f32],
+    ) -> Result<(usize, usize), Error> {
+        let frames = self.process(input, output);
+        Ok((frames * self.input_channels, frames * self.output_channels))
+    }
+
+    fn input_channels(&self) -> usize {
+        self.input_channels
+    }
+
+    fn output_channels(&self) -> usize {
+        self.output_channels
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// Default duration of a gain ramp.
+const DEFAULT_GAIN_RAMP_MS: f32 = 20.0;
 
-    #[test]
-    fn test_audio_processor_passthrough() {
-        let processor = AudioProcessor::new(48000, 48000, 2, AudioFormat::F32LE);
-        let input = vec![1.0, -1.0, 0.5, -0.5];
-        let mut output = vec![0.0; 4];
+/// Converts a level in decibels to a linear gain factor.
+pub(crate) fn db_to_gain(db: f32) -> f32 {
+    10f32.powf(db / 20.0)
+}
 
-        let result = processor.process(&input, &mut output).unwrap();
-        assert_eq!(result, 4);
-        assert_eq!(output, input);
+/// Gain parameters shared between a [`GainStage`] and control threads.
+///
+/// All setters are lock-free and can be called from any thread while the
+/// stage is running; the stage picks the new values up at its next block
+/// and ramps towards them.
+#[derive(Debug)]
+pub struct GainControl {
+    gains_db: Vec<AtomicF32>,
+    inverted: Vec<AtomicBool>,
+    muted: AtomicBool,
+}
+
+impl GainControl {
+    /// Creates parameters for `channels` channels at 0 dB.
+    pub fn new(channels: usize) -> Self {
+        let channels = channels.max(1);
+        Self {
+            gains_db: (0..channels).map(|_| AtomicF32::new(0.0)).collect(),
+            inverted: (0..channels).map(|_| AtomicBool::new(false)).collect(),
+            muted: AtomicBool::new(false),
+        }
     }
 
-    #[test]
-    fn test_format_conversion_all() {
-        let processor = AudioProcessor::new(48000, 48000, 2, AudioFormat::F32LE);
-        let input = vec![1.0, 0.0, -1.0, 0.5];
+    /// Returns the number of channels.
+    pub fn channels(&self) -> usize {
+        self.gains_db.len()
+    }
 
-        // S16
-        let bytes = processor.convert_format(&input, AudioFormat::S16LE);
-        assert_eq!(bytes.len(), 8);
-        assert_eq!(i16::from_le_bytes([bytes[0], bytes[1]]), 32767);
+    /// Sets the gain of every channel, in dB.
+    ///
+    /// Returns false, leaving the gains unchanged, if `gain_db` is not
+    /// finite.
+    pub fn set_gain_db(&self, gain_db: f32) -> bool {
+        if !gain_db.is_finite() {
+            return false;
+        }
+        for gain in &self.gains_db {
+            gain.store(gain_db, Ordering::Relaxed);
+        }
+        true
+    }
 
-        // S32
-        let bytes = processor.convert_format(&input, AudioFormat::S32LE);
-        assert_eq!(bytes.len(), 16);
-        assert_eq!(
-            i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
-            2147483647
-        );
+    /// Sets the gain of one channel, in dB.
+    ///
+    /// Returns false, leaving the gains unchanged, if the channel is out of
+    /// range or `gain_db` is not finite.
+    pub fn set_channel_gain_db(&self, channel: usize, gain_db: f32) -> bool {
+        let Some(gain) = self.gains_db.get(channel).filter(|_| gain_db.is_finite()) else {
+            return false;
+        };
+        gain.store(gain_db, Ordering::Relaxed);
+        true
+    }
 
-        // F32
-        let bytes = processor.convert_format(&input, AudioFormat::F32LE);
-        assert_eq!(bytes.len(), 16);
-        let val: f32 = unsafe { *(bytes.as_ptr() as *const f32) };
-        assert_eq!(val, 1.0);
+    /// Returns the gain of a channel in dB, or `None` if out of range.
+    pub fn channel_gain_db(&self, channel: usize) -> Option<f32> {
+        Some(self.gains_db.get(channel)?.load(Ordering::Relaxed))
     }
 
-    #[test]
-    fn test_bytes_to_samples_s32() {
-        let processor = AudioProcessor::new(48000, 48000, 2, AudioFormat::F32LE);
-        let mut bytes = Vec::new();
-        bytes.extend_from_slice(&2147483647i32.to_le_bytes());
+    /// Mutes or unmutes all channels, keeping their gains.
+    pub fn set_muted(&self, muted: bool) {
+        self.muted.store(muted, Ordering::Relaxed);
+    }
 
-        let samples = processor.bytes_to_samples(&bytes, AudioFormat::S32LE);
-        assert_eq!(samples.len(), 1);
-        assert!((samples[0] - 1.0).abs() < 0.00001);
+    /// Returns true if the output is muted.
+    pub fn is_muted(&self) -> bool {
+        self.muted.load(Ordering::Relaxed)
+    }
+
+    /// Inverts the polarity of one channel. Out-of-range channels are
+    /// ignored.
+    pub fn set_inverted(&self, channel: usize, inverted: bool) {
+        if let Some(flag) = self.inverted.get(channel) {
+            flag.store(inverted, Ordering::Relaxed);
+        }
+    }
+
+    /// Returns true if the polarity of a channel is inverted.
+    pub fn is_inverted(&self, channel: usize) -> bool {
+        self.inverted
+            .get(channel)
+            .is_some_and(|flag| flag.load(Ordering::Relaxed))
+    }
+
+    /// Returns the linear factor of a channel with mute and polarity applied.
+    fn factor(&self, channel: usize) -> f32 {
+        if self.is_muted() {
+            return 0.0;
+        }
+        let gain = db_to_gain(self.gains_db[channel].load(Ordering::Relaxed));
+        if self.is_inverted(channel) {
+            -gain
+        } else {
+            gain
+        }
+    }
+}
+
+/// Applies per-channel gain, mute and polarity to interleaved audio.
+///
+/// Parameter changes made through the shared [`GainControl`] are ramped
+/// linearly over a short time, so they never click. A polarity flip ramps
+/// through silence.
+pub struct GainStage {
+    control: Arc<GainControl>,
+    ramp_frames: usize,
+    /// Factor applied to the last frame, per channel
+    current: Vec<f32>,
+    /// Factor being ramped towards, per channel
+    target: Vec<f32>,
+    /// Change of the factor per frame during a ramp, per channel
+    step: Vec<f32>,
+    /// Frames left in the current ramp
+    remaining: usize,
+}
+
+impl GainStage {
+    /// Creates a stage at 0 dB for `channels` channels.
+    pub fn new(channels: usize, sample_rate: u32) -> Self {
+        Self::with_control(Arc::new(GainControl::new(channels)), sample_rate)
+    }
+
+    /// Creates a stage driven by existing parameters, starting at their
+    /// current values.
+    pub fn with_control(control: Arc<GainControl>, sample_rate: u32) -> Self {
+        let factors: Vec<f32> = (0..control.channels()).map(|c| control.factor(c)).collect();
+        Self {
+            ramp_frames: 1,
+            current: factors.clone(),
+            step: vec![0.0; factors.len()],
+            target: factors,
+            remaining: 0,
+            control,
+        }
+        .with_ramp_ms(DEFAULT_GAIN_RAMP_MS, sample_rate)
+    }
+
+    /// Sets the duration of the ramp towards new parameter values.
+    pub fn with_ramp_ms(mut self, ramp_ms: f32, sample_rate: u32) -> Self {
+        self.ramp_frames = ((ramp_ms.max(0.0) * sample_rate as f32 / 1000.0) as usize).max(1);
+        self
+    }
+
+    /// Returns the shared parameters, to be handed to control threads.
+    pub fn control(&self) -> &Arc<GainControl> {
+        &self.control
+    }
+
+    /// Returns the number of interleaved channels per frame.
+    pub fn channels(&self) -> usize {
+        self.current.len()
+    }
+
+    /// Applies the gain to interleaved samples in place.
+    pub fn process(&mut self, block: &mut [f32]) {
+        let channels = self.channels();
+        let mut changed = false;
+        for (channel, target) in self.target.iter_mut().enumerate() {
+            let factor = self.control.factor(channel);
+            changed |= factor != *target;
+            *target = factor;
+        }
+        if changed {
+            for ((step, target), current) in
+                self.step.iter_mut().zip(&self.target).zip(&self.current)
+            {
+                *step = (target - current) / self.ramp_frames as f32;
+            }
+            self.remaining = self.ramp_frames;
+        }
+
+        let ramp_len = (self.remaining * channels).min(block.len());
+        let (ramp, steady) = block.split_at_mut(ramp_len - ramp_len % channels);
+        for frame in ramp.chunks_exact_mut(channels) {
+            self.remaining -= 1;
+            for ((sample, current), (step, target)) in frame
+                .iter_mut()
+                .zip(&mut self.current)
+                .zip(self.step.iter().zip(&self.target))
+            {
+                // Land exactly on the target at the end of the ramp
+                *current = if self.remaining == 0 {
+                    *target
+                } else {
+                    *current + step
+                };
+                *sample *= *current;
+            }
+        }
+
+        if self.current.iter().all(|&factor| factor == self.current[0]) {
+            if self.current[0] != 1.0 {
+                simd::apply_gain(steady, self.current[0]);
+            }
+        } else {
+            for frame in steady.chunks_exact_mut(channels) {
+                for (sample, factor) in frame.iter_mut().zip(&self.current) {
+                    *sample *= factor;
+                }
+            }
+        }
+    }
+
+    /// Jumps to the current parameter values without a ramp.
+    pub fn reset(&mut self) {
+        for (channel, (current, target)) in
+            self.current.iter_mut().zip(&mut self.target).enumerate()
+        {
+            *target = self.control.factor(channel);
+            *current = *target;
+        }
+        self.remaining = 0;
+    }
+}
+
+impl AudioEffect for GainStage {
+    fn process(&mut self, block: &mut [f32]) {
+        GainStage::process(self, block);
+    }
+
+    fn reset(&mut self) {
+        GainStage::reset(self);
+    }
+}
+
+impl ProcessingStage for GainStage {
+    fn process_stage(
+        &mut self,
+        input: &[f32],
+        output: &mut [f32],
+    ) -> Result<(usize, usize), Error> {
+        let len = input.len().min(output.len());
+        let len = len - len % self.channels();
+        output[..len].copy_from_slice(&input[..len]);
+        self.process(&mut output[..len]);
+        Ok((len, len))
+    }
+
+    fn input_channels(&self) -> usize {
+        self.channels()
+    }
+}
+
+/// A stage that converts interleaved audio between two ring buffers.
+///
+/// [`crate::TripleRingBuffer`] runs its stage between the resample and the
+/// output buffer. Stages are called on the real-time path, so they must not
+/// allocate.
+pub trait ProcessingStage: Any + Send {
+    /// Processes interleaved samples from `input` into `output`.
+    ///
+    /// Both slices hold whole frames. Returns the number of samples consumed
+    /// from `input` and produced into `output`, again in whole frames.
+    /// Unconsumed input is offered again on the next call.
+    fn process_stage(&mut self, input: &[f32], output: &mut [f32])
+        -> Result<(usize, usize), Error>;
+
+    /// Returns the number of interleaved channels per input frame.
+    fn input_channels(&self) -> usize;
+
+    /// Returns the number of interleaved channels per output frame, by
+    /// default the same as the input.
+    fn output_channels(&self) -> usize {
+        self.input_channels()
+    }
+}
+
+/// Extra fractional bits of the phase, so ratio corrections resolve to well
+/// under one ppm even when the rates are equal.
+const PHASE_FRACTION_BITS: u32 = 24;
+
+/// Streaming linear-interpolation resampler over interleaved frames.
+///
+/// Keeps the fractional read position and the previous frame between calls,
+/// so consecutive blocks join without a discontinuity. The phase is an exact
+/// fraction of the reduced `input_rate / output_rate` ratio, so over any
+/// number of calls the output length follows the rate ratio with no
+/// accumulated rounding error. The last input frame of a call is held back
+/// until the next call provides the frame after it.
+pub struct StreamingResampler {
+    channels: usize,
+    /// Phase units per input frame
+    unit: u64,
+    /// Phase units advanced per output frame
+    step: u64,
+    /// Read position relative to `prev`, in phase units
+    position: u64,
+    prev: Vec<f32>,
+    primed: bool,
+}
+
+impl StreamingResampler {
+    /// Creates a new streaming resampler.
+    pub fn new(input_rate: u32, output_rate: u32, channels: u16) -> Self {
+        let (input_rate, output_rate) = (input_rate.max(1) as u64, output_rate.max(1) as u64);
+        let divisor = gcd(input_rate, output_rate);
+        let channels = (channels as usize).max(1);
+        Self {
+            channels,
+            unit: (output_rate / divisor) << PHASE_FRACTION_BITS,
+            step: (input_rate / divisor) << PHASE_FRACTION_BITS,
+            position: 0,
+            prev: vec![0.0; channels],
+            primed: false,
+        }
+    }
+
+    /// Returns the number of interleaved channels.
+    pub fn channels(&self) -> usize {
+        self.channels
+    }
+
+    /// Returns the current ratio (output frames per input frame).
+    pub fn ratio(&self) -> f64 {
+        self.unit as f64 / self.step as f64
+    }
+
+    /// Returns an output length, in samples, large enough for one call with
+    /// `input_len` input samples.
+    pub fn max_output_len(&self, input_len: usize) -> usize {
+        let frames = (input_len / self.channels + 1) as u64;
+        let out_frames = (frames * self.unit).div_ceil(self.step) + 1;
+        out_frames as usize * self.channels
+    }
+
+    /// Resamples interleaved `input` into `output`.
+    ///
+    /// Returns (samples consumed, samples produced), both in whole frames.
+    /// Input that is not consumed because `output` is full must be offered
+    /// again on the next call.
+    pub fn process(&mut self, input: &[f32], output: &mut [f32]) -> (usize, usize) {
+        let ch = self.channels;
+        let mut input = &input[..input.len() - input.len() % ch];
+        let mut consumed = 0;
+
+        if !self.primed {
+            if input.is_empty() {
+                return (0, 0);
+            }
+            self.prev.copy_from_slice(&input[..ch]);
+            self.primed = true;
+            input = &input[ch..];
+            consumed = ch;
+        }
+
+        // Frame 0 is `prev`, frames 1..=n come from `input`
+        let in_frames = input.len() / ch;
+        let frame = |i: usize| -> &[f32] {
+            if i == 0 {
+                &self.prev
+            } else {
+                &input[(i - 1) * ch..i * ch]
+            }
+        };
+
+        let mut produced = 0;
+        let mut position = self.position;
+        for out in output.chunks_exact_mut(ch) {
+            let index = (position / self.unit) as usize;
+            if index + 1 > in_frames {
+                break;
+            }
+            let frac = ((position % self.unit) as f64 / self.unit as f64) as f32;
+            let (a, b) = (frame(index), frame(index + 1));
+            for ((o, &y0), &y1) in out.iter_mut().zip(a).zip(b) {
+                *o = y0 + (y1 - y0) * frac;
+            }
+            produced += ch;
+            position += self.step;
+        }
+
+        let passed = ((position / self.unit) as usize).min(in_frames);
+        if passed > 0 {
+            self.prev
+                .copy_from_slice(&input[(passed - 1) * ch..passed * ch]);
+        }
+        self.position = position - passed as u64 * self.unit;
+
+        (consumed + passed * ch, produced)
+    }
+
+    /// Ends the stream, writing the output still owed for the held-back
+    /// last frame into `output`, and resets the resampler.
+    ///
+    /// With no following frame to interpolate towards, the last frame is
+    /// held. Returns the number of samples written; [`Self::max_output_len`]
+    /// with an empty input is always large enough.
+    pub fn flush(&mut self, output: &mut [f32]) -> usize {
+        let mut produced = 0;
+        if self.primed {
+            for out in output.chunks_exact_mut(self.channels) {
+                if self.position >= self.unit {
+                    break;
+                }
+                out.copy_from_slice(&self.prev);
+                produced += self.channels;
+                self.position += self.step;
+            }
+        }
+        self.reset();
+        produced
+    }
+
+    /// Clears the phase and the held-back frame.
+    pub fn reset(&mut self) {
+        self.position = 0;
+        self.primed = false;
+    }
+
+    /// Scales the nominal step, e.g. by `1.0 + correction` for drift control.
+    fn set_step_scale(&mut self, nominal_step: u64, scale: f64) {
+        self.step = ((nominal_step as f64 * scale).round() as u64).max(1);
+    }
+}
+
+impl ProcessingStage for StreamingResampler {
+    fn process_stage(
+        &mut self,
+        input: &[f32],
+        output: &mut [f32],
+    ) -> Result<(usize, usize), Error> {
+        Ok(self.process(input, output))
+    }
+
+    fn input_channels(&self) -> usize {
+        self.channels()
+    }
+}
+
+fn gcd(mut a: u64, mut b: u64) -> u64 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+/// Largest change of the applied correction per output frame.
+///
+/// A full 2000 ppm swing takes about 0.4 s at 48 kHz, slow enough that the
+/// pitch change is inaudible.
+const CORRECTION_SLEW_PER_FRAME: f64 = 1e-7;
+
+/// Output frames produced between two correction updates.
+const SLEW_BLOCK_FRAMES: usize = 32;
+
+/// Resampler whose conversion ratio can be adjusted while it runs.
+///
+/// Used to compensate for clock drift: the nominal ratio comes from the
+/// configured rates, and a small correction (a few hundred ppm) speeds up or
+/// slows down how fast input is consumed. A new correction is approached
+/// gradually while processing, so changing it never causes a click.
+pub struct VariableResampler {
+    nominal_step: u64,
+    correction: f64,
+    target_correction: f64,
+    resampler: StreamingResampler,
+}
+
+impl VariableResampler {
+    /// Creates a new variable-ratio resampler.
+    pub fn new(input_rate: u32, output_rate: u32, channels: u16) -> Self {
+        let resampler = StreamingResampler::new(input_rate, output_rate, channels);
+        Self {
+            nominal_step: resampler.step,
+            correction: 0.0,
+            target_correction: 0.0,
+            resampler,
+        }
+    }
+
+    /// Sets the relative speed correction to move towards.
+    ///
+    /// A positive value consumes input faster (e.g. `100e-6` consumes 100 ppm
+    /// more input per output frame than the nominal ratio). The applied
+    /// correction follows at a bounded rate as output is produced.
+    pub fn set_correction(&mut self, correction: f64) {
+        self.target_correction = correction;
+    }
+
+    /// Returns the relative speed correction currently applied.
+    pub fn correction(&self) -> f64 {
+        self.correction
+    }
+
+    /// Returns the relative speed correction being moved towards.
+    pub fn target_correction(&self) -> f64 {
+        self.target_correction
+    }
+
+    /// Returns the effective ratio (output frames per input frame).
+    pub fn ratio(&self) -> f64 {
+        self.resampler.ratio()
+    }
+
+    /// Clears the streaming state.
+    pub fn reset(&mut self) {
+        self.resampler.reset();
+    }
+
+    /// Moves the applied correction towards the target for `frames` frames.
+    fn slew(&mut self, frames: usize) {
+        let difference = self.target_correction - self.correction;
+        if difference == 0.0 {
+            return;
+        }
+        let limit = CORRECTION_SLEW_PER_FRAME * frames as f64;
+        self.correction += difference.clamp(-limit, limit);
+        self.resampler
+            .set_step_scale(self.nominal_step, 1.0 + self.correction);
+    }
+}
+
+impl ProcessingStage for VariableResampler {
+    fn process_stage(
+        &mut self,
+        input: &[f32],
+        output: &mut [f32],
+    ) -> Result<(usize, usize), Error> {
+        let block = SLEW_BLOCK_FRAMES * self.resampler.channels();
+        let whole = output.len() - output.len() % self.resampler.channels();
+        let (mut consumed, mut produced) = (0, 0);
+        while produced < whole {
+            let end = (produced + block).min(whole);
+            self.slew((end - produced) / self.resampler.channels());
+            let (used, made) = self
+                .resampler
+                .process(&input[consumed..], &mut output[produced..end]);
+            consumed += used;
+            produced += made;
+            if produced < end {
+                break;
+            }
+        }
+        Ok((consumed, produced))
+    }
+
+    fn input_channels(&self) -> usize {
+        self.resampler.channels()
+    }
+}
+
+/// Conversion engine behind a [`Resampler`].
+enum ResamplerBackend {
+    Linear(StreamingResampler),
+    #[cfg(feature = "rubato")]
+    Rubato(Box<RubatoStage>),
+}
+
+/// Resampler for sample rate conversion.
+///
+/// Consecutive calls to [`Resampler::process`] continue the same stream.
+/// The default is the built-in [`StreamingResampler`]; with the `rubato`
+/// feature, [`Resampler::with_quality`] selects a higher-quality engine.
+pub struct Resampler {
+    input_rate: u32,
+    output_rate: u32,
+    channels: u16,
+    quality: ResampleQuality,
+    backend: ResamplerBackend,
+}
+
+impl Resampler {
+    /// Creates a new linear-interpolation resampler.
+    pub fn new(input_rate: u32, output_rate: u32, channels: u16) -> Self {
+        Self {
+            input_rate,
+            output_rate,
+            channels,
+            quality: ResampleQuality::Linear,
+            backend: ResamplerBackend::Linear(StreamingResampler::new(
+                input_rate,
+                output_rate,
+                channels,
+            )),
+        }
+    }
+
+    /// Creates a resampler with the given quality preset.
+    ///
+    /// Falls back to [`ResampleQuality::Linear`], with a warning, if the
+    /// `rubato` feature is disabled or the engine cannot be built for these
+    /// rates. Equal rates need no conversion and always use the built-in
+    /// engine. Check [`Resampler::quality`] for the preset in use.
+    pub fn with_quality(
+        input_rate: u32,
+        output_rate: u32,
+        channels: u16,
+        quality: ResampleQuality,
+    ) -> Self {
+        if quality == ResampleQuality::Linear || input_rate == output_rate {
+            return Self::new(input_rate, output_rate, channels);
+        }
+
+        #[cfg(feature = "rubato")]
+        match RubatoStage::new(input_rate, output_rate, channels as usize, quality) {
+            Ok(stage) => {
+                return Self {
+                    input_rate,
+                    output_rate,
+                    channels,
+                    quality,
+                    backend: ResamplerBackend::Rubato(Box::new(stage)),
+                }
+            }
+            Err(e) => log::warn!("{:?} resampling unavailable, using linear: {}", quality, e),
+        }
+
+        #[cfg(not(feature = "rubato"))]
+        log::warn!(
+            "{:?} resampling needs the `rubato` feature, using linear",
+            quality
+        );
+
+        Self::new(input_rate, output_rate, channels)
+    }
+
+    /// Returns the number of interleaved channels.
+    pub fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    /// Returns the quality preset in use.
+    pub fn quality(&self) -> ResampleQuality {
+        self.quality
+    }
+
+    /// Returns an output length, in samples, large enough for one call with
+    /// `input_len` input samples.
+    pub fn max_output_len(&self, input_len: usize) -> usize {
+        match &self.backend {
+            ResamplerBackend::Linear(resampler) => resampler.max_output_len(input_len),
+            #[cfg(feature = "rubato")]
+            ResamplerBackend::Rubato(stage) => stage.max_output_len(input_len),
+        }
+    }
+
+    /// Clears the streaming state, starting a new stream.
+    pub fn reset(&mut self) {
+        match &mut self.backend {
+            ResamplerBackend::Linear(resampler) => resampler.reset(),
+            #[cfg(feature = "rubato")]
+            ResamplerBackend::Rubato(stage) => stage.reset(),
+        }
+    }
+
+    /// Resamples the next block of the stream from input rate to output rate.
+    ///
+    /// The phase carries over from the previous call, and input the engine
+    /// cannot convert yet (the last frame for linear interpolation, a partial
+    /// chunk for rubato) is held back until the next call, or until
+    /// [`Resampler::flush`] ends the stream.
+    pub fn process(&mut self, input: &[f32]) -> Result<Vec<f32>, Error> {
+        if self.input_rate == self.output_rate {
+            return Ok(input.to_vec());
+        }
+
+        let mut output = vec![0.0; self.max_output_len(input.len())];
+        let (_, produced) = self.process_stage(input, &mut output)?;
+        output.truncate(produced);
+        Ok(output)
+    }
+
+    /// Ends the stream, returning the output still owed for input held back
+    /// by [`Resampler::process`], and resets the resampler.
+    ///
+    /// Appended to the output of every `process` call, this makes the total
+    /// length follow the rate ratio.
+    pub fn flush(&mut self) -> Result<Vec<f32>, Error> {
+        if self.input_rate == self.output_rate {
+            return Ok(Vec::new());
+        }
+
+        let (mut output, produced) = match &mut self.backend {
+            ResamplerBackend::Linear(resampler) => {
+                let mut output = vec![0.0; resampler.max_output_len(0)];
+                let produced = resampler.flush(&mut output);
+                (output, produced)
+            }
+            #[cfg(feature = "rubato")]
+            ResamplerBackend::Rubato(stage) => {
+                let mut output = vec![0.0; stage.flush_len()];
+                let produced = stage.flush(&mut output)?;
+                (output, produced)
+            }
+        };
+        output.truncate(produced);
+        Ok(output)
+    }
+}
+
+impl ProcessingStage for Resampler {
+    fn process_stage(
+        &mut self,
+        input: &[f32],
+        output: &mut [f32],
+    ) -> Result<(usize, usize), Error> {
+        match &mut self.backend {
+            ResamplerBackend::Linear(resampler) => Ok(resampler.process(input, output)),
+            #[cfg(feature = "rubato")]
+            ResamplerBackend::Rubato(stage) => stage.process(input, output),
+        }
+    }
+
+    fn input_channels(&self) -> usize {
+        (self.channels as usize).max(1)
+    }
+}
+
+/// Input frames per rubato processing chunk.
+#[cfg(feature = "rubato")]
+const RUBATO_CHUNK_FRAMES: usize = 256;
+
+/// Streams interleaved audio through a rubato resampler.
+///
+/// Rubato works on fixed chunks of de-interleaved frames, so input is
+/// gathered into a chunk and converted output is kept until the caller has
+/// room for it. All buffers are allocated up front.
+#[cfg(feature = "rubato")]
+struct RubatoStage {
+    engine: RubatoEngine,
+    channels: usize,
+    /// Output frames per input frame
+    ratio: f64,
+    output_max: usize,
+    input: Vec<Vec<f32>>,
+    filled: usize,
+    output: Vec<Vec<f32>>,
+    output_pos: usize,
+    output_len: usize,
+    /// Input frames converted since the last reset
+    frames_in: u64,
+    /// Output frames converted since the last reset
+    frames_out: u64,
+}
+
+/// The rubato resamplers used by the presets.
+#[cfg(feature = "rubato")]
+enum RubatoEngine {
+    Fast(rubato::FastFixedIn<f32>),
+    Sinc(rubato::SincFixedIn<f32>),
+}
+
+#[cfg(feature = "rubato")]
+impl RubatoEngine {
+    fn resampler(&mut self) -> &mut dyn rubato::VecResampler<f32> {
+        match self {
+            RubatoEngine::Fast(resampler) => resampler,
+            RubatoEngine::Sinc(resampler) => resampler,
+        }
+    }
+
+    fn reset(&mut self) {
+        match self {
+            RubatoEngine::Fast(resampler) => rubato::Resampler::reset(resampler),
+            RubatoEngine::Sinc(resampler) => rubato::Resampler::reset(resampler),
+        }
+    }
+}
+
+#[cfg(feature = "rubato")]
+impl RubatoStage {
+    fn new(
+        input_rate: u32,
+        output_rate: u32,
+        channels: usize,
+        quality: ResampleQuality,
+    ) -> Result<Self, Error> {
+        use rubato::{
+            FastFixedIn, PolynomialDegree, SincFixedIn, SincInterpolationParameters,
+            SincInterpolationType, WindowFunction,
+        };
+
+        let ratio = output_rate as f64 / input_rate as f64;
+        let channels = channels.max(1);
+        let sinc =
+            |sinc_len, oversampling_factor, interpolation, window| SincInterpolationParameters {
+                sinc_len,
+                f_cutoff: rubato::calculate_cutoff(sinc_len, window),
+                oversampling_factor,
+                interpolation,
+                window,
+            };
+
+        let mut engine = match quality {
+            ResampleQuality::Fast => RubatoEngine::Fast(FastFixedIn::new(
+                ratio,
+                1.0,
+                PolynomialDegree::Cubic,
+                RUBATO_CHUNK_FRAMES,
+                channels,
+            )?),
+            ResampleQuality::Medium => RubatoEngine::Sinc(SincFixedIn::new(
+                ratio,
+                1.0,
+                sinc(
+                    64,
+                    128,
+                    SincInterpolationType::Linear,
+                    WindowFunction::Blackman2,
+                ),
+                RUBATO_CHUNK_FRAMES,
+                channels,
+            )?),
+            ResampleQuality::Best => RubatoEngine::Sinc(SincFixedIn::new(
+                ratio,
+                1.0,
+                sinc(
+                    256,
+                    256,
+                    SincInterpolationType::Cubic,
+                    WindowFunction::BlackmanHarris2,
+                ),
+                RUBATO_CHUNK_FRAMES,
+                channels,
+            )?),
+            ResampleQuality::Linear => {
+                return Err(Error::AudioError(
+                    "linear resampling is built in".to_string(),
+                ))
+            }
+        };
+
+        let output_max = engine.resampler().output_frames_max();
+        Ok(Self {
+            engine,
+            channels,
+            ratio,
+            output_max,
+            input: vec![vec![0.0; RUBATO_CHUNK_FRAMES]; channels],
+            filled: 0,
+            output: vec![vec![0.0; output_max]; channels],
+            output_pos: 0,
+            output_len: 0,
+            frames_in: 0,
+            frames_out: 0,
+        })
+    }
+
+    /// Returns (samples consumed, samples produced).
+    fn process(&mut self, input: &[f32], output: &mut [f32]) -> Result<(usize, usize), Error> {
+        let ch = self.channels;
+        let (mut consumed, mut produced) = (0, 0);
+
+        loop {
+            // Hand out converted frames first
+            produced += self.drain(&mut output[produced..]);
+            if self.output_pos < self.output_len {
+                break;
+            }
+
+            // Gather the next chunk
+            let needed = self.engine.resampler().input_frames_next();
+            let frames = (needed - self.filled).min((input.len() - consumed) / ch);
+            for (f, frame) in input[consumed..consumed + frames * ch]
+                .chunks_exact(ch)
+                .enumerate()
+            {
+                for (&sample, channel) in frame.iter().zip(&mut self.input) {
+                    channel[self.filled + f] = sample;
+                }
+            }
+            self.filled += frames;
+            consumed += frames * ch;
+            if self.filled < needed {
+                break;
+            }
+
+            let (_, converted) =
+                self.engine
+                    .resampler()
+                    .process_into_buffer(&self.input, &mut self.output, None)?;
+            self.frames_in += self.filled as u64;
+            self.frames_out += converted as u64;
+            self.filled = 0;
+            self.output_pos = 0;
+            self.output_len = converted;
+        }
+
+        Ok((consumed, produced))
+    }
+
+    /// Copies converted frames that fit into `output`; returns the samples
+    /// written.
+    fn drain(&mut self, output: &mut [f32]) -> usize {
+        let ch = self.channels;
+        let frames = (self.output_len - self.output_pos).min(output.len() / ch);
+        for (f, out) in output[..frames * ch].chunks_exact_mut(ch).enumerate() {
+            for (o, channel) in out.iter_mut().zip(&self.output) {
+                *o = channel[self.output_pos + f];
+            }
+        }
+        self.output_pos += frames;
+        frames * ch
+    }
+
+    /// Writes the pending output, then converts the partial chunk gathered
+    /// so far, padded with silence, until the output since the last reset
+    /// matches the rate ratio. Resets the stage.
+    ///
+    /// `output` must hold [`Self::flush_len`] samples.
+    fn flush(&mut self, output: &mut [f32]) -> Result<usize, Error> {
+        let mut produced = self.drain(output);
+        let total = self.frames_in + self.filled as u64;
+        let mut owed = ((total as f64 * self.ratio).round() as u64).saturating_sub(self.frames_out);
+        while self.filled > 0 || owed > 0 {
+            let needed = self.engine.resampler().input_frames_next();
+            for channel in &mut self.input {
+                channel[self.filled..needed].fill(0.0);
+            }
+            let (_, converted) =
+                self.engine
+                    .resampler()
+                    .process_into_buffer(&self.input, &mut self.output, None)?;
+            if converted == 0 {
+                break;
+            }
+            self.filled = 0;
+            self.output_pos = 0;
+            self.output_len = (converted as u64).min(owed) as usize;
+            owed -= self.output_len as u64;
+            produced += self.drain(&mut output[produced..]);
+        }
+        self.reset();
+        Ok(produced)
+    }
+
+    /// Returns an output length, in samples, large enough for a flush.
+    fn flush_len(&self) -> usize {
+        (self.output_len - self.output_pos + 2 * self.output_max) * self.channels
+    }
+
+    fn max_output_len(&self, input_len: usize) -> usize {
+        let chunks = (self.filled + input_len / self.channels) / RUBATO_CHUNK_FRAMES;
+        let pending = self.output_len - self.output_pos;
+        (pending + chunks * self.output_max) * self.channels
+    }
+
+    fn reset(&mut self) {
+        self.engine.reset();
+        self.filled = 0;
+        self.output_pos = 0;
+        self.output_len = 0;
+        self.frames_in = 0;
+        self.frames_out = 0;
+    }
+}
+
+#[cfg(feature = "rubato")]
+impl From<rubato::ResamplerConstructionError> for Error {
+    fn from(e: rubato::ResamplerConstructionError) -> Self {
+        Error::AudioError(format!("Failed to create resampler: {}", e))
+    }
+}
+
+#[cfg(feature = "rubato")]
+impl From<rubato::ResampleError> for Error {
+    fn from(e: rubato::ResampleError) -> Self {
+        Error::AudioError(format!("Resampling failed: {}", e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_audio_processor_passthrough() {
+        let mut processor = AudioProcessor::new(48000, 48000, 2, AudioFormat::F32LE);
+        let input = vec![1.0, -1.0, 0.5, -0.5];
+        let mut output = vec![0.0; 4];
+
+        let result = processor.process(&input, &mut output).unwrap();
+        assert_eq!(result, 4);
+        assert_eq!(output, input);
+    }
+
+    #[test]
+    fn test_format_conversion_all() {
+        let mut processor = AudioProcessor::new(48000, 48000, 2, AudioFormat::F32LE);
+        let input = vec![1.0, 0.0, -1.0, 0.5];
+
+        // S16
+        let bytes = processor.convert_format(&input, AudioFormat::S16LE);
+        assert_eq!(bytes.len(), 8);
+        assert_eq!(i16::from_le_bytes([bytes[0], bytes[1]]), 32767);
+        assert_eq!(i16::from_le_bytes([bytes[4], bytes[5]]), -32768);
+
+        // S32
+        let bytes = processor.convert_format(&input, AudioFormat::S32LE);
+        assert_eq!(bytes.len(), 16);
+        assert_eq!(
+            i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+            2147483647
+        );
+
+        // F32
+        let bytes = processor.convert_format(&input, AudioFormat::F32LE);
+        assert_eq!(bytes.len(), 16);
+        let val = f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+        assert_eq!(val, 1.0);
+    }
+
+    #[test]
+    fn test_quantizer_rounding_and_clipping() {
+        let mut round = Quantizer::new(DitherMode::Round, 1);
+        assert_eq!(round.quantize(-1.0, 16), -32768);
+        assert_eq!(round.quantize(1.0, 16), 32767);
+        assert_eq!(round.quantize(0.75 / 32768.0, 16), 1);
+        assert_eq!(round.quantize(-1.0, 24), -8_388_608);
+        assert_eq!(round.clipped_samples(), 0);
+
+        let mut truncate = Quantizer::new(DitherMode::Truncate, 1);
+        assert_eq!(truncate.quantize(0.75 / 32768.0, 16), 0);
+        assert_eq!(truncate.quantize(2.0, 16), 32767);
+        assert_eq!(truncate.quantize(-1.5, 16), -32768);
+        assert_eq!(truncate.clipped_samples(), 2);
+    }
+
+    #[test]
+    fn test_kernels_match_quantizer() {
+        let input: Vec<f32> = (0..515)
+            .map(|i| (i as f32 * 0.61).sin() * 1.1)
+            .chain([0.5 / 32768.0, 1.5 / 32768.0, -0.5 / 8_388_608.0])
+            .collect();
+        let mut processor = AudioProcessor::default();
+        for format in [AudioFormat::S16LE, AudioFormat::S24LE, AudioFormat::S32LE] {
+            let mut quantizer = Quantizer::new(DitherMode::Round, 2);
+            let mut expected = vec![0u8; input.len() * format.bytes_per_sample()];
+            with_sample_format!(format, F => {
+                SampleSliceMut::<F>::new(&mut expected)
+                    .unwrap()
+                    .encode_from(&input, &mut quantizer)
+            });
+            let before = processor.clipped_samples();
+            assert_eq!(processor.convert_format(&input, format), expected);
+            assert_eq!(
+                processor.clipped_samples() - before,
+                quantizer.clipped_samples()
+            );
+
+            let mut decoded = vec![0.0f32; input.len()];
+            assert_eq!(
+                processor.decode_into(&expected, format, &mut decoded),
+                input.len()
+            );
+            let reference = with_sample_format!(format, F => {
+                SampleSlice::<F>::new(&expected).unwrap().to_vec()
+            });
+            assert_eq!(decoded, reference);
+        }
+    }
+
+    #[test]
+    fn test_gain_stage_ramps_changes() {
+        let mut stage = GainStage::new(2, 1000).with_ramp_ms(10.0, 1000);
+        let control = Arc::clone(stage.control());
+
+        let mut block = [1.0f32; 8];
+        stage.process(&mut block);
+        assert_eq!(block, [1.0; 8]);
+
+        // -6 dB on the left channel ramps over 10 frames
+        assert!(control.set_channel_gain_db(0, -6.0));
+        let target = db_to_gain(-6.0);
+        let mut block = [1.0f32; 40];
+        stage.process(&mut block);
+        let left: Vec<f32> = block.iter().step_by(2).copied().collect();
+        assert!(left.windows(2).all(|w| w[1] <= w[0]));
+        assert!((left[0] - (1.0 - (1.0 - target) / 10.0)).abs() < 1e-6);
+        assert!(left[9..].iter().all(|&s| s == target));
+        assert!(block.iter().skip(1).step_by(2).all(|&s| s == 1.0));
+
+        // Mute keeps the gains, and polarity flips ramp through zero
+        control.set_muted(true);
+        control.set_inverted(1, true);
+        assert_eq!(control.channel_gain_db(0), Some(-6.0));
+        let mut block = [1.0f32; 40];
+        stage.process(&mut block);
+        assert!(block[18..].iter().all(|&s| s == 0.0));
+
+        control.set_muted(false);
+        let mut block = [1.0f32; 40];
+        stage.process(&mut block);
+        let right: Vec<f32> = block.iter().skip(1).step_by(2).copied().collect();
+        assert!(right
+            .windows(2)
+            .all(|w| w[1] <= w[0] && w[0] - w[1] <= 0.1 + 1e-6));
+        assert_eq!(right[19], -1.0);
+    }
+
+    #[test]
+    fn test_gain_stage_control_from_other_thread() {
+        let mut stage = GainStage::new(1, 48000);
+        let control = Arc::clone(stage.control());
+        std::thread::spawn(move || control.set_gain_db(-20.0))
+            .join()
+            .unwrap();
+
+        let mut block = vec![1.0f32; 2048];
+        stage.process(&mut block);
+        assert!((block[2047] - 0.1).abs() < 1e-6);
+
+        assert!(!stage.control().set_gain_db(f32::NAN));
+        assert!(!stage.control().set_gain_db(f32::INFINITY));
+        assert!(!stage.control().set_channel_gain_db(0, f32::NEG_INFINITY));
+        assert!(!stage.control().set_channel_gain_db(1, -6.0));
+        stage.reset();
+        assert_eq!(stage.control().channel_gain_db(0), Some(-20.0));
+    }
+
+    #[test]
+    fn test_tpdf_dither_keeps_quiet_signal() {
+        // A level of 0.3 LSB rounds away completely without dither
+        let sample = 0.3 / 32768.0;
+        let mut round = Quantizer::new(DitherMode::Round, 1);
+        assert!((0..1000).all(|_| round.quantize(sample, 16) == 0));
+
+        let mut tpdf = Quantizer::new(DitherMode::Tpdf, 1);
+        let codes: Vec<i32> = (0..100_000).map(|_| tpdf.quantize(sample, 16)).collect();
+        assert!(codes.iter().all(|code| code.abs() <= 2));
+        let mean = codes.iter().sum::<i32>() as f64 / codes.len() as f64;
+        assert!((mean - 0.3).abs() < 0.02, "{}", mean);
+    }
+
+    #[test]
+    fn test_noise_shaping_moves_noise_up() {
+        let signal = crate::test_util::sine(440.0, 0.01, 0.0, 48_000, 1);
+
+        // Energy of the quantization error after a 32-sample moving average,
+        // which keeps only the low-frequency part
+        let low_band_noise = |mode| {
+            let mut quantizer = Quantizer::new(mode, 1);
+            let error: Vec<f64> = signal
+                .iter()
+                .map(|&s| quantizer.quantize(s, 16) as f64 - s as f64 * 32768.0)
+                .collect();
+            error
+                .windows(32)
+                .map(|w| (w.iter().sum::<f64>() / 32.0).powi(2))
+                .sum::<f64>()
+        };
+
+        let flat = low_band_noise(DitherMode::Tpdf);
+        let shaped = low_band_noise(DitherMode::NoiseShaped);
+        assert!(shaped < flat * 0.25, "{} vs {}", shaped, flat);
+    }
+
+    #[test]
+    fn test_convert_format_counts_clipped_samples() {
+        let mut processor = AudioProcessor::default().with_dither(DitherMode::Tpdf);
+        assert_eq!(processor.dither(), DitherMode::Tpdf);
+        processor.convert_format(&[0.5, 1.5, -0.5, -3.0], AudioFormat::S16LE);
+        processor.convert_format(&[1.25, 0.0], AudioFormat::F32LE);
+        processor.convert_format(&[1.25, 0.0], AudioFormat::S24LE);
+        assert_eq!(processor.clipped_samples(), 3);
+    }
+
+    #[test]
+    fn test_byte_order_is_explicit() {
+        let mut processor = AudioProcessor::default();
+        let input = [0.5f32, -1.0];
+
+        let bytes = processor.convert_format(&input, AudioFormat::S16BE);
+        assert_eq!(bytes, [0x40, 0x00, 0x80, 0x00]);
+        let bytes = processor.convert_format(&input, AudioFormat::S24BE);
+        assert_eq!(bytes, [0x40, 0x00, 0x00, 0x80, 0x00, 0x00]);
+        let bytes = processor.convert_format(&input, AudioFormat::S24_32LE);
+        assert_eq!(bytes[..4], [0x00, 0x00, 0x40, 0x00]);
+        let bytes = processor.convert_format(&input, AudioFormat::F32BE);
+        assert_eq!(bytes[..4], 0.5f32.to_be_bytes());
+        let bytes = processor.convert_format(&input, AudioFormat::U8);
+        assert_eq!(bytes, [192, 0]);
+    }
+
+    #[test]
+    fn test_decode_sign_extends_24_bit() {
+        let processor = AudioProcessor::default();
+        let samples = processor
+            .bytes_to_samples(&[0x00, 0x00, 0xC0], AudioFormat::S24LE)
+            .unwrap();
+        assert_eq!(samples, [-0.5]);
+
+        // The container byte of S24_32 is ignored
+        let samples = processor
+            .bytes_to_samples(&[0x00, 0x00, 0xC0, 0x7F], AudioFormat::S24_32LE)
+            .unwrap();
+        assert_eq!(samples, [-0.5]);
+        let samples = processor
+            .bytes_to_samples(&[0x12, 0xC0, 0x00, 0x00], AudioFormat::S24_32BE)
+            .unwrap();
+        assert_eq!(samples, [-0.5]);
+    }
+
+    #[test]
+    fn test_bytes_to_samples_rejects_partial_sample() {
+        let processor = AudioProcessor::default();
+        assert!(processor
+            .bytes_to_samples(&[0, 0, 0], AudioFormat::S16LE)
+            .is_err());
+
+        // Streaming decode stops at the last whole sample instead
+        let mut output = [1.0f32; 2];
+        assert_eq!(
+            processor.decode_into(&[0, 0, 0], AudioFormat::S16LE, &mut output),
+            1
+        );
+        assert_eq!(output, [0.0, 1.0]);
+    }
+
+    #[test]
+    fn test_bytes_to_samples_s32() {
+        let processor = AudioProcessor::new(48000, 48000, 2, AudioFormat::F32LE);
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&2147483647i32.to_le_bytes());
+
+        let samples = processor
+            .bytes_to_samples(&bytes, AudioFormat::S32LE)
+            .unwrap();
+        assert_eq!(samples.len(), 1);
+        assert!((samples[0] - 1.0).abs() < 0.00001);
+    }
+
+    #[test]
+    fn test_decode_into_heapless_buffer() {
+        use crate::buffer::HeaplessRingBuffer;
+
+        let processor = AudioProcessor::default();
+        let mut buffer = HeaplessRingBuffer::<f32, 128>::new();
+
+        let bytes: Vec<u8> = (0..200i16)
+            .flat_map(|value| (value * 100).to_le_bytes())
+            .collect();
+
+        // Stops once the 127-sample buffer is full
+        assert_eq!(
+            processor.decode_into_buffer(&bytes, AudioFormat::S16LE, &mut buffer),
+            127
+        );
+        let mut output = [0.0; 127];
+        assert_eq!(buffer.read(&mut output), 127);
+        assert!((output[126] - 12600.0 / 32768.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_decode_into_ring_wraps() {
+        let processor = AudioProcessor::default();
+        let mut ring = RingBuffer::<f32>::new(4);
+        ring.write(&[0.0; 3]);
+        ring.commit_read(3);
+
+        let mut bytes = Vec::new();
+        for value in [1i16, 2, 3, 4, 5, 6] {
+            bytes.extend_from_slice(&(value * 1000).to_le_bytes());
+        }
+
+        // Only 4 samples fit, split across the wrap point
+        assert_eq!(
+            processor.decode_into_ring(&bytes, AudioFormat::S16LE, &mut ring),
+            4
+        );
+        let mut output = [0.0; 4];
+        ring.read(&mut output);
+        for (i, sample) in output.iter().enumerate() {
+            assert!((sample - (i + 1) as f32 * 1000.0 / 32768.0).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_stage_keeps_phase_across_blocks() {
+        // 2:1 downsampling of a stereo ramp, fed in uneven blocks
+        let mut resampler = Resampler::new(48000, 24000, 2);
+        let input: Vec<f32> = (0..200).flat_map(|i| [i as f32, -(i as f32)]).collect();
+
+        let mut output = vec![0.0; 400];
+        let (mut pos, mut produced) = (0, 0);
+        for block in [6, 14, 2, 100, 78, 200] {
+            let end = (pos + block).min(input.len());
+            let (used, made) = resampler
+                .process_stage(&input[pos..end], &mut output[produced..])
+                .unwrap();
+            pos += used;
+            produced += made;
+        }
+
+        assert_eq!(pos, input.len());
+        assert_eq!(produced / 2, 100);
+        for (k, frame) in output[..produced].chunks(2).enumerate() {
+            assert_eq!(frame, &[2.0 * k as f32, -2.0 * k as f32]);
+        }
+    }
+
+    #[test]
+    fn test_streaming_resampler_long_run_length() {
+        // 44.1 kHz to 48 kHz stereo, fed in irregular blocks
+        let mut resampler = StreamingResampler::new(44100, 48000, 2);
+        let input: Vec<f32> = (0..2 * 44100).map(|i| (i / 2) as f32).collect();
+        let mut output = vec![0.0; 2 * 48000 + 64];
+
+        let (mut pos, mut produced) = (0, 0);
+        for block in [2, 882, 1, 7, 4096, 960].iter().cycle() {
+            if pos == input.len() {
+                break;
+            }
+            let end = (pos + block).min(input.len());
+            let (used, made) = resampler.process(&input[pos..end], &mut output[produced..]);
+            pos += used;
+            produced += made;
+        }
+
+        // One second in, one second out, minus the held-back frame
+        assert_eq!(produced / 2, 48000 - 1);
+        for (k, frame) in output[..produced].chunks(2).enumerate() {
+            // No phase jumps at block boundaries (f32 ramp resolution)
+            let expected = k as f64 * 44100.0 / 48000.0;
+            assert!((frame[0] as f64 - expected).abs() < 1e-2);
+            assert_eq!(frame[0], frame[1]);
+        }
+
+        // The flush makes up for the held-back frame
+        produced += resampler.flush(&mut output[produced..]);
+        assert_eq!(produced / 2, 48000);
+        assert_eq!(resampler.flush(&mut output[produced..]), 0);
+    }
+
+    #[test]
+    fn test_audio_processor_streams_across_calls() {
+        let mut processor = AudioProcessor::new(24000, 48000, 1, AudioFormat::F32LE);
+        let mut output = vec![0.0; processor.max_output_len(2)];
+
+        let produced = processor.process(&[0.0, 1.0], &mut output).unwrap();
+        assert_eq!(&output[..produced], &[0.0, 0.5]);
+        let produced = processor.process(&[2.0, 3.0], &mut output).unwrap();
+        assert_eq!(&output[..produced], &[1.0, 1.5, 2.0, 2.5]);
+    }
+
+    #[cfg(not(feature = "rubato"))]
+    #[test]
+    fn test_quality_falls_back_to_linear() {
+        let resampler = Resampler::with_quality(44100, 48000, 2, ResampleQuality::Best);
+        assert_eq!(resampler.quality(), ResampleQuality::Linear);
+    }
+
+    #[cfg(feature = "rubato")]
+    #[test]
+    fn test_rubato_quality_presets() {
+        // One second of a 1 kHz stereo sine, fed in irregular blocks
+        let input: Vec<f32> = (0..44100)
+            .flat_map(|i| {
+                let s = (2.0 * std::f32::consts::PI * 1000.0 * i as f32 / 44100.0).sin() * 0.5;
+                [s, -s]
+            })
+            .collect();
+
+        for quality in [
+            ResampleQuality::Fast,
+            ResampleQuality::Medium,
+            ResampleQuality::Best,
+        ] {
+            let mut resampler = Resampler::with_quality(44100, 48000, 2, quality);
+            assert_eq!(resampler.quality(), quality);
+
+            let mut output = Vec::new();
+            for block in input.chunks(2 * 300) {
+                output.extend(resampler.process(block).unwrap());
+            }
+
+            // All but the last partial chunk comes out
+            let frames = output.len() / 2;
+            assert!(
+                (48000 - 600..=48000).contains(&frames),
+                "{quality:?}: {frames}"
+            );
+            // and the flush emits it
+            output.extend(resampler.flush().unwrap());
+            let frames = output.len() / 2;
+            assert!(
+                (48000 - 1..=48000 + 1).contains(&frames),
+                "{quality:?}: {frames}"
+            );
+            for frame in output.chunks(2) {
+                assert!(frame[0].is_finite() && frame[0].abs() < 0.6);
+                assert_eq!(frame[0], -frame[1]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_variable_resampler_slews_correction() {
+        let mut resampler = VariableResampler::new(48_000, 48_000, 1);
+        let input = vec![0.5f32; 1_000];
+        let mut output = vec![0.0f32; 1_000];
+
+        resampler.set_correction(500e-6);
+        assert_eq!(resampler.correction(), 0.0);
+        assert_eq!(resampler.target_correction(), 500e-6);
+
+        // 1000 frames may move the correction by at most 100 ppm
+        resampler.process_stage(&input, &mut output).unwrap();
+        assert!(resampler.correction() > 0.0);
+        assert!(resampler.correction() <= 100e-6 + 1e-12);
+
+        for _ in 0..10 {
+            resampler.process_stage(&input, &mut output).unwrap();
+        }
+        assert_eq!(resampler.correction(), 500e-6);
+        assert!((resampler.ratio() - 1.0 / (1.0 + 500e-6)).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_channel_mixer_standard_matrices() {
+        let mut output = [0.0f32; 4];
+        assert_eq!(
+            ChannelMixer::new(1, 2).process(&[0.5, -0.25], &mut output),
+            2
+        );
+        assert_eq!(output, [0.5, 0.5, -0.25, -0.25]);
+
+        let fold = ChannelMixer::new(2, 1);
+        assert_eq!(fold.process(&[1.0, 0.0, 0.5, 0.5], &mut output), 2);
+        assert_eq!(&output[..2], &[0.5, 0.5]);
+
+        // 5.1: L, R, C, LFE, Ls, Rs
+        let h = std::f32::consts::FRAC_1_SQRT_2;
+        let surround = ChannelMixer::new(6, 2);
+        assert_eq!(
+            surround.process(&[0.1, 0.2, 0.3, 0.9, 0.4, 0.5], &mut output),
+            1
+        );
+        assert!((output[0] - (0.1 + h * 0.3 + h * 0.4)).abs() < 1e-6);
+        assert!((output[1] - (0.2 + h * 0.3 + h * 0.5)).abs() < 1e-6);
+
+        // 7.1 to mono averages the stereo downmix, LFE still dropped
+        let mono = ChannelMixer::new(8, 1);
+        assert_eq!(mono.gain(0, 0), 0.5);
+        assert_eq!(mono.gain(0, 2), h);
+        assert_eq!(mono.gain(0, 3), 0.0);
+        assert_eq!(mono.gain(0, 7), 0.5 * h);
+    }
+
+    #[test]
+    fn test_channel_mixer_custom_and_map() {
+        let swap = ChannelMixer::from_channel_map(2, &[1, 0]).unwrap();
+        let mut output = [0.0f32; 4];
+        swap.process(&[1.0, 2.0, 3.0, 4.0], &mut output);
+        assert_eq!(output, [2.0, 1.0, 4.0, 3.0]);
+
+        let custom = ChannelMixer::from_matrix(2, 1, vec![1.0, -1.0]).unwrap();
+        custom.process(&[0.75, 0.25], &mut output);
+        assert_eq!(output[0], 0.5);
+
+        assert!(ChannelMixer::from_matrix(2, 2, vec![1.0; 3]).is_err());
+        assert!(ChannelMixer::from_channel_map(2, &[0, 2]).is_err());
+    }
+
+    #[test]
+    fn test_audio_processor_remixes_channels() {
+        let mut processor =
+            AudioProcessor::new(48000, 48000, 2, AudioFormat::F32LE).with_output_channels(1);
+        assert_eq!(processor.output_channels(), 1);
+        let mut output = vec![0.0f32; processor.max_output_len(4)];
+        assert_eq!(
+            processor
+                .process(&[1.0, 0.0, 0.0, -1.0], &mut output)
+                .unwrap(),
+            2
+        );
+        assert_eq!(output, [0.5, -0.5]);
+
+        // Mono into a stereo cable at another rate
+        let mut processor =
+            AudioProcessor::new(24000, 48000, 1, AudioFormat::F32LE).with_output_channels(2);
+        let input = [0.5f32; 100];
+        let mut output = vec![0.0f32; processor.max_output_len(input.len())];
+        let produced = processor.process(&input, &mut output).unwrap();
+        assert!(produced >= 2 * 190, "{}", produced);
+        assert!(output[..produced].iter().all(|&s| (s - 0.5).abs() < 1e-6));
+
+        let mismatched = ChannelMixer::new(6, 2);
+        assert!(AudioProcessor::default().with_mixer(mismatched).is_err());
+    }
+
+    #[test]
+    fn test_audio_processor_remixes_large_blocks_in_place() {
+        let mut processor =
+            AudioProcessor::new(24000, 48000, 1, AudioFormat::F32LE).with_output_channels(2);
+        let capacity = processor.mix_buffer.capacity();
+        assert_eq!(capacity, MIX_BLOCK_FRAMES * 2);
+
+        // Several mix blocks in one call, without growing the scratch buffer
+        let input: Vec<f32> = (0..1000).map(|i| (i as f32 * 0.01).sin()).collect();
+        let mut output = vec![0.0f32; processor.max_output_len(input.len())];
+        let produced = processor.process(&input, &mut output).unwrap();
+        assert!(produced >= 2 * 1998, "{}", produced);
+        assert_eq!(processor.mix_buffer.capacity(), capacity);
+
+        // Left and right carry the same interpolated signal without a seam
+        let left: Vec<f32> = output[..produced].iter().step_by(2).copied().collect();
+        assert!(output[..produced].chunks(2).all(|f| f[0] == f[1]));
+        assert!(left.windows(2).all(|w| (w[1] - w[0]).abs() < 0.01));
     }
 
     #[test]
     fn test_resampler_identity() {
-        let resampler = Resampler::new(48000, 48000, 1);
+        let mut resampler = Resampler::new(48000, 48000, 1);
         let input = vec![0.1, 0.2, 0.3];
         let output = resampler.process(&input).unwrap();
         assert_eq!(input, output);
@@ -318,12 +2299,17 @@ mod tests {
 
     #[test]
     fn test_resampler_up_down() {
-        let resampler_up = Resampler::new(44100, 88200, 1);
+        let mut resampler_up = Resampler::new(44100, 88200, 1);
         let input = vec![1.0, 0.0];
         let output = resampler_up.process(&input).unwrap();
-        assert_eq!(output.len(), 4);
+        // The last frame waits for the next block
+        assert_eq!(output, [1.0, 0.5]);
+        let output = resampler_up.process(&[-1.0]).unwrap();
+        assert_eq!(output, [0.0, -0.5]);
+        assert_eq!(resampler_up.flush().unwrap(), [-1.0, -1.0]);
+        assert!(resampler_up.flush().unwrap().is_empty());
 
-        let resampler_down = Resampler::new(48000, 24000, 1);
+        let mut resampler_down = Resampler::new(48000, 24000, 1);
         let input = vec![1.0, 0.5, 0.0, -0.5];
         let output = resampler_down.process(&input).unwrap();
         assert_eq!(output.len(), 2);
@@ -0,0 +1,67 @@
+//! Second-order IIR filter section.
+
+/// A biquad filter in transposed direct form II.
+///
+/// Coefficients are normalized so that `a0` is 1. State and arithmetic are
+/// in f64, which keeps low-frequency sections stable at high sample rates.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Biquad {
+    b0: f64,
+    b1: f64,
+    b2: f64,
+    a1: f64,
+    a2: f64,
+    z1: f64,
+    z2: f64,
+}
+
+impl Biquad {
+    /// Creates a section from normalized coefficients `[b0, b1, b2]` and
+    /// `[a1, a2]`.
+    pub(crate) fn new([b0, b1, b2]: [f64; 3], [a1, a2]: [f64; 2]) -> Self {
+        Self {
+            b0,
+            b1,
+            b2,
+            a1,
+            a2,
+            z1: 0.0,
+            z2: 0.0,
+        }
+    }
+
+    /// Replaces the coefficients, keeping the filter history.
+    pub(crate) fn set_coefficients(&mut self, [b0, b1, b2]: [f64; 3], [a1, a2]: [f64; 2]) {
+        self.b0 = b0;
+        self.b1 = b1;
+        self.b2 = b2;
+        self.a1 = a1;
+        self.a2 = a2;
+    }
+
+    /// Returns the gain in dB at `omega` radians per sample.
+    pub(crate) fn response_db(&self, omega: f64) -> f64 {
+        // Evaluate both polynomials at z^-1 = e^(-j omega)
+        let (sin1, cos1) = omega.sin_cos();
+        let (sin2, cos2) = (2.0 * omega).sin_cos();
+        let num_re = self.b0 + self.b1 * cos1 + self.b2 * cos2;
+        let num_im = -(self.b1 * sin1 + self.b2 * sin2);
+        let den_re = 1.0 + self.a1 * cos1 + self.a2 * cos2;
+        let den_im = -(self.a1 * sin1 + self.a2 * sin2);
+        10.0 * ((num_re * num_re + num_im * num_im) / (den_re * den_re + den_im * den_im)).log10()
+    }
+
+    /// Filters one sample.
+    pub(crate) fn process(&mut self, x: f64) -> f64 {
+        let y = self.b0 * x + self.z1;
+        self.z1 = self.b1 * x - self.a1 * y + self.z2;
+        self.z2 = self.b2 * x - self.a2 * y;
+        y
+    }
+
+    /// Clears the filter history.
+    pub(crate) fn reset(&mut self) {
+        self.z1 = 0.0;
+        self.z2 = 0.0;
+    }
+}
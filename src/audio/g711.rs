@@ -0,0 +1,132 @@
+//! G.711 A-law and μ-law companding.
+//!
+//! Follows the reference implementation in ITU-T G.191: 16-bit linear PCM
+//! is reduced to 13 (A-law) or 14 (μ-law) bits and encoded as a sign, a
+//! 3-bit segment and a 4-bit step within the segment.
+
+/// Segment end points of 13-bit A-law magnitudes.
+const ALAW_SEGMENT_END: [i32; 8] = [0x1F, 0x3F, 0x7F, 0xFF, 0x1FF, 0x3FF, 0x7FF, 0xFFF];
+
+/// Segment end points of biased 14-bit μ-law magnitudes.
+const ULAW_SEGMENT_END: [i32; 8] = [0x3F, 0x7F, 0xFF, 0x1FF, 0x3FF, 0x7FF, 0xFFF, 0x1FFF];
+
+/// Bias added to μ-law magnitudes so every segment starts on a power of two.
+const ULAW_BIAS: i32 = 0x84;
+
+/// Largest 14-bit μ-law magnitude before the bias is added.
+const ULAW_CLIP: i32 = 8159;
+
+/// Returns the first segment whose end point is at or above `value`.
+fn segment(value: i32, ends: &[i32; 8]) -> usize {
+    ends.iter()
+        .position(|&end| value <= end)
+        .unwrap_or(ends.len())
+}
+
+/// Encodes a 16-bit linear sample as A-law.
+pub(crate) fn encode_alaw(sample: i16) -> u8 {
+    let mut magnitude = i32::from(sample) >> 3;
+    let mask = if magnitude >= 0 {
+        0xD5
+    } else {
+        magnitude = -magnitude - 1;
+        0x55
+    };
+
+    let segment = segment(magnitude, &ALAW_SEGMENT_END);
+    if segment >= 8 {
+        return 0x7F ^ mask;
+    }
+    let shift = if segment < 2 { 1 } else { segment };
+    let code = (segment << 4) as i32 | ((magnitude >> shift) & 0x0F);
+    code as u8 ^ mask
+}
+
+/// Decodes an A-law byte to a 16-bit linear sample.
+pub(crate) fn decode_alaw(code: u8) -> i16 {
+    let code = code ^ 0x55;
+    let mut magnitude = i32::from(code & 0x0F) << 4;
+    match (code & 0x70) >> 4 {
+        0 => magnitude += 8,
+        1 => magnitude += 0x108,
+        segment => magnitude = (magnitude + 0x108) << (segment - 1),
+    }
+    if code & 0x80 != 0 {
+        magnitude as i16
+    } else {
+        -magnitude as i16
+    }
+}
+
+/// Encodes a 16-bit linear sample as μ-law.
+pub(crate) fn encode_ulaw(sample: i16) -> u8 {
+    let mut magnitude = i32::from(sample) >> 2;
+    let mask = if magnitude < 0 {
+        magnitude = -magnitude;
+        0x7F
+    } else {
+        0xFF
+    };
+    magnitude = magnitude.min(ULAW_CLIP) + (ULAW_BIAS >> 2);
+
+    let segment = segment(magnitude, &ULAW_SEGMENT_END);
+    if segment >= 8 {
+        return 0x7F ^ mask;
+    }
+    let code = (segment << 4) as i32 | ((magnitude >> (segment + 1)) & 0x0F);
+    code as u8 ^ mask
+}
+
+/// Decodes a μ-law byte to a 16-bit linear sample.
+pub(crate) fn decode_ulaw(code: u8) -> i16 {
+    let code = !code;
+    let magnitude = ((i32::from(code & 0x0F) << 3) + ULAW_BIAS) << ((code & 0x70) >> 4);
+    if code & 0x80 != 0 {
+        (ULAW_BIAS - magnitude) as i16
+    } else {
+        (magnitude - ULAW_BIAS) as i16
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_silence_codes() {
+        assert_eq!(encode_alaw(0), 0xD5);
+        assert_eq!(encode_ulaw(0), 0xFF);
+        assert_eq!(decode_ulaw(0xFF), 0);
+        assert_eq!(decode_alaw(0xD5), 8);
+    }
+
+    #[test]
+    fn test_every_code_round_trips() {
+        for code in 0..=255u8 {
+            assert_eq!(encode_alaw(decode_alaw(code)), code, "A-law {:#04x}", code);
+            // 0x7F and 0xFF both decode to zero
+            if code != 0x7F {
+                assert_eq!(encode_ulaw(decode_ulaw(code)), code, "μ-law {:#04x}", code);
+            }
+        }
+    }
+
+    #[test]
+    fn test_error_stays_within_segment_step() {
+        for sample in (i16::MIN..=i16::MAX).step_by(97) {
+            let alaw = decode_alaw(encode_alaw(sample));
+            let ulaw = decode_ulaw(encode_ulaw(sample));
+            // The widest steps, at full scale, are 1024 codes
+            assert!(
+                (i32::from(alaw) - i32::from(sample)).abs() <= 1024,
+                "{}",
+                sample
+            );
+            assert!(
+                (i32::from(ulaw) - i32::from(sample)).abs() <= 1024,
+                "{}",
+                sample
+            );
+        }
+    }
+}
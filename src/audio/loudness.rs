@@ -0,0 +1,647 @@
+//! Loudness measurement after ITU-R BS.1770-4 and EBU R128.
+//!
+//! Audio is K-weighted, squared and summed over channels with the BS.1770
+//! channel weights, then averaged over 100 ms sub-blocks. Momentary loudness
+//! covers the last 400 ms, short-term loudness the last 3 s. Integrated
+//! loudness averages the 400 ms blocks (overlapping by 75%) that pass the
+//! absolute gate of -70 LUFS and the relative gate 10 LU below their mean.
+//! Loudness range (EBU Tech 3342) is the spread between the 10th and 95th
+//! percentile of the short-term values, gated 20 LU below their mean.
+//!
+//! Gated values are kept in histograms of 0.1 LU bins, so a meter uses the
+//! same memory however long it runs.
+
+use super::biquad::Biquad;
+use crate::sample::{with_sample_format, SampleSlice};
+use crate::{AudioFormat, Error};
+
+use std::f64::consts::PI;
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+
+/// Sub-blocks per second.
+const SUB_BLOCKS_PER_SECOND: u32 = 10;
+
+/// Sub-blocks in a momentary (400 ms) window.
+const MOMENTARY_SUB_BLOCKS: usize = 4;
+
+/// Sub-blocks in a short-term (3 s) window.
+const SHORT_TERM_SUB_BLOCKS: usize = 30;
+
+/// Blocks at or below this loudness are ignored by the gated measurements.
+const ABSOLUTE_GATE_LUFS: f64 = -70.0;
+
+/// Relative gate of the integrated loudness, in LU below the mean.
+const INTEGRATED_RELATIVE_GATE_LU: f64 = -10.0;
+
+/// Relative gate of the loudness range, in LU below the mean.
+const RANGE_RELATIVE_GATE_LU: f64 = -20.0;
+
+/// Width of a histogram bin, in LU.
+const HISTOGRAM_BIN_LU: f64 = 0.1;
+
+/// Histogram bins, covering -70 to +10 LUFS (louder blocks share the last
+/// bin).
+const HISTOGRAM_BINS: usize = 800;
+
+/// Frames read per step by [`measure_file`].
+const FILE_CHUNK_FRAMES: usize = 4096;
+
+/// Converts a weighted mean square to LUFS.
+fn energy_to_lufs(energy: f64) -> f64 {
+    -0.691 + 10.0 * energy.log10()
+}
+
+/// BS.1770 weights per channel in WAVE order: surround channels count
+/// +1.5 dB and the LFE channel is ignored.
+fn channel_weights(channels: usize) -> Vec<f64> {
+    match channels {
+        // L, R, C, LFE, Ls, Rs
+        6 => vec![1.0, 1.0, 1.0, 0.0, 1.41, 1.41],
+        // L, R, C, LFE, Lb, Rb, Ls, Rs
+        8 => vec![1.0, 1.0, 1.0, 0.0, 1.0, 1.0, 1.41, 1.41],
+        _ => vec![1.0; channels],
+    }
+}
+
+/// The two-stage K-weighting filter (high shelf and RLB high-pass) for a
+/// sample rate.
+fn k_weighting(sample_rate: u32) -> [Biquad; 2] {
+    let rate = f64::from(sample_rate);
+
+    // Stage 1: shelf of about +4 dB above 1.5 kHz
+    let (f0, gain_db, q) = (
+        1_681.974_450_955_533,
+        3.999_843_853_973_347,
+        0.707_175_236_955_419_6,
+    );
+    let k = (PI * f0 / rate).tan();
+    let vh = 10f64.powf(gain_db / 20.0);
+    let vb = vh.powf(0.499_666_774_154_541_6);
+    let a0 = 1.0 + k / q + k * k;
+    let shelf = Biquad::new(
+        [
+            (vh + vb * k / q + k * k) / a0,
+            2.0 * (k * k - vh) / a0,
+            (vh - vb * k / q + k * k) / a0,
+        ],
+        [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+    );
+
+    // Stage 2: high-pass at about 38 Hz
+    let (f0, q) = (38.135_470_876_024_44, 0.500_327_037_323_877_3);
+    let k = (PI * f0 / rate).tan();
+    let a0 = 1.0 + k / q + k * k;
+    let high_pass = Biquad::new(
+        [1.0, -2.0, 1.0],
+        [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+    );
+
+    [shelf, high_pass]
+}
+
+/// Loudness readings of a [`LoudnessMeter`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LoudnessReading {
+    /// Loudness of the last 400 ms, in LUFS
+    pub momentary_lufs: f64,
+
+    /// Loudness of the last 3 s, in LUFS
+    pub short_term_lufs: f64,
+
+    /// Gated loudness since the last reset, in LUFS
+    pub integrated_lufs: f64,
+
+    /// Loudness range since the last reset, in LU
+    pub loudness_range_lu: f64,
+}
+
+impl Default for LoudnessReading {
+    /// Readings of silence: negative infinity LUFS and no range.
+    fn default() -> Self {
+        Self {
+            momentary_lufs: f64::NEG_INFINITY,
+            short_term_lufs: f64::NEG_INFINITY,
+            integrated_lufs: f64::NEG_INFINITY,
+            loudness_range_lu: 0.0,
+        }
+    }
+}
+
+/// Gated block energies, counted per 0.1 LU of loudness.
+///
+/// Each bin keeps the exact energy sum of its blocks, so means are exact
+/// and only the gate thresholds are rounded to a bin.
+#[derive(Debug, Clone)]
+struct LoudnessHistogram {
+    counts: Vec<u64>,
+    energies: Vec<f64>,
+}
+
+impl LoudnessHistogram {
+    fn new() -> Self {
+        Self {
+            counts: vec![0; HISTOGRAM_BINS],
+            energies: vec![0.0; HISTOGRAM_BINS],
+        }
+    }
+
+    /// Adds a block unless it falls below the absolute gate.
+    fn add(&mut self, energy: f64) {
+        let lufs = energy_to_lufs(energy);
+        if lufs > ABSOLUTE_GATE_LUFS {
+            let bin = self.bin(lufs);
+            self.counts[bin] += 1;
+            self.energies[bin] += energy;
+        }
+    }
+
+    /// Returns the bin holding `lufs`, clamped to the histogram.
+    fn bin(&self, lufs: f64) -> usize {
+        let bin = ((lufs - ABSOLUTE_GATE_LUFS) / HISTOGRAM_BIN_LU).floor();
+        (bin.max(0.0) as usize).min(HISTOGRAM_BINS - 1)
+    }
+
+    /// Returns the loudness of a bin's centre.
+    fn bin_lufs(bin: usize) -> f64 {
+        ABSOLUTE_GATE_LUFS + (bin as f64 + 0.5) * HISTOGRAM_BIN_LU
+    }
+
+    /// Returns the mean energy of the blocks from bin `start` up.
+    fn mean_energy(&self, start: usize) -> Option<f64> {
+        let count: u64 = self.counts[start..].iter().sum();
+        let energy: f64 = self.energies[start..].iter().sum();
+        (count > 0).then(|| energy / count as f64)
+    }
+
+    /// Returns the first bin passing a gate `gate_lu` below the mean of all
+    /// blocks.
+    fn relative_gate(&self, gate_lu: f64) -> Option<usize> {
+        let threshold = energy_to_lufs(self.mean_energy(0)?) + gate_lu;
+        let bin = self.bin(threshold);
+        Some(if Self::bin_lufs(bin) < threshold {
+            bin + 1
+        } else {
+            bin
+        })
+    }
+
+    /// Returns the loudness below which `fraction` of the blocks from bin
+    /// `start` up fall.
+    fn percentile(&self, start: usize, fraction: f64) -> f64 {
+        let total: u64 = self.counts[start..].iter().sum();
+        let rank = (fraction * (total - 1) as f64).round() as u64;
+        let mut seen = 0;
+        for (bin, &count) in self.counts.iter().enumerate().skip(start) {
+            seen += count;
+            if seen > rank {
+                return Self::bin_lufs(bin);
+            }
+        }
+        Self::bin_lufs(HISTOGRAM_BINS - 1)
+    }
+
+    fn clear(&mut self) {
+        self.counts.fill(0);
+        self.energies.fill(0.0);
+    }
+}
+
+/// Measures momentary, short-term and integrated loudness and loudness
+/// range of interleaved audio.
+///
+/// `process` does not allocate, so the meter can run on the audio path.
+#[derive(Debug, Clone)]
+pub struct LoudnessMeter {
+    sample_rate: u32,
+    filters: Vec<[Biquad; 2]>,
+    weights: Vec<f64>,
+    sub_block_frames: usize,
+    /// Frames and weighted energy of the sub-block being collected
+    frames: usize,
+    energy: f64,
+    /// Mean energy of the last sub-blocks, as a ring
+    recent: [f64; SHORT_TERM_SUB_BLOCKS],
+    recent_pos: usize,
+    recent_filled: usize,
+    blocks: LoudnessHistogram,
+    short_terms: LoudnessHistogram,
+}
+
+impl LoudnessMeter {
+    /// Creates a meter for interleaved audio in the WAVE channel order.
+    pub fn new(channels: usize, sample_rate: u32) -> Self {
+        let channels = channels.max(1);
+        let sample_rate = sample_rate.max(SUB_BLOCKS_PER_SECOND);
+        Self {
+            sample_rate,
+            filters: vec![k_weighting(sample_rate); channels],
+            weights: channel_weights(channels),
+            sub_block_frames: (sample_rate / SUB_BLOCKS_PER_SECOND) as usize,
+            frames: 0,
+            energy: 0.0,
+            recent: [0.0; SHORT_TERM_SUB_BLOCKS],
+            recent_pos: 0,
+            recent_filled: 0,
+            blocks: LoudnessHistogram::new(),
+            short_terms: LoudnessHistogram::new(),
+        }
+    }
+
+    /// Returns the number of interleaved channels per frame.
+    pub fn channels(&self) -> usize {
+        self.filters.len()
+    }
+
+    /// Returns the sample rate in Hz.
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Measures a block of interleaved samples.
+    pub fn process(&mut self, block: &[f32]) {
+        for frame in block.chunks_exact(self.filters.len()) {
+            for ((&sample, filters), weight) in
+                frame.iter().zip(&mut self.filters).zip(&self.weights)
+            {
+                let [shelf, high_pass] = filters;
+                let y = high_pass.process(shelf.process(f64::from(sample)));
+                self.energy += weight * y * y;
+            }
+            self.frames += 1;
+            if self.frames == self.sub_block_frames {
+                self.finish_sub_block();
+            }
+        }
+    }
+
+    /// Returns the loudness of the last 400 ms, or negative infinity until
+    /// that much audio was measured.
+    pub fn momentary_lufs(&self) -> f64 {
+        self.window_energy(MOMENTARY_SUB_BLOCKS)
+            .map_or(f64::NEG_INFINITY, energy_to_lufs)
+    }
+
+    /// Returns the loudness of the last 3 s, or negative infinity until
+    /// that much audio was measured.
+    pub fn short_term_lufs(&self) -> f64 {
+        self.window_energy(SHORT_TERM_SUB_BLOCKS)
+            .map_or(f64::NEG_INFINITY, energy_to_lufs)
+    }
+
+    /// Returns the gated loudness since the last reset, or negative
+    /// infinity if nothing passed the gates.
+    pub fn integrated_lufs(&self) -> f64 {
+        self.blocks
+            .relative_gate(INTEGRATED_RELATIVE_GATE_LU)
+            .and_then(|start| self.blocks.mean_energy(start))
+            .map_or(f64::NEG_INFINITY, energy_to_lufs)
+    }
+
+    /// Returns the loudness range since the last reset, in LU.
+    pub fn loudness_range_lu(&self) -> f64 {
+        let Some(start) = self.short_terms.relative_gate(RANGE_RELATIVE_GATE_LU) else {
+            return 0.0;
+        };
+        if self.short_terms.mean_energy(start).is_none() {
+            return 0.0;
+        }
+        self.short_terms.percentile(start, 0.95) - self.short_terms.percentile(start, 0.10)
+    }
+
+    /// Returns all readings at once.
+    pub fn reading(&self) -> LoudnessReading {
+        LoudnessReading {
+            momentary_lufs: self.momentary_lufs(),
+            short_term_lufs: self.short_term_lufs(),
+            integrated_lufs: self.integrated_lufs(),
+            loudness_range_lu: self.loudness_range_lu(),
+        }
+    }
+
+    /// Starts a new measurement, e.g. at the beginning of a programme.
+    pub fn reset(&mut self) {
+        for filters in &mut self.filters {
+            filters.iter_mut().for_each(Biquad::reset);
+        }
+        self.frames = 0;
+        self.energy = 0.0;
+        self.recent_pos = 0;
+        self.recent_filled = 0;
+        self.blocks.clear();
+        self.short_terms.clear();
+    }
+
+    /// Stores the completed sub-block and feeds the gated histograms.
+    fn finish_sub_block(&mut self) {
+        self.recent[self.recent_pos] = self.energy / self.frames as f64;
+        self.recent_pos = (self.recent_pos + 1) % SHORT_TERM_SUB_BLOCKS;
+        self.recent_filled = (self.recent_filled + 1).min(SHORT_TERM_SUB_BLOCKS);
+        self.frames = 0;
+        self.energy = 0.0;
+
+        if let Some(energy) = self.window_energy(MOMENTARY_SUB_BLOCKS) {
+            self.blocks.add(energy);
+        }
+        if let Some(energy) = self.window_energy(SHORT_TERM_SUB_BLOCKS) {
+            self.short_terms.add(energy);
+        }
+    }
+
+    /// Returns the mean energy of the last `sub_blocks` sub-blocks.
+    fn window_energy(&self, sub_blocks: usize) -> Option<f64> {
+        if self.recent_filled < sub_blocks {
+            return None;
+        }
+        let sum: f64 = (1..=sub_blocks)
+            .map(|age| {
+                let index = (self.recent_pos + SHORT_TERM_SUB_BLOCKS - age) % SHORT_TERM_SUB_BLOCKS;
+                self.recent[index]
+            })
+            .sum();
+        Some(sum / sub_blocks as f64)
+    }
+}
+
+/// Layout of the sample data in a WAV file.
+struct WavFormat {
+    format: AudioFormat,
+    channels: usize,
+    sample_rate: u32,
+    data_len: u64,
+}
+
+/// Reads a little-endian u16 from a chunk body.
+fn le_u16(bytes: &[u8], at: usize) -> u16 {
+    u16::from_le_bytes([bytes[at], bytes[at + 1]])
+}
+
+/// Reads a little-endian u32 from a chunk body.
+fn le_u32(bytes: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]])
+}
+
+/// Longest part of a `fmt ` chunk that is read; the rest is skipped.
+const WAV_FMT_MAX_LEN: usize = 64;
+
+/// Discards `len` bytes of `reader` without buffering them.
+fn skip_bytes(reader: &mut impl Read, len: u64) -> Result<(), Error> {
+    let skipped = std::io::copy(&mut (&mut *reader).take(len), &mut std::io::sink())?;
+    if skipped < len {
+        return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into());
+    }
+    Ok(())
+}
+
+/// Parses the RIFF header up to the start of the `data` chunk.
+fn read_wav_header(reader: &mut impl Read) -> Result<WavFormat, Error> {
+    let invalid = |what: &str| Error::AudioError(format!("Invalid WAV file: {}", what));
+
+    let mut riff = [0u8; 12];
+    reader.read_exact(&mut riff)?;
+    if &riff[..4] != b"RIFF" || &riff[8..] != b"WAVE" {
+        return Err(invalid("missing RIFF/WAVE header"));
+    }
+
+    let mut format = None;
+    loop {
+        let mut header = [0u8; 8];
+        reader.read_exact(&mut header)?;
+        let len = le_u32(&header, 4) as usize;
+
+        if &header[..4] == b"data" {
+            let (format, channels, sample_rate) =
+                format.ok_or_else(|| invalid("data before fmt"))?;
+            return Ok(WavFormat {
+                format,
+                channels,
+                sample_rate,
+                data_len: len as u64,
+            });
+        }
+
+        // Chunks are padded to an even length
+        let padded = len as u64 + len as u64 % 2;
+        if &header[..4] != b"fmt " {
+            skip_bytes(reader, padded)?;
+            continue;
+        }
+        if len < 16 {
+            return Err(invalid("short fmt chunk"));
+        }
+        let mut body = [0u8; WAV_FMT_MAX_LEN];
+        let kept = len.min(WAV_FMT_MAX_LEN);
+        reader.read_exact(&mut body[..kept])?;
+        skip_bytes(reader, padded - kept as u64)?;
+
+        let mut tag = le_u16(&body, 0);
+        let channels = le_u16(&body, 2) as usize;
+        let sample_rate = le_u32(&body, 4);
+        let block_align = le_u16(&body, 12) as usize;
+        let bits = le_u16(&body, 14);
+        let mut valid_bits = bits;
+        // WAVE_FORMAT_EXTENSIBLE: the real tag opens the sub-format GUID
+        if tag == 0xFFFE && len >= 40 {
+            valid_bits = le_u16(&body, 18);
+            tag = le_u16(&body, 24);
+        }
+        if channels == 0 || block_align != channels * (bits as usize).div_ceil(8) {
+            return Err(invalid("inconsistent fmt chunk"));
+        }
+
+        let sample_format = match (tag, bits, valid_bits) {
+            (1, 8, _) => AudioFormat::U8,
+            (1, 16, _) => AudioFormat::S16LE,
+            (1, 24, _) => AudioFormat::S24LE,
+            (1, 32, 24) => AudioFormat::S24_32LE,
+            (1, 32, _) => AudioFormat::S32LE,
+            (3, 32, _) => AudioFormat::F32LE,
+            (3, 64, _) => AudioFormat::F64LE,
+            (6, 8, _) => AudioFormat::ALaw,
+            (7, 8, _) => AudioFormat::MuLaw,
+            _ => {
+                return Err(Error::AudioError(format!(
+                    "Unsupported WAV format: tag {}, {} bits",
+                    tag, bits
+                )))
+            }
+        };
+        format = Some((sample_format, channels, sample_rate));
+    }
+}
+
+/// Reads until `buf` is full or the input ends, returning the bytes read.
+fn read_full(reader: &mut impl Read, buf: &mut [u8]) -> Result<usize, Error> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(filled)
+}
+
+/// Measures the loudness of a whole WAV file.
+///
+/// Integer PCM, float, A-law and μ-law files are supported. The file is
+/// streamed, so its length is not limited by memory.
+pub fn measure_file(path: impl AsRef<Path>) -> Result<LoudnessReading, Error> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let wav = read_wav_header(&mut reader)?;
+    let mut data = reader.take(wav.data_len);
+
+    let frame_bytes = wav.format.bytes_per_sample() * wav.channels;
+    let mut bytes = vec![0u8; FILE_CHUNK_FRAMES * frame_bytes];
+    let mut samples = vec![0.0f32; FILE_CHUNK_FRAMES * wav.channels];
+    let mut meter = LoudnessMeter::new(wav.channels, wav.sample_rate);
+    loop {
+        let len = read_full(&mut data, &mut bytes)?;
+        let len = len - len % frame_bytes;
+        if len == 0 {
+            break;
+        }
+        let decoded = with_sample_format!(wav.format, F => {
+            SampleSlice::<F>::split_whole(&bytes[..len]).0.decode_into(&mut samples)
+        });
+        meter.process(&samples[..decoded]);
+    }
+    Ok(meter.reading())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::test_util::{self, SAMPLE_RATE};
+
+    /// Interleaved 1 kHz sine at `dbfs` peak level on every channel.
+    fn sine(dbfs: f64, seconds: f64, channels: usize) -> Vec<f32> {
+        let amplitude = 10f64.powf(dbfs / 20.0);
+        let frames = (seconds * SAMPLE_RATE) as usize;
+        test_util::sine(1000.0, amplitude, 0.0, frames, channels)
+    }
+
+    #[test]
+    fn test_stereo_sine_reads_minus_23() {
+        // EBU Tech 3341 case 1
+        let mut meter = LoudnessMeter::new(2, 48_000);
+        meter.process(&sine(-23.0, 20.0, 2));
+        let reading = meter.reading();
+
+        assert!((reading.momentary_lufs + 23.0).abs() < 0.1, "{:?}", reading);
+        assert!(
+            (reading.short_term_lufs + 23.0).abs() < 0.1,
+            "{:?}",
+            reading
+        );
+        assert!(
+            (reading.integrated_lufs + 23.0).abs() < 0.1,
+            "{:?}",
+            reading
+        );
+        assert!(reading.loudness_range_lu < 0.2, "{:?}", reading);
+    }
+
+    #[test]
+    fn test_relative_gate_and_range() {
+        // EBU Tech 3341 case 3: the quiet parts fall below the relative gate
+        let mut meter = LoudnessMeter::new(2, 48_000);
+        meter.process(&sine(-36.0, 10.0, 2));
+        meter.process(&sine(-23.0, 20.0, 2));
+        meter.process(&sine(-36.0, 10.0, 2));
+        assert!((meter.integrated_lufs() + 23.0).abs() < 0.1);
+
+        // EBU Tech 3342 case 1
+        meter.reset();
+        assert_eq!(meter.reading(), LoudnessReading::default());
+        meter.process(&sine(-20.0, 20.0, 2));
+        meter.process(&sine(-30.0, 20.0, 2));
+        let range = meter.loudness_range_lu();
+        assert!((range - 10.0).abs() < 1.0, "{}", range);
+    }
+
+    #[test]
+    fn test_silence_is_gated() {
+        let mut meter = LoudnessMeter::new(1, 44_100);
+        meter.process(&[0.0; 44_100]);
+        assert_eq!(meter.integrated_lufs(), f64::NEG_INFINITY);
+        assert_eq!(meter.momentary_lufs(), f64::NEG_INFINITY);
+    }
+
+    #[test]
+    fn test_measure_wav_file() {
+        let samples = sine(-23.0, 5.0, 2);
+        let data: Vec<u8> = samples
+            .iter()
+            .flat_map(|&s| ((s * 32768.0).round() as i16).to_le_bytes())
+            .collect();
+
+        let mut wav = Vec::new();
+        wav.extend_from_slice(b"RIFF");
+        wav.extend_from_slice(&(36 + data.len() as u32).to_le_bytes());
+        wav.extend_from_slice(b"WAVEfmt ");
+        wav.extend_from_slice(&16u32.to_le_bytes());
+        for field in [1u16, 2] {
+            wav.extend_from_slice(&field.to_le_bytes());
+        }
+        wav.extend_from_slice(&48_000u32.to_le_bytes());
+        wav.extend_from_slice(&(48_000u32 * 4).to_le_bytes());
+        for field in [4u16, 16] {
+            wav.extend_from_slice(&field.to_le_bytes());
+        }
+        wav.extend_from_slice(b"data");
+        wav.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        wav.extend_from_slice(&data);
+
+        let path = std::env::temp_dir().join(format!("vac-loudness-{}.wav", std::process::id()));
+        std::fs::write(&path, &wav).unwrap();
+        let reading = measure_file(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        let reading = reading.unwrap();
+        assert!(
+            (reading.integrated_lufs + 23.0).abs() < 0.1,
+            "{:?}",
+            reading
+        );
+
+        assert!(matches!(
+            read_wav_header(&mut &b"RIFF\0\0\0\0AVI "[..]),
+            Err(Error::AudioError(_))
+        ));
+    }
+
+    #[test]
+    fn test_wav_header_skips_chunks() {
+        let mut wav = b"RIFF\0\0\0\0WAVE".to_vec();
+        // An odd-length chunk is padded to an even length
+        wav.extend_from_slice(b"LIST\x03\0\0\0abc\0");
+        wav.extend_from_slice(b"fmt \x12\0\0\0");
+        for field in [3u16, 1] {
+            wav.extend_from_slice(&field.to_le_bytes());
+        }
+        wav.extend_from_slice(&44_100u32.to_le_bytes());
+        wav.extend_from_slice(&(44_100u32 * 4).to_le_bytes());
+        for field in [4u16, 32, 0] {
+            wav.extend_from_slice(&field.to_le_bytes());
+        }
+        wav.extend_from_slice(b"data\x08\0\0\0");
+        let header = read_wav_header(&mut wav.as_slice()).unwrap();
+        assert_eq!(header.format, AudioFormat::F32LE);
+        assert_eq!((header.channels, header.sample_rate), (1, 44_100));
+        assert_eq!(header.data_len, 8);
+
+        // A huge junk chunk in a truncated file fails without buffering it
+        let mut wav = b"RIFF\0\0\0\0WAVE".to_vec();
+        wav.extend_from_slice(b"junk\xf0\xff\xff\xff");
+        wav.extend_from_slice(&[0; 100]);
+        assert!(matches!(
+            read_wav_header(&mut wav.as_slice()),
+            Err(Error::IoError(_))
+        ));
+    }
+}
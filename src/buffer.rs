@@ -3,87 +3,525 @@
 //! This module provides thread-safe, lock-free ring buffers optimized
 //! for real-time audio processing.
 
-use crate::Error;
-use std::sync::atomic::{AtomicUsize, Ordering};
+use crate::audio::{
+    AudioProcessor, GainControl, GainStage, LoudnessMeter, LoudnessReading, ProcessingStage,
+    VariableResampler,
+};
+use crate::effect::{AudioEffect, EffectChain};
+use crate::meter::{ChannelLevels, LevelMeter};
+use crate::{CableConfig, Error};
+use std::any::Any;
+use std::cell::UnsafeCell;
+use std::collections::VecDeque;
+use std::future::poll_fn;
+use std::sync::atomic::{fence, AtomicU32, AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::task::{Poll, Wake, Waker};
+use std::thread::{self, Thread};
+use std::time::{Duration, Instant};
+
+#[cfg(target_os = "linux")]
+mod shm;
+#[cfg(target_os = "linux")]
+pub use shm::SharedRingBuffer;
+
+/// What a write does when the buffer cannot hold all incoming samples.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OverrunPolicy {
+    /// Keep the buffered samples and drop the incoming ones that do not fit.
+    #[default]
+    DropNewest,
+
+    /// Discard the oldest buffered samples to make room for the new ones.
+    ///
+    /// Only an exclusive [`RingBuffer::write`] can move the read cursor, so a
+    /// split [`Producer`] falls back to [`OverrunPolicy::DropNewest`].
+    OverwriteOldest,
+
+    /// Wait up to the given time for the reader to make room, then drop.
+    ///
+    /// Only a split [`Producer`] can wait for a concurrent reader; an
+    /// exclusive [`RingBuffer::write`] drops immediately.
+    Block(Duration),
+}
+
+/// How a read fills the part of the output the buffer could not provide.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum UnderrunPolicy {
+    /// Leave the rest of the output untouched.
+    #[default]
+    Partial,
+
+    /// Fill the rest of the output with silence.
+    ZeroFill,
+
+    /// Repeat the last frame that was read.
+    RepeatLastFrame,
+
+    /// Ramp the last frame that was read down to silence.
+    FadeToSilence,
+}
+
+/// No waker registered and nobody waking.
+const WAKER_WAITING: usize = 0;
+
+/// A waker is being registered.
+const WAKER_REGISTERING: usize = 1;
+
+/// A waker is being taken to be woken.
+const WAKER_WAKING: usize = 2;
+
+/// A single waker slot that can be woken without taking a lock.
+///
+/// Waking is a single atomic operation when nobody is waiting, so the
+/// real-time side of a buffer can call [`AtomicWaker::wake`] after every
+/// cursor update.
+struct AtomicWaker {
+    state: AtomicUsize,
+    waker: UnsafeCell<Option<Waker>>,
+}
+
+// SAFETY: `waker` is only accessed by whoever moved `state` away from
+// `WAKER_WAITING`, so it is never accessed concurrently.
+unsafe impl Sync for AtomicWaker {}
+
+impl AtomicWaker {
+    fn new() -> Self {
+        Self {
+            state: AtomicUsize::new(WAKER_WAITING),
+            waker: UnsafeCell::new(None),
+        }
+    }
+
+    /// Registers `waker` to be woken by the next [`AtomicWaker::wake`].
+    fn register(&self, waker: &Waker) {
+        match self.state.compare_exchange(
+            WAKER_WAITING,
+            WAKER_REGISTERING,
+            Ordering::Acquire,
+            Ordering::Acquire,
+        ) {
+            Ok(_) => {
+                // SAFETY: we hold the REGISTERING state.
+                unsafe {
+                    match &mut *self.waker.get() {
+                        Some(old) if old.will_wake(waker) => {}
+                        slot => *slot = Some(waker.clone()),
+                    }
+                }
+
+                if self
+                    .state
+                    .compare_exchange(
+                        WAKER_REGISTERING,
+                        WAKER_WAITING,
+                        Ordering::AcqRel,
+                        Ordering::Acquire,
+                    )
+                    .is_err()
+                {
+                    // A wake arrived while registering; deliver it now.
+                    // SAFETY: the waker backs off while REGISTERING is set.
+                    let waker = unsafe { (*self.waker.get()).take() };
+                    self.state.swap(WAKER_WAITING, Ordering::AcqRel);
+                    if let Some(waker) = waker {
+                        waker.wake();
+                    }
+                }
+            }
+            // A wake is in progress; make sure this task polls again
+            Err(WAKER_WAKING) => waker.wake_by_ref(),
+            Err(_) => {}
+        }
+    }
+
+    /// Wakes the registered waker, if any.
+    fn wake(&self) {
+        if self.state.fetch_or(WAKER_WAKING, Ordering::AcqRel) == WAKER_WAITING {
+            // SAFETY: we moved the state from WAITING to WAKING.
+            let waker = unsafe { (*self.waker.get()).take() };
+            self.state.fetch_and(!WAKER_WAKING, Ordering::Release);
+            if let Some(waker) = waker {
+                waker.wake();
+            }
+        }
+    }
+}
+
+/// Wakes a parked thread; lets blocking calls reuse [`AtomicWaker`].
+struct ThreadWaker(Thread);
+
+impl ThreadWaker {
+    /// Returns a waker that unparks the calling thread.
+    fn current() -> Waker {
+        Waker::from(Arc::new(ThreadWaker(thread::current())))
+    }
+}
+
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+/// Sample types that can be scaled by a gain factor.
+///
+/// Needed to set an [`UnderrunPolicy`], since
+/// [`UnderrunPolicy::FadeToSilence`] scales the last frame.
+pub trait Attenuate: Copy + Default {
+    /// Returns the sample multiplied by `gain` (0.0-1.0).
+    fn attenuate(self, gain: f32) -> Self;
+}
+
+impl Attenuate for f32 {
+    fn attenuate(self, gain: f32) -> Self {
+        self * gain
+    }
+}
+
+impl Attenuate for f64 {
+    fn attenuate(self, gain: f32) -> Self {
+        self * gain as f64
+    }
+}
+
+impl Attenuate for i16 {
+    fn attenuate(self, gain: f32) -> Self {
+        (self as f32 * gain) as i16
+    }
+}
+
+impl Attenuate for i32 {
+    fn attenuate(self, gain: f32) -> Self {
+        (self as f64 * gain as f64) as i32
+    }
+}
 
 /// A simple ring buffer for audio samples.
 ///
 /// This buffer is lock-free and suitable for real-time audio processing.
+/// Use [`RingBuffer::split`] to obtain a [`Producer`] and a [`Consumer`]
+/// that can be moved to different threads.
 pub struct RingBuffer<T> {
-    data: Vec<T>,
+    data: Box<[UnsafeCell<T>]>,
     write_pos: AtomicUsize,
     read_pos: AtomicUsize,
     capacity: usize,
-    mask: usize,
+    frame_len: usize,
+    overrun_policy: OverrunPolicy,
+    underrun_policy: UnderrunPolicy,
+    /// Scales a sample for [`UnderrunPolicy::FadeToSilence`]; set together
+    /// with the policy
+    attenuate: Option<fn(T, f32) -> T>,
+    overruns: AtomicU64,
+    underruns: AtomicU64,
+    /// Woken when samples are published
+    data_waker: AtomicWaker,
+    /// Woken when samples are released
+    space_waker: AtomicWaker,
 }
 
+// SAFETY: Slots are only written through `&mut RingBuffer` or by the single
+// `Producer`, and only slots published by `write_pos` are ever read, so
+// shared access never observes a slot while it is being written.
+unsafe impl<T: Send> Sync for RingBuffer<T> {}
+
 impl<T: Clone + Copy + Default> RingBuffer<T> {
     /// Creates a new ring buffer with the specified capacity.
     ///
     /// The capacity is rounded up to the next power of 2 for efficient indexing.
     pub fn new(capacity: usize) -> Self {
-        let capacity = capacity.next_power_of_two();
-        let mask = capacity - 1;
+        Self::with_exact_capacity(capacity.next_power_of_two())
+    }
+
+    /// Creates a ring buffer holding exactly `capacity` samples.
+    ///
+    /// Cursors grow monotonically and are reduced modulo the capacity, which
+    /// stays exact until they wrap at `usize::MAX` samples.
+    fn with_exact_capacity(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
 
         Self {
-            data: vec![T::default(); capacity],
+            data: (0..capacity)
+                .map(|_| UnsafeCell::new(T::default()))
+                .collect(),
             write_pos: AtomicUsize::new(0),
             read_pos: AtomicUsize::new(0),
             capacity,
-            mask,
+            frame_len: 1,
+            overrun_policy: OverrunPolicy::default(),
+            underrun_policy: UnderrunPolicy::default(),
+            attenuate: None,
+            overruns: AtomicU64::new(0),
+            underruns: AtomicU64::new(0),
+            data_waker: AtomicWaker::new(),
+            space_waker: AtomicWaker::new(),
         }
     }
 
+    /// Sets the policy applied when a write does not fit.
+    pub fn with_overrun_policy(mut self, policy: OverrunPolicy) -> Self {
+        self.overrun_policy = policy;
+        self
+    }
+
+    /// Sets the number of samples per frame (the channel count).
+    ///
+    /// Used by [`UnderrunPolicy::RepeatLastFrame`] and
+    /// [`UnderrunPolicy::FadeToSilence`].
+    pub fn with_frame_len(mut self, frame_len: usize) -> Self {
+        self.frame_len = frame_len.clamp(1, self.capacity);
+        self
+    }
+
+    /// Splits the buffer into a producer and a consumer half.
+    ///
+    /// Both halves are `Send` and can live on different threads; neither
+    /// takes a lock.
+    pub fn split(self) -> (Producer<T>, Consumer<T>) {
+        let last_frame = vec![T::default(); self.frame_len];
+        let buffer = Arc::new(self);
+        (
+            Producer {
+                buffer: Arc::clone(&buffer),
+            },
+            Consumer { buffer, last_frame },
+        )
+    }
+
     /// Writes data into the ring buffer.
     ///
-    /// Returns the number of samples actually written.
+    /// Returns the number of samples actually written. Samples that do not
+    /// fit are handled according to the [`OverrunPolicy`]; with
+    /// [`OverrunPolicy::OverwriteOldest`] every sample is accepted.
     pub fn write(&mut self, samples: &[T]) -> usize {
-        let write_pos = self.write_pos.load(Ordering::Relaxed);
-        let read_pos = self.read_pos.load(Ordering::Acquire);
+        if self.overrun_policy != OverrunPolicy::OverwriteOldest {
+            // SAFETY: `&mut self` guarantees we are the only writer.
+            let written = unsafe { self.write_shared(samples) };
+            if written < samples.len() {
+                self.overruns.fetch_add(1, Ordering::Relaxed);
+            }
+            return written;
+        }
+
+        // Anything beyond the capacity would be overwritten by this same call
+        let kept = &samples[samples.len().saturating_sub(self.capacity)..];
+        let free = self.free_space();
+        if kept.len() < samples.len() || kept.len() > free {
+            self.commit_read(kept.len().saturating_sub(free));
+            self.overruns.fetch_add(1, Ordering::Relaxed);
+        }
+
+        // SAFETY: `&mut self` guarantees we are the only writer.
+        unsafe { self.write_shared(kept) };
+        samples.len()
+    }
+
+    /// Returns the free regions of the buffer as up to two contiguous slices.
+    ///
+    /// Fill the slices in order and then publish the samples with
+    /// [`RingBuffer::commit_write`]. The second slice is empty unless the
+    /// free region wraps around the end of the buffer.
+    pub fn write_slices(&mut self) -> (&mut [T], &mut [T]) {
+        // SAFETY: `&mut self` guarantees we are the only writer.
+        unsafe { self.write_slices_shared() }
+    }
+
+    /// Publishes `count` samples previously written through
+    /// [`RingBuffer::write_slices`].
+    ///
+    /// `count` is clamped to the free space of the buffer.
+    pub fn commit_write(&mut self, count: usize) {
+        self.commit_write_shared(count);
+    }
 
-        let available = self.capacity - (write_pos - read_pos);
-        let to_write = samples.len().min(available);
+    /// Writes from a shared reference.
+    ///
+    /// # Safety
+    ///
+    /// The caller must be the only writer for the duration of the call.
+    unsafe fn write_shared(&self, samples: &[T]) -> usize {
+        let (first, second) = self.write_slices_shared();
+        let to_write = samples.len().min(first.len() + second.len());
 
         if to_write == 0 {
             return 0;
         }
 
-        for (i, &sample) in samples.iter().take(to_write).enumerate() {
-            let idx = (write_pos + i) & self.mask;
-            self.data[idx] = sample;
-        }
+        let head = to_write.min(first.len());
+        first[..head].copy_from_slice(&samples[..head]);
+        second[..to_write - head].copy_from_slice(&samples[head..to_write]);
 
-        self.write_pos
-            .store(write_pos + to_write, Ordering::Release);
+        self.commit_write_shared(to_write);
         to_write
     }
 
+    /// Returns the free regions from a shared reference.
+    ///
+    /// # Safety
+    ///
+    /// The caller must be the only writer while the slices are alive.
+    #[allow(clippy::mut_from_ref)]
+    unsafe fn write_slices_shared(&self) -> (&mut [T], &mut [T]) {
+        let write_pos = self.write_pos.load(Ordering::Relaxed);
+        let read_pos = self.read_pos.load(Ordering::Acquire);
+        let free = self.capacity - (write_pos - read_pos);
+
+        let (start, first_len, second_len) = self.regions(write_pos, free);
+        let base = UnsafeCell::raw_get(self.data.as_ptr());
+        (
+            std::slice::from_raw_parts_mut(base.add(start), first_len),
+            std::slice::from_raw_parts_mut(base, second_len),
+        )
+    }
+
+    fn commit_write_shared(&self, count: usize) {
+        let write_pos = self.write_pos.load(Ordering::Relaxed);
+        let read_pos = self.read_pos.load(Ordering::Acquire);
+        let free = self.capacity - (write_pos - read_pos);
+        debug_assert!(count <= free, "committed more samples than were free");
+
+        self.write_pos
+            .store(write_pos + count.min(free), Ordering::Release);
+        self.data_waker.wake();
+    }
+
     /// Reads data from the ring buffer.
     ///
-    /// Returns the number of samples actually read.
+    /// Returns the number of samples actually read. If fewer samples than
+    /// requested are available, an underrun is counted and the rest of the
+    /// output is filled according to the [`UnderrunPolicy`].
     pub fn read(&self, output: &mut [T]) -> usize {
-        let read_pos = self.read_pos.load(Ordering::Relaxed);
-        let write_pos = self.write_pos.load(Ordering::Acquire);
+        let read = self.read_available(output);
+        if read < output.len() {
+            let read_pos = self.read_pos.load(Ordering::Relaxed);
+            let frame_start = read_pos.wrapping_sub(self.frame_len);
+            self.fill_underrun(&mut output[read..], |i| {
+                if read_pos < self.frame_len {
+                    return T::default();
+                }
+                // SAFETY: only an exclusive writer can touch the slots behind
+                // the read cursor, and it cannot run while `&self` is held.
+                unsafe { *self.data[(frame_start + i) % self.capacity].get() }
+            });
+        }
+        read
+    }
 
-        let available = write_pos - read_pos;
-        let to_read = output.len().min(available);
+    /// Copies as many samples as are available, without underrun handling.
+    fn read_available(&self, output: &mut [T]) -> usize {
+        let (first, second) = self.read_slices();
+        let to_read = output.len().min(first.len() + second.len());
 
         if to_read == 0 {
             return 0;
         }
 
-        for (i, out) in output.iter_mut().take(to_read).enumerate() {
-            let idx = (read_pos + i) & self.mask;
-            *out = self.data[idx];
-        }
+        let head = to_read.min(first.len());
+        output[..head].copy_from_slice(&first[..head]);
+        output[head..to_read].copy_from_slice(&second[..to_read - head]);
 
-        self.read_pos.store(read_pos + to_read, Ordering::Release);
+        self.commit_read(to_read);
         to_read
     }
 
+    /// Counts an underrun and fills `missing` according to the policy.
+    ///
+    /// `last_sample(i)` returns the `i`-th sample of the last frame read.
+    fn fill_underrun(&self, missing: &mut [T], last_sample: impl Fn(usize) -> T) {
+        self.underruns.fetch_add(1, Ordering::Relaxed);
+
+        match self.underrun_policy {
+            UnderrunPolicy::Partial => {}
+            UnderrunPolicy::ZeroFill => missing.fill(T::default()),
+            UnderrunPolicy::RepeatLastFrame => {
+                for (i, out) in missing.iter_mut().enumerate() {
+                    *out = last_sample(i % self.frame_len);
+                }
+            }
+            UnderrunPolicy::FadeToSilence => {
+                let Some(attenuate) = self.attenuate else {
+                    missing.fill(T::default());
+                    return;
+                };
+                let frames = missing.len().div_ceil(self.frame_len);
+                for (i, out) in missing.iter_mut().enumerate() {
+                    let gain = 1.0 - (i / self.frame_len + 1) as f32 / frames as f32;
+                    *out = attenuate(last_sample(i % self.frame_len), gain);
+                }
+            }
+        }
+    }
+
+    /// Returns the readable samples as up to two contiguous slices.
+    ///
+    /// The samples stay in the buffer until they are released with
+    /// [`RingBuffer::commit_read`].
+    pub fn read_slices(&self) -> (&[T], &[T]) {
+        let read_pos = self.read_pos.load(Ordering::Relaxed);
+        let write_pos = self.write_pos.load(Ordering::Acquire);
+
+        let (start, first_len, second_len) = self.regions(read_pos, write_pos - read_pos);
+        let base = UnsafeCell::raw_get(self.data.as_ptr()) as *const T;
+        // SAFETY: slots between `read_pos` and `write_pos` have been
+        // published by the writer and are not written until consumed.
+        unsafe {
+            (
+                std::slice::from_raw_parts(base.add(start), first_len),
+                std::slice::from_raw_parts(base, second_len),
+            )
+        }
+    }
+
+    /// Releases `count` samples previously inspected through
+    /// [`RingBuffer::read_slices`].
+    ///
+    /// `count` is clamped to the number of available samples.
+    pub fn commit_read(&self, count: usize) {
+        let read_pos = self.read_pos.load(Ordering::Relaxed);
+        let write_pos = self.write_pos.load(Ordering::Acquire);
+        let available = write_pos - read_pos;
+        debug_assert!(
+            count <= available,
+            "committed more samples than were available"
+        );
+
+        self.read_pos
+            .store(read_pos + count.min(available), Ordering::Release);
+        self.space_waker.wake();
+    }
+
+    /// Moves as many samples as fit from `source` into this buffer.
+    ///
+    /// Samples that do not fit stay in `source`, so this is back-pressure
+    /// rather than an overrun and is not counted as one.
+    fn fill_from(&mut self, source: &Self) -> usize {
+        let (first, second) = source.read_slices();
+        // SAFETY: `&mut self` guarantees we are the only writer.
+        let mut moved = unsafe { self.write_shared(first) };
+        if moved == first.len() {
+            // SAFETY: as above.
+            moved += unsafe { self.write_shared(second) };
+        }
+        source.commit_read(moved);
+        moved
+    }
+
+    /// Splits `len` slots starting at `pos` into (start index, first length,
+    /// second length).
+    fn regions(&self, pos: usize, len: usize) -> (usize, usize, usize) {
+        let start = pos % self.capacity;
+        let first_len = len.min(self.capacity - start);
+        (start, first_len, len - first_len)
+    }
+
     /// Returns the number of samples available for reading.
     pub fn available(&self) -> usize {
-        let write_pos = self.write_pos.load(Ordering::Relaxed);
+        let write_pos = self.write_pos.load(Ordering::Acquire);
         let read_pos = self.read_pos.load(Ordering::Acquire);
         write_pos - read_pos
     }
@@ -93,10 +531,58 @@ impl<T: Clone + Copy + Default> RingBuffer<T> {
         self.capacity - self.available()
     }
 
+    /// Returns the total capacity of the buffer in samples.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
     /// Clears the buffer.
     pub fn clear(&self) {
-        let write_pos = self.write_pos.load(Ordering::Relaxed);
+        let write_pos = self.write_pos.load(Ordering::Acquire);
         self.read_pos.store(write_pos, Ordering::Release);
+        self.space_waker.wake();
+    }
+
+    /// Returns the total number of samples written since creation.
+    pub fn write_position(&self) -> u64 {
+        self.write_pos.load(Ordering::Acquire) as u64
+    }
+
+    /// Returns the total number of samples consumed since creation.
+    ///
+    /// Samples discarded by [`RingBuffer::clear`] or
+    /// [`OverrunPolicy::OverwriteOldest`] count as consumed.
+    pub fn read_position(&self) -> u64 {
+        self.read_pos.load(Ordering::Acquire) as u64
+    }
+
+    /// Returns the policy applied when a write does not fit.
+    pub fn overrun_policy(&self) -> OverrunPolicy {
+        self.overrun_policy
+    }
+
+    /// Returns the policy applied when a read cannot be satisfied.
+    pub fn underrun_policy(&self) -> UnderrunPolicy {
+        self.underrun_policy
+    }
+
+    /// Returns the number of writes that could not store all their samples.
+    pub fn overruns(&self) -> u64 {
+        self.overruns.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of reads that could not fill their output.
+    pub fn underruns(&self) -> u64 {
+        self.underruns.load(Ordering::Relaxed)
+    }
+}
+
+impl<T: Attenuate> RingBuffer<T> {
+    /// Sets the policy applied when a read cannot be satisfied.
+    pub fn with_underrun_policy(mut self, policy: UnderrunPolicy) -> Self {
+        self.underrun_policy = policy;
+        self.attenuate = Some(T::attenuate);
+        self
     }
 }
 
@@ -107,54 +593,1730 @@ where
     fn default() -> Self {
         Self::new(1024)
     }
-}
+}
+
+/// Operations shared by the sample ring buffers.
+///
+/// Lets code that only moves samples in and out, such as
+/// [`AudioProcessor::decode_into_buffer`], run on either a heap-backed
+/// [`RingBuffer`] or an allocation-free [`HeaplessRingBuffer`].
+///
+/// [`AudioProcessor::decode_into_buffer`]: crate::audio::AudioProcessor::decode_into_buffer
+pub trait SampleBuffer<T> {
+    /// Writes samples and returns how many were accepted.
+    fn write(&mut self, samples: &[T]) -> usize;
+
+    /// Reads samples and returns how many were read.
+    fn read(&mut self, output: &mut [T]) -> usize;
+
+    /// Returns the number of samples available for reading.
+    fn available(&self) -> usize;
+
+    /// Returns the number of samples that can be written.
+    fn free_space(&self) -> usize;
+
+    /// Returns the total capacity in samples.
+    fn capacity(&self) -> usize;
+
+    /// Discards all buffered samples.
+    fn clear(&mut self);
+}
+
+impl<T: Clone + Copy + Default> SampleBuffer<T> for RingBuffer<T> {
+    fn write(&mut self, samples: &[T]) -> usize {
+        RingBuffer::write(self, samples)
+    }
+
+    fn read(&mut self, output: &mut [T]) -> usize {
+        RingBuffer::read(self, output)
+    }
+
+    fn available(&self) -> usize {
+        RingBuffer::available(self)
+    }
+
+    fn free_space(&self) -> usize {
+        RingBuffer::free_space(self)
+    }
+
+    fn capacity(&self) -> usize {
+        RingBuffer::capacity(self)
+    }
+
+    fn clear(&mut self) {
+        RingBuffer::clear(self)
+    }
+}
+
+/// A fixed-capacity ring buffer that never allocates.
+///
+/// Storage is an inline `heapless::spsc::Queue`, so the buffer can live on
+/// the stack or in a `static`. The queue keeps one slot free, so the buffer
+/// holds `N - 1` samples; use a power of 2 for `N` for cheap indexing.
+/// Reads that come up short leave the rest of the output untouched, like
+/// [`UnderrunPolicy::Partial`], and writes that do not fit drop the newest
+/// samples, like [`OverrunPolicy::DropNewest`].
+pub struct HeaplessRingBuffer<T, const N: usize> {
+    queue: heapless::spsc::Queue<T, N>,
+    overruns: u64,
+    underruns: u64,
+}
+
+impl<T: Copy, const N: usize> HeaplessRingBuffer<T, N> {
+    /// Creates an empty buffer.
+    pub const fn new() -> Self {
+        Self {
+            queue: heapless::spsc::Queue::new(),
+            overruns: 0,
+            underruns: 0,
+        }
+    }
+
+    /// Splits the buffer into lock-free producer and consumer halves.
+    pub fn split(
+        &mut self,
+    ) -> (
+        heapless::spsc::Producer<'_, T, N>,
+        heapless::spsc::Consumer<'_, T, N>,
+    ) {
+        self.queue.split()
+    }
+
+    /// Returns the number of writes that could not store all their samples.
+    pub fn overruns(&self) -> u64 {
+        self.overruns
+    }
+
+    /// Returns the number of reads that could not fill their output.
+    pub fn underruns(&self) -> u64 {
+        self.underruns
+    }
+}
+
+impl<T: Copy, const N: usize> Default for HeaplessRingBuffer<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Copy, const N: usize> SampleBuffer<T> for HeaplessRingBuffer<T, N> {
+    fn write(&mut self, samples: &[T]) -> usize {
+        let to_write = samples.len().min(self.free_space());
+        for &sample in &samples[..to_write] {
+            // Cannot fail: the free space was checked above
+            let _ = self.queue.enqueue(sample);
+        }
+        if to_write < samples.len() {
+            self.overruns += 1;
+        }
+        to_write
+    }
+
+    fn read(&mut self, output: &mut [T]) -> usize {
+        let to_read = output.len().min(self.queue.len());
+        for out in &mut output[..to_read] {
+            if let Some(sample) = self.queue.dequeue() {
+                *out = sample;
+            }
+        }
+        if to_read < output.len() {
+            self.underruns += 1;
+        }
+        to_read
+    }
+
+    fn available(&self) -> usize {
+        self.queue.len()
+    }
+
+    fn free_space(&self) -> usize {
+        self.queue.capacity() - self.queue.len()
+    }
+
+    fn capacity(&self) -> usize {
+        self.queue.capacity()
+    }
+
+    fn clear(&mut self) {
+        while self.queue.dequeue().is_some() {}
+    }
+}
+
+/// Writing half of a split [`RingBuffer`].
+///
+/// Created by [`RingBuffer::split`]. There is exactly one producer per
+/// buffer, so writes never contend with each other.
+pub struct Producer<T> {
+    buffer: Arc<RingBuffer<T>>,
+}
+
+impl<T: Clone + Copy + Default> Producer<T> {
+    /// Writes data into the ring buffer.
+    ///
+    /// Returns the number of samples actually written. With
+    /// [`OverrunPolicy::Block`] this waits for the consumer to make room
+    /// until the timeout expires.
+    pub fn write(&mut self, samples: &[T]) -> usize {
+        let written = match self.buffer.overrun_policy {
+            OverrunPolicy::Block(timeout) => self.write_all_timeout(samples, timeout),
+            // SAFETY: the producer is unique and `&mut self` serializes its writes.
+            _ => unsafe { self.buffer.write_shared(samples) },
+        };
+
+        if written < samples.len() {
+            self.buffer.overruns.fetch_add(1, Ordering::Relaxed);
+        }
+        written
+    }
+
+    /// Writes all samples, parking the thread until the consumer makes room
+    /// or the timeout expires.
+    ///
+    /// Returns the number of samples actually written. Unlike
+    /// [`Producer::write`], a short write is not counted as an overrun.
+    pub fn write_all_timeout(&mut self, samples: &[T], timeout: Duration) -> usize {
+        let deadline = Instant::now() + timeout;
+        // Only allocated if the first write comes up short
+        let mut waker = None;
+        let mut written = 0;
+
+        loop {
+            // SAFETY: the producer is unique and `&mut self` serializes its writes.
+            written += unsafe { self.buffer.write_shared(&samples[written..]) };
+            if written == samples.len() {
+                return written;
+            }
+
+            // Register before re-checking so a release in between is not lost
+            let waker = waker.get_or_insert_with(ThreadWaker::current);
+            self.buffer.space_waker.register(waker);
+            if self.buffer.free_space() > 0 {
+                continue;
+            }
+
+            let now = Instant::now();
+            if now >= deadline {
+                return written;
+            }
+            thread::park_timeout(deadline - now);
+        }
+    }
+
+    /// Waits until there is free space, then writes as many samples as fit.
+    ///
+    /// Returns the number of samples written, which is only zero if
+    /// `samples` is empty. Short writes are not counted as overruns.
+    pub async fn write_async(&mut self, samples: &[T]) -> usize {
+        if samples.is_empty() {
+            return 0;
+        }
+
+        let buffer = &self.buffer;
+        poll_fn(|cx| {
+            // SAFETY: the producer is unique and `&mut self` serializes its writes.
+            let written = unsafe { buffer.write_shared(samples) };
+            if written > 0 {
+                return Poll::Ready(written);
+            }
+
+            buffer.space_waker.register(cx.waker());
+            // SAFETY: as above.
+            match unsafe { buffer.write_shared(samples) } {
+                0 => Poll::Pending,
+                written => Poll::Ready(written),
+            }
+        })
+        .await
+    }
+
+    /// Waits until every sample has been written.
+    pub async fn write_all_async(&mut self, samples: &[T]) {
+        let mut written = 0;
+        while written < samples.len() {
+            written += self.write_async(&samples[written..]).await;
+        }
+    }
+
+    /// Returns the free regions of the buffer as up to two contiguous slices.
+    ///
+    /// See [`RingBuffer::write_slices`].
+    pub fn write_slices(&mut self) -> (&mut [T], &mut [T]) {
+        // SAFETY: the producer is unique and the slices borrow it mutably.
+        unsafe { self.buffer.write_slices_shared() }
+    }
+
+    /// Publishes `count` samples written through [`Producer::write_slices`].
+    pub fn commit_write(&mut self, count: usize) {
+        self.buffer.commit_write_shared(count);
+    }
+
+    /// Returns the number of samples available for reading.
+    pub fn available(&self) -> usize {
+        self.buffer.available()
+    }
+
+    /// Returns the amount of free space in the buffer.
+    pub fn free_space(&self) -> usize {
+        self.buffer.free_space()
+    }
+
+    /// Returns the total capacity of the buffer in samples.
+    pub fn capacity(&self) -> usize {
+        self.buffer.capacity()
+    }
+
+    /// Returns the number of writes that could not store all their samples.
+    pub fn overruns(&self) -> u64 {
+        self.buffer.overruns()
+    }
+}
+
+/// Reading half of a split [`RingBuffer`].
+///
+/// Created by [`RingBuffer::split`].
+pub struct Consumer<T> {
+    buffer: Arc<RingBuffer<T>>,
+    // The producer may reuse the slots behind the read cursor, so the last
+    // frame is kept here for the underrun policies.
+    last_frame: Vec<T>,
+}
+
+impl<T: Clone + Copy + Default> Consumer<T> {
+    /// Reads data from the ring buffer.
+    ///
+    /// Returns the number of samples actually read.
+    pub fn read(&mut self, output: &mut [T]) -> usize {
+        let read = self.read_available(output);
+
+        if read < output.len() {
+            let last_frame = &self.last_frame;
+            self.buffer
+                .fill_underrun(&mut output[read..], |i| last_frame[i]);
+        }
+        read
+    }
+
+    /// Fills `output` completely, parking the thread until enough samples
+    /// arrive or the timeout expires.
+    ///
+    /// Returns the number of samples actually read; on timeout the rest of
+    /// the output is left untouched and no underrun is counted.
+    pub fn read_exact_timeout(&mut self, output: &mut [T], timeout: Duration) -> usize {
+        let deadline = Instant::now() + timeout;
+        // Only allocated if the first read comes up short
+        let mut waker = None;
+        let mut read = 0;
+
+        loop {
+            read += self.read_available(&mut output[read..]);
+            if read == output.len() {
+                return read;
+            }
+
+            // Register before re-checking so a publish in between is not lost
+            let waker = waker.get_or_insert_with(ThreadWaker::current);
+            self.buffer.data_waker.register(waker);
+            if self.buffer.available() > 0 {
+                continue;
+            }
+
+            let now = Instant::now();
+            if now >= deadline {
+                return read;
+            }
+            thread::park_timeout(deadline - now);
+        }
+    }
+
+    /// Waits until samples are available, then reads as many as fit.
+    ///
+    /// Returns the number of samples read, which is only zero if `output`
+    /// is empty. Never counts an underrun.
+    pub async fn read_async(&mut self, output: &mut [T]) -> usize {
+        if output.is_empty() {
+            return 0;
+        }
+
+        poll_fn(|cx| {
+            let read = self.read_available(output);
+            if read > 0 {
+                return Poll::Ready(read);
+            }
+
+            self.buffer.data_waker.register(cx.waker());
+            match self.read_available(output) {
+                0 => Poll::Pending,
+                read => Poll::Ready(read),
+            }
+        })
+        .await
+    }
+
+    /// Waits until `output` has been filled completely.
+    pub async fn read_exact_async(&mut self, output: &mut [T]) {
+        let mut read = 0;
+        while read < output.len() {
+            read += self.read_async(&mut output[read..]).await;
+        }
+    }
+
+    /// Copies the available samples and remembers the last frame read.
+    fn read_available(&mut self, output: &mut [T]) -> usize {
+        let read = self.buffer.read_available(output);
+
+        let frame_len = self.last_frame.len();
+        if read >= frame_len {
+            self.last_frame
+                .copy_from_slice(&output[read - frame_len..read]);
+        } else if read > 0 {
+            self.last_frame.rotate_left(read);
+            self.last_frame[frame_len - read..].copy_from_slice(&output[..read]);
+        }
+        read
+    }
+
+    /// Returns the readable samples as up to two contiguous slices.
+    ///
+    /// See [`RingBuffer::read_slices`].
+    pub fn read_slices(&self) -> (&[T], &[T]) {
+        self.buffer.read_slices()
+    }
+
+    /// Releases `count` samples inspected through [`Consumer::read_slices`].
+    pub fn commit_read(&mut self, count: usize) {
+        self.buffer.commit_read(count);
+    }
+
+    /// Returns the number of samples available for reading.
+    pub fn available(&self) -> usize {
+        self.buffer.available()
+    }
+
+    /// Returns the amount of free space in the buffer.
+    pub fn free_space(&self) -> usize {
+        self.buffer.free_space()
+    }
+
+    /// Returns the total capacity of the buffer in samples.
+    pub fn capacity(&self) -> usize {
+        self.buffer.capacity()
+    }
+
+    /// Discards all samples currently available.
+    pub fn clear(&mut self) {
+        self.buffer.clear();
+    }
+
+    /// Returns the number of reads that could not fill their output.
+    pub fn underruns(&self) -> u64 {
+        self.buffer.underruns()
+    }
+}
+
+/// A ring buffer of interleaved frames that never splits a frame.
+///
+/// Capacity, availability, reads and writes are all counted in frames of
+/// `channels` samples. The underlying storage is a whole number of frames,
+/// so the slices returned by [`FrameRingBuffer::read_slices`] and
+/// [`FrameRingBuffer::write_slices`] always hold whole frames as well.
+pub struct FrameRingBuffer<T> {
+    ring: RingBuffer<T>,
+    channels: usize,
+}
+
+impl<T: Clone + Copy + Default> FrameRingBuffer<T> {
+    /// Creates a new frame buffer.
+    ///
+    /// The frame capacity is rounded up to the next power of 2.
+    pub fn new(capacity_frames: usize, channels: usize) -> Self {
+        let channels = channels.max(1);
+        let capacity_frames = capacity_frames.max(1).next_power_of_two();
+        Self {
+            ring: RingBuffer::with_exact_capacity(capacity_frames * channels)
+                .with_frame_len(channels),
+            channels,
+        }
+    }
+
+    /// Sets the policy applied when a write does not fit.
+    pub fn with_overrun_policy(mut self, policy: OverrunPolicy) -> Self {
+        self.ring = self.ring.with_overrun_policy(policy);
+        self
+    }
+
+    /// Returns the number of samples per frame.
+    pub fn channels(&self) -> usize {
+        self.channels
+    }
+
+    /// Writes interleaved samples into the buffer.
+    ///
+    /// A trailing partial frame in `samples` is ignored. Returns the number
+    /// of frames actually written.
+    pub fn write(&mut self, samples: &[T]) -> usize {
+        let whole = samples.len() - samples.len() % self.channels;
+        self.ring.write(&samples[..whole]) / self.channels
+    }
+
+    /// Reads interleaved samples from the buffer.
+    ///
+    /// Only whole frames are read; a trailing partial frame in `output` is
+    /// left untouched. Returns the number of frames actually read, with the
+    /// rest of the output filled according to the [`UnderrunPolicy`].
+    pub fn read(&self, output: &mut [T]) -> usize {
+        let whole = output.len() - output.len() % self.channels;
+        self.ring.read(&mut output[..whole]) / self.channels
+    }
+
+    /// Returns the free regions as up to two slices of whole frames.
+    ///
+    /// See [`RingBuffer::write_slices`].
+    pub fn write_slices(&mut self) -> (&mut [T], &mut [T]) {
+        self.ring.write_slices()
+    }
+
+    /// Publishes `frames` frames written through
+    /// [`FrameRingBuffer::write_slices`].
+    pub fn commit_write(&mut self, frames: usize) {
+        self.ring.commit_write(frames * self.channels);
+    }
+
+    /// Returns the readable frames as up to two slices of whole frames.
+    ///
+    /// See [`RingBuffer::read_slices`].
+    pub fn read_slices(&self) -> (&[T], &[T]) {
+        self.ring.read_slices()
+    }
+
+    /// Releases `frames` frames inspected through
+    /// [`FrameRingBuffer::read_slices`].
+    pub fn commit_read(&self, frames: usize) {
+        self.ring.commit_read(frames * self.channels);
+    }
+
+    /// Returns the number of frames available for reading.
+    pub fn available_frames(&self) -> usize {
+        self.ring.available() / self.channels
+    }
+
+    /// Returns the number of frames that can be written.
+    pub fn free_frames(&self) -> usize {
+        self.ring.free_space() / self.channels
+    }
+
+    /// Returns the total capacity of the buffer in frames.
+    pub fn capacity_frames(&self) -> usize {
+        self.ring.capacity() / self.channels
+    }
+
+    /// Clears the buffer.
+    pub fn clear(&self) {
+        self.ring.clear();
+    }
+
+    /// Returns the number of writes that could not store all their frames.
+    pub fn overruns(&self) -> u64 {
+        self.ring.overruns()
+    }
+
+    /// Returns the number of reads that could not fill their output.
+    pub fn underruns(&self) -> u64 {
+        self.ring.underruns()
+    }
+
+    /// Returns the total number of frames written since creation.
+    pub fn write_position(&self) -> u64 {
+        self.ring.write_position() / self.channels as u64
+    }
+
+    /// Returns the total number of frames consumed since creation.
+    pub fn read_position(&self) -> u64 {
+        self.ring.read_position() / self.channels as u64
+    }
+
+    /// Moves as many frames as fit from `source` into this buffer.
+    fn fill_from(&mut self, source: &Self) -> usize {
+        debug_assert_eq!(self.channels, source.channels);
+        self.ring.fill_from(&source.ring) / self.channels
+    }
+}
+
+impl<T: Attenuate> FrameRingBuffer<T> {
+    /// Sets the policy applied when a read cannot be satisfied.
+    pub fn with_underrun_policy(mut self, policy: UnderrunPolicy) -> Self {
+        self.ring = self.ring.with_underrun_policy(policy);
+        self
+    }
+}
+
+/// Capture time of a block of frames.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlockTimestamp {
+    /// Stream position of the first frame of the block
+    pub frame: u64,
+    /// When the first frame of the block was captured
+    pub captured_at: Instant,
+}
+
+/// Number of block timestamps reserved up front.
+const TIMESTAMP_QUEUE_CAPACITY: usize = 64;
+
+/// A frame buffer that remembers when each written block was captured.
+///
+/// Every write records the stream position of its first frame together with
+/// a capture time in a side queue. A reader can then ask which capture time
+/// the next frame it reads corresponds to; frames inside a block are placed
+/// at `1 / sample_rate` intervals after the block's timestamp.
+pub struct TimestampedRingBuffer<T> {
+    ring: FrameRingBuffer<T>,
+    sample_rate: u32,
+    blocks: VecDeque<BlockTimestamp>,
+}
+
+impl<T: Clone + Copy + Default> TimestampedRingBuffer<T> {
+    /// Creates a new timestamped buffer.
+    ///
+    /// The frame capacity is rounded up to the next power of 2.
+    pub fn new(capacity_frames: usize, channels: usize, sample_rate: u32) -> Self {
+        Self {
+            ring: FrameRingBuffer::new(capacity_frames, channels),
+            sample_rate: sample_rate.max(1),
+            blocks: VecDeque::with_capacity(TIMESTAMP_QUEUE_CAPACITY),
+        }
+    }
+
+    /// Sets the policy applied when a write does not fit.
+    pub fn with_overrun_policy(mut self, policy: OverrunPolicy) -> Self {
+        self.ring = self.ring.with_overrun_policy(policy);
+        self
+    }
+
+    /// Writes a block of interleaved samples captured at `captured_at`.
+    ///
+    /// Returns the number of frames actually written.
+    pub fn write(&mut self, samples: &[T], captured_at: Instant) -> usize {
+        let frame = self.ring.write_position();
+        let written = self.ring.write(samples);
+        if written > 0 {
+            // With `OverwriteOldest` a block longer than the buffer only
+            // keeps its tail, which was captured later than the block start
+            let kept = self.ring.write_position() - frame;
+            let skipped = (written as u64).saturating_sub(kept);
+            let offset = Duration::from_secs_f64(skipped as f64 / self.sample_rate as f64);
+            self.blocks.push_back(BlockTimestamp {
+                frame,
+                captured_at: captured_at + offset,
+            });
+            // Overwriting moves the read position without a read
+            self.discard_consumed_blocks();
+        }
+        written
+    }
+
+    /// Reads interleaved samples from the buffer.
+    ///
+    /// Returns the number of frames actually read. See
+    /// [`FrameRingBuffer::read`].
+    pub fn read(&mut self, output: &mut [T]) -> usize {
+        let read = self.ring.read(output);
+        self.discard_consumed_blocks();
+        read
+    }
+
+    /// Returns the capture time of the next frame to be read.
+    ///
+    /// Returns `None` if the buffer is empty.
+    pub fn next_timestamp(&self) -> Option<BlockTimestamp> {
+        let frame = self.ring.read_position();
+        if frame >= self.ring.write_position() {
+            return None;
+        }
+
+        let index = self.blocks.partition_point(|block| block.frame <= frame);
+        let block = self.blocks.get(index.checked_sub(1)?)?;
+        let offset =
+            Duration::from_secs_f64((frame - block.frame) as f64 / self.sample_rate as f64);
+        Some(BlockTimestamp {
+            frame,
+            captured_at: block.captured_at + offset,
+        })
+    }
+
+    /// Returns how long ago the next frame to be read was captured.
+    pub fn latency(&self, now: Instant) -> Option<Duration> {
+        self.next_timestamp()
+            .map(|timestamp| now.saturating_duration_since(timestamp.captured_at))
+    }
+
+    /// Returns the total number of frames written since creation.
+    pub fn write_position(&self) -> u64 {
+        self.ring.write_position()
+    }
+
+    /// Returns the total number of frames consumed since creation.
+    pub fn read_position(&self) -> u64 {
+        self.ring.read_position()
+    }
+
+    /// Returns the number of frames available for reading.
+    pub fn available_frames(&self) -> usize {
+        self.ring.available_frames()
+    }
+
+    /// Returns the number of frames that can be written.
+    pub fn free_frames(&self) -> usize {
+        self.ring.free_frames()
+    }
+
+    /// Returns the number of samples per frame.
+    pub fn channels(&self) -> usize {
+        self.ring.channels()
+    }
+
+    /// Returns the sample rate used to place frames inside a block.
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Clears the buffer and its timestamps.
+    pub fn clear(&mut self) {
+        self.ring.clear();
+        self.blocks.clear();
+    }
+
+    /// Drops timestamps of blocks that have been read completely.
+    fn discard_consumed_blocks(&mut self) {
+        let frame = self.ring.read_position();
+        while self.blocks.len() > 1 && self.blocks[1].frame <= frame {
+            self.blocks.pop_front();
+        }
+    }
+}
+
+impl<T: Attenuate> TimestampedRingBuffer<T> {
+    /// Sets the policy applied when a read cannot be satisfied.
+    pub fn with_underrun_policy(mut self, policy: UnderrunPolicy) -> Self {
+        self.ring = self.ring.with_underrun_policy(policy);
+        self
+    }
+}
+
+/// Time constant of the fill-level smoothing, in seconds.
+const JITTER_FILL_SMOOTHING_SECS: f64 = 1.0;
+
+/// Proportional gain of the drift controller, per second of fill error.
+const JITTER_PROPORTIONAL_GAIN: f64 = 0.1;
+
+/// Integral gain of the drift controller, per second squared of fill error.
+const JITTER_INTEGRAL_GAIN: f64 = 0.0025;
+
+/// Largest speed correction the controller applies (2000 ppm).
+const JITTER_MAX_CORRECTION: f64 = 0.002;
+
+/// Longest target latency of a jitter buffer, in seconds.
+const JITTER_MAX_TARGET_SECS: u64 = 10;
+
+/// Adaptive jitter buffer that holds its fill level at a target latency.
+///
+/// A virtual cable connects two clocks, so a plain ring buffer slowly drains
+/// or fills. The jitter buffer compares its smoothed fill level against the
+/// target and nudges a [`VariableResampler`] on the read side so that the
+/// reader consumes slightly faster or slower. Once settled, the correction
+/// it applies is the measured clock drift.
+pub struct JitterBuffer {
+    ring: FrameRingBuffer<f32>,
+    resampler: VariableResampler,
+    sample_rate: f64,
+    target_frames: usize,
+    filtered_fill: f64,
+    integral: f64,
+    primed: bool,
+    underruns: u64,
+}
+
+impl JitterBuffer {
+    /// Creates a new jitter buffer.
+    ///
+    /// Reads return silence until `target_frames` frames have been buffered.
+    /// The target is capped at ten seconds of audio.
+    pub fn new(
+        capacity_frames: usize,
+        channels: usize,
+        sample_rate: u32,
+        target_frames: usize,
+    ) -> Self {
+        let max_target = JITTER_MAX_TARGET_SECS as usize * sample_rate.max(1) as usize;
+        let target_frames = target_frames.clamp(1, max_target);
+        let ring = FrameRingBuffer::new(capacity_frames.max(target_frames * 2), channels);
+        Self {
+            resampler: VariableResampler::new(sample_rate, sample_rate, ring.channels() as u16),
+            ring,
+            sample_rate: sample_rate.max(1) as f64,
+            target_frames,
+            filtered_fill: 0.0,
+            integral: 0.0,
+            primed: false,
+            underruns: 0,
+        }
+    }
+
+    /// Creates a jitter buffer for a cable with the given target latency.
+    pub fn from_config(config: &CableConfig, target_latency_ms: f64) -> Self {
+        let target_frames = (target_latency_ms * config.sample_rate as f64 / 1000.0) as usize;
+        Self::new(
+            config.buffer_size.div_ceil(config.channels.max(1) as usize),
+            config.channels as usize,
+            config.sample_rate,
+            target_frames,
+        )
+    }
+
+    /// Writes interleaved samples. Returns the number of frames written.
+    pub fn write(&mut self, samples: &[f32]) -> usize {
+        self.ring.write(samples)
+    }
+
+    /// Reads interleaved samples through the drift-compensating resampler.
+    ///
+    /// Returns the number of frames produced from buffered audio; the rest
+    /// of the output is filled with silence.
+    pub fn read(&mut self, output: &mut [f32]) -> Result<usize, Error> {
+        let channels = self.ring.channels();
+        let whole = output.len() - output.len() % channels;
+        let output = &mut output[..whole];
+
+        if !self.primed {
+            if self.ring.available_frames() < self.target_frames {
+                output.fill(0.0);
+                return Ok(0);
+            }
+            self.primed = true;
+            self.filtered_fill = self.target_frames as f64;
+        }
+
+        self.update_correction(whole / channels);
+
+        let mut produced = 0;
+        while produced < whole {
+            let (input, _) = self.ring.read_slices();
+            let (consumed, made) = self
+                .resampler
+                .process_stage(input, &mut output[produced..])?;
+            self.ring.commit_read(consumed / channels);
+            produced += made;
+            if consumed == 0 && made == 0 {
+                break;
+            }
+        }
+
+        if produced < whole {
+            // Ran dry: play silence and build the level up again
+            output[produced..].fill(0.0);
+            self.underruns += 1;
+            self.primed = false;
+        }
+        Ok(produced / channels)
+    }
+
+    /// Runs one step of the PI controller for a read of `frames` frames.
+    fn update_correction(&mut self, frames: usize) {
+        let dt = frames as f64 / self.sample_rate;
+        let fill = self.ring.available_frames() as f64;
+        let alpha = (dt / JITTER_FILL_SMOOTHING_SECS).min(1.0);
+        self.filtered_fill += alpha * (fill - self.filtered_fill);
+
+        // Fill error in seconds of audio
+        let error = (self.filtered_fill - self.target_frames as f64) / self.sample_rate;
+        self.integral = (self.integral + JITTER_INTEGRAL_GAIN * error * dt)
+            .clamp(-JITTER_MAX_CORRECTION, JITTER_MAX_CORRECTION);
+        let correction = (JITTER_PROPORTIONAL_GAIN * error + self.integral)
+            .clamp(-JITTER_MAX_CORRECTION, JITTER_MAX_CORRECTION);
+        self.resampler.set_correction(correction);
+    }
+
+    /// Returns the number of frames currently buffered.
+    pub fn fill_frames(&self) -> usize {
+        self.ring.available_frames()
+    }
+
+    /// Returns the target fill level in frames.
+    pub fn target_frames(&self) -> usize {
+        self.target_frames
+    }
+
+    /// Returns the measured clock drift in parts per million.
+    ///
+    /// Positive when the writer's clock runs faster than the reader's.
+    pub fn drift_ppm(&self) -> f64 {
+        self.integral * 1e6
+    }
+
+    /// Returns the number of frames that can be written.
+    pub fn free_frames(&self) -> usize {
+        self.ring.free_frames()
+    }
+
+    /// Returns the number of reads that ran out of buffered audio.
+    pub fn underruns(&self) -> u64 {
+        self.underruns
+    }
+
+    /// Returns the number of writes that could not store all their frames.
+    pub fn overruns(&self) -> u64 {
+        self.ring.overruns()
+    }
+
+    /// Clears the buffered audio and the controller state.
+    pub fn clear(&mut self) {
+        self.ring.clear();
+        self.resampler.reset();
+        self.resampler.set_correction(0.0);
+        self.filtered_fill = 0.0;
+        self.integral = 0.0;
+        self.primed = false;
+    }
+}
+
+/// Default length of one drift measurement window.
+const DRIFT_WINDOW: Duration = Duration::from_secs(1);
+
+/// Number of measurement windows the drift estimate spans.
+const DRIFT_HISTORY_WINDOWS: usize = 60;
+
+/// Time constant of the fill level smoothing, in seconds.
+const DRIFT_FILL_SMOOTHING_SECS: f64 = 5.0;
+
+/// Time over which a fill level error is corrected, in seconds.
+const DRIFT_FILL_RECOVERY_SECS: f64 = 30.0;
+
+/// Largest correction the estimator will ask for (2000 ppm).
+const DRIFT_MAX_CORRECTION: f64 = 0.002;
+
+/// Positions of a ring buffer at the end of one measurement window.
+#[derive(Debug, Clone, Copy)]
+struct DriftSnapshot {
+    at: Instant,
+    written: u64,
+    /// Samples read, scaled back to the consumer's uncorrected rate
+    nominal_read: f64,
+}
+
+/// Estimates the clock drift between the producer and consumer of a
+/// [`RingBuffer`].
+///
+/// Feed it the buffer's positions regularly (e.g. once per read) and apply
+/// [`DriftEstimator::correction`] to a [`VariableResampler`] on the read
+/// side. The drift is measured over the last minute of positions, and a slow
+/// fill level term keeps the latency at the level first observed, so long
+/// streams neither drift in latency nor run into overruns or underruns.
+#[derive(Debug, Clone)]
+pub struct DriftEstimator {
+    window: Duration,
+    target_fill: Option<f64>,
+    history: VecDeque<DriftSnapshot>,
+    last_read: Option<u64>,
+    last_at: Option<Instant>,
+    nominal_read: f64,
+    filtered_fill: f64,
+    drift: f64,
+    correction: f64,
+}
+
+impl DriftEstimator {
+    /// Creates a new estimator with a one second measurement window.
+    pub fn new() -> Self {
+        Self {
+            window: DRIFT_WINDOW,
+            target_fill: None,
+            history: VecDeque::with_capacity(DRIFT_HISTORY_WINDOWS + 1),
+            last_read: None,
+            last_at: None,
+            nominal_read: 0.0,
+            filtered_fill: 0.0,
+            drift: 0.0,
+            correction: 0.0,
+        }
+    }
+
+    /// Sets the length of one measurement window.
+    pub fn with_window(mut self, window: Duration) -> Self {
+        self.window = window.max(Duration::from_millis(1));
+        self
+    }
+
+    /// Sets the fill level, in samples, to hold the buffer at.
+    ///
+    /// Defaults to the fill level seen by the first update.
+    pub fn with_target_fill(mut self, samples: usize) -> Self {
+        self.target_fill = Some(samples as f64);
+        self
+    }
+
+    /// Updates the estimate from the current state of `ring`.
+    ///
+    /// Returns the correction to apply on the read side.
+    pub fn observe<T: Clone + Copy + Default>(
+        &mut self,
+        ring: &RingBuffer<T>,
+        now: Instant,
+    ) -> f64 {
+        self.update(
+            ring.write_position(),
+            ring.read_position(),
+            ring.available(),
+            now,
+        )
+    }
+
+    /// Updates the estimate from raw positions.
+    ///
+    /// `written` and `read` are the total samples written to and read from
+    /// the buffer, and `fill` is the number of samples buffered. Returns the
+    /// correction to apply on the read side.
+    pub fn update(&mut self, written: u64, read: u64, fill: usize, now: Instant) -> f64 {
+        let fill = fill as f64;
+        let (Some(last_read), Some(last_at)) = (self.last_read, self.last_at) else {
+            self.target_fill.get_or_insert(fill);
+            self.filtered_fill = fill;
+            self.last_read = Some(read);
+            self.last_at = Some(now);
+            self.history.push_back(DriftSnapshot {
+                at: now,
+                written,
+                nominal_read: 0.0,
+            });
+            return self.correction;
+        };
+
+        // Reads ran at (1 + correction) times the consumer's own rate
+        self.nominal_read += read.wrapping_sub(last_read) as f64 / (1.0 + self.correction);
+        let dt = now.saturating_duration_since(last_at).as_secs_f64();
+        let alpha = (dt / DRIFT_FILL_SMOOTHING_SECS).min(1.0);
+        self.filtered_fill += alpha * (fill - self.filtered_fill);
+        self.last_read = Some(read);
+        self.last_at = Some(now);
+
+        let newest = self.history.back().map_or(now, |snapshot| snapshot.at);
+        if now.saturating_duration_since(newest) < self.window {
+            return self.correction;
+        }
+        self.history.push_back(DriftSnapshot {
+            at: now,
+            written,
+            nominal_read: self.nominal_read,
+        });
+        if self.history.len() > DRIFT_HISTORY_WINDOWS + 1 {
+            self.history.pop_front();
+        }
+
+        let (first, last) = (self.history[0], self.history[self.history.len() - 1]);
+        let read_span = last.nominal_read - first.nominal_read;
+        let seconds = last.at.saturating_duration_since(first.at).as_secs_f64();
+        if read_span <= 0.0 || seconds <= 0.0 {
+            return self.correction;
+        }
+        self.drift = last.written.wrapping_sub(first.written) as f64 / read_span - 1.0;
+
+        // Drain or refill the fill level error over the recovery time
+        let read_rate = read_span / seconds;
+        let error = self.filtered_fill - self.target_fill.unwrap_or(self.filtered_fill);
+        let recovery = error / (read_rate * DRIFT_FILL_RECOVERY_SECS);
+        self.correction =
+            (self.drift + recovery).clamp(-DRIFT_MAX_CORRECTION, DRIFT_MAX_CORRECTION);
+        self.correction
+    }
+
+    /// Returns the correction to apply on the read side.
+    ///
+    /// Pass this to [`VariableResampler::set_correction`].
+    pub fn correction(&self) -> f64 {
+        self.correction
+    }
+
+    /// Returns the measured clock drift in parts per million.
+    ///
+    /// Positive when the producer's clock runs faster than the consumer's.
+    pub fn drift_ppm(&self) -> f64 {
+        self.drift * 1e6
+    }
+
+    /// Forgets all measurements, keeping the window and target fill level.
+    pub fn reset(&mut self) {
+        *self = Self {
+            window: self.window,
+            target_fill: self.target_fill,
+            ..Self::new()
+        };
+    }
+}
+
+impl Default for DriftEstimator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Storage shared by a [`BroadcastRingBuffer`] and its readers.
+struct BroadcastShared {
+    /// Samples stored as `f32` bits so readers may race the writer safely
+    data: Box<[AtomicU32]>,
+    /// End of the region the writer is about to overwrite
+    claim_pos: AtomicUsize,
+    /// End of the region that has been fully written
+    write_pos: AtomicUsize,
+    capacity: usize,
+    mask: usize,
+}
+
+/// Single-writer, multi-reader ring buffer.
+///
+/// Every [`BroadcastReader`] has its own cursor and sees every sample
+/// written after it subscribed. The writer never waits: a reader that falls
+/// more than the capacity behind is skipped forward to the oldest sample
+/// still in the buffer, and the skipped samples are counted.
+pub struct BroadcastRingBuffer {
+    shared: Arc<BroadcastShared>,
+}
+
+impl BroadcastRingBuffer {
+    /// Creates a new broadcast buffer with the specified capacity.
+    ///
+    /// The capacity is rounded up to the next power of 2 for efficient indexing.
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1).next_power_of_two();
+        Self {
+            shared: Arc::new(BroadcastShared {
+                data: (0..capacity).map(|_| AtomicU32::new(0)).collect(),
+                claim_pos: AtomicUsize::new(0),
+                write_pos: AtomicUsize::new(0),
+                capacity,
+                mask: capacity - 1,
+            }),
+        }
+    }
+
+    /// Creates a reader that starts at the current write position.
+    pub fn subscribe(&self) -> BroadcastReader {
+        BroadcastReader {
+            shared: Arc::clone(&self.shared),
+            cursor: self.shared.write_pos.load(Ordering::Acquire),
+            skipped: 0,
+            lag_events: 0,
+        }
+    }
+
+    /// Writes samples, overwriting the oldest data if necessary.
+    ///
+    /// Always accepts every sample; if `samples` is longer than the
+    /// capacity, only the newest samples are kept.
+    pub fn write(&mut self, samples: &[f32]) -> usize {
+        let shared = &*self.shared;
+        let pos = shared.write_pos.load(Ordering::Relaxed);
+        let end = pos + samples.len();
+        let kept = samples.len().min(shared.capacity);
+
+        // Announce the overwrite before touching any slot
+        shared.claim_pos.store(end, Ordering::Relaxed);
+        fence(Ordering::Release);
+
+        for (i, &sample) in samples[samples.len() - kept..].iter().enumerate() {
+            let idx = (end - kept + i) & shared.mask;
+            shared.data[idx].store(sample.to_bits(), Ordering::Relaxed);
+        }
+
+        shared.write_pos.store(end, Ordering::Release);
+        samples.len()
+    }
+
+    /// Returns the total capacity of the buffer in samples.
+    pub fn capacity(&self) -> usize {
+        self.shared.capacity
+    }
+
+    /// Returns the number of readers currently subscribed.
+    pub fn reader_count(&self) -> usize {
+        Arc::strong_count(&self.shared) - 1
+    }
+}
+
+/// A reader of a [`BroadcastRingBuffer`] with its own cursor.
+///
+/// Cloning a reader creates an independent reader at the same position.
+#[derive(Clone)]
+pub struct BroadcastReader {
+    shared: Arc<BroadcastShared>,
+    cursor: usize,
+    skipped: u64,
+    lag_events: u64,
+}
+
+impl BroadcastReader {
+    /// Reads the next samples for this reader.
+    ///
+    /// Returns the number of samples actually read. If the reader fell
+    /// behind by more than the capacity, it first skips forward.
+    pub fn read(&mut self, output: &mut [f32]) -> usize {
+        let shared = &*self.shared;
+        loop {
+            let write_pos = shared.write_pos.load(Ordering::Acquire);
+            let oldest = write_pos.saturating_sub(shared.capacity);
+            if oldest > self.cursor {
+                self.skipped += (oldest - self.cursor) as u64;
+                self.lag_events += 1;
+                self.cursor = oldest;
+            }
+
+            let to_read = output.len().min(write_pos - self.cursor);
+            for (i, out) in output.iter_mut().take(to_read).enumerate() {
+                let idx = (self.cursor + i) & shared.mask;
+                *out = f32::from_bits(shared.data[idx].load(Ordering::Relaxed));
+            }
+
+            // If the writer lapped us while copying, part of the copy may be
+            // newer data: skip past it and try again
+            fence(Ordering::Acquire);
+            let claim_pos = shared.claim_pos.load(Ordering::Relaxed);
+            if claim_pos.saturating_sub(shared.capacity) > self.cursor {
+                continue;
+            }
+
+            self.cursor += to_read;
+            return to_read;
+        }
+    }
+
+    /// Returns the number of samples waiting for this reader.
+    ///
+    /// Never more than the capacity; older samples have been overwritten.
+    pub fn available(&self) -> usize {
+        let write_pos = self.shared.write_pos.load(Ordering::Acquire);
+        (write_pos - self.cursor).min(self.shared.capacity)
+    }
+
+    /// Returns the total number of samples this reader has missed.
+    pub fn skipped(&self) -> u64 {
+        self.skipped
+    }
+
+    /// Returns how many times this reader fell behind and was skipped forward.
+    pub fn lag_events(&self) -> u64 {
+        self.lag_events
+    }
+}
+
+/// Number of buckets in a [`FillStats::histogram`].
+pub const FILL_HISTOGRAM_BUCKETS: usize = 8;
+
+/// Length of one slot of the rolling fill window, in seconds.
+const FILL_SLOT_SECS: u64 = 10;
+
+/// Number of slots in the rolling fill window (one minute in total).
+const FILL_WINDOW_SLOTS: usize = 6;
+
+/// Marks a slot or watermark that has not recorded anything yet.
+const FILL_UNSET: u64 = u64::MAX;
+
+/// Fill levels recorded during one slot of the rolling window.
+struct FillSlot {
+    epoch: AtomicU64,
+    min: AtomicUsize,
+    max: AtomicUsize,
+    histogram: [AtomicU64; FILL_HISTOGRAM_BUCKETS],
+}
+
+impl FillSlot {
+    fn new() -> Self {
+        Self {
+            epoch: AtomicU64::new(FILL_UNSET),
+            min: AtomicUsize::new(0),
+            max: AtomicUsize::new(0),
+            histogram: std::array::from_fn(|_| AtomicU64::new(0)),
+        }
+    }
+}
+
+/// Lock-free record of a buffer's fill level over time.
+///
+/// Keeps low and high watermarks since the last reset, plus the minimum,
+/// maximum and a histogram of fill levels over the last minute in
+/// `FILL_SLOT_SECS`-long slots. Recording is a handful of relaxed atomic
+/// operations, so it is safe on the real-time path; a single thread records
+/// while any thread may read.
+pub struct FillHistory {
+    capacity_frames: usize,
+    start: Instant,
+    low_watermark: AtomicU64,
+    high_watermark: AtomicU64,
+    slots: [FillSlot; FILL_WINDOW_SLOTS],
+}
+
+impl FillHistory {
+    /// Creates an empty history for a buffer of `capacity_frames` frames.
+    pub fn new(capacity_frames: usize) -> Self {
+        Self {
+            capacity_frames: capacity_frames.max(1),
+            start: Instant::now(),
+            low_watermark: AtomicU64::new(FILL_UNSET),
+            high_watermark: AtomicU64::new(0),
+            slots: std::array::from_fn(|_| FillSlot::new()),
+        }
+    }
+
+    /// Records the current fill level, in frames.
+    pub fn record(&self, fill_frames: usize) {
+        self.record_at(fill_frames, Instant::now());
+    }
+
+    fn record_at(&self, fill_frames: usize, now: Instant) {
+        self.low_watermark
+            .fetch_min(fill_frames as u64, Ordering::Relaxed);
+        self.high_watermark
+            .fetch_max(fill_frames as u64, Ordering::Relaxed);
+
+        let epoch = self.epoch(now);
+        let slot = &self.slots[epoch as usize % FILL_WINDOW_SLOTS];
+        if slot.epoch.load(Ordering::Acquire) != epoch {
+            // First record in a new slot: drop what the slot held a minute ago
+            slot.min.store(fill_frames, Ordering::Relaxed);
+            slot.max.store(fill_frames, Ordering::Relaxed);
+            for bucket in &slot.histogram {
+                bucket.store(0, Ordering::Relaxed);
+            }
+            slot.epoch.store(epoch, Ordering::Release);
+        }
+
+        slot.min.fetch_min(fill_frames, Ordering::Relaxed);
+        slot.max.fetch_max(fill_frames, Ordering::Relaxed);
+        let bucket = (fill_frames * FILL_HISTOGRAM_BUCKETS / self.capacity_frames)
+            .min(FILL_HISTOGRAM_BUCKETS - 1);
+        slot.histogram[bucket].fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Returns the fill statistics as of now.
+    pub fn stats(&self) -> FillStats {
+        self.stats_at(Instant::now())
+    }
+
+    fn stats_at(&self, now: Instant) -> FillStats {
+        let low = self.low_watermark.load(Ordering::Relaxed);
+        let mut stats = FillStats {
+            capacity_frames: self.capacity_frames,
+            low_watermark: if low == FILL_UNSET { 0 } else { low as usize },
+            high_watermark: self.high_watermark.load(Ordering::Relaxed) as usize,
+            ..Default::default()
+        };
+
+        // The recording thread may have stamped a slot with a later instant
+        // than `now`; such a slot is the newest one, not an old one
+        let current = self.epoch(now);
+        let mut min = usize::MAX;
+        for slot in &self.slots {
+            let epoch = slot.epoch.load(Ordering::Acquire);
+            if epoch == FILL_UNSET || current.saturating_sub(epoch) >= FILL_WINDOW_SLOTS as u64 {
+                continue;
+            }
+            min = min.min(slot.min.load(Ordering::Relaxed));
+            stats.max_frames = stats.max_frames.max(slot.max.load(Ordering::Relaxed));
+            for (total, bucket) in stats.histogram.iter_mut().zip(&slot.histogram) {
+                *total += bucket.load(Ordering::Relaxed);
+            }
+        }
+        if min != usize::MAX {
+            stats.min_frames = min;
+        }
+        stats
+    }
+
+    /// Forgets the watermarks and the rolling window.
+    pub fn reset(&self) {
+        self.low_watermark.store(FILL_UNSET, Ordering::Relaxed);
+        self.high_watermark.store(0, Ordering::Relaxed);
+        for slot in &self.slots {
+            slot.epoch.store(FILL_UNSET, Ordering::Release);
+        }
+    }
+
+    fn epoch(&self, now: Instant) -> u64 {
+        now.saturating_duration_since(self.start).as_secs() / FILL_SLOT_SECS
+    }
+}
+
+/// Fill-level statistics of one buffer, counted in frames.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FillStats {
+    /// Capacity of the buffer
+    pub capacity_frames: usize,
+
+    /// Lowest fill over the last minute
+    pub min_frames: usize,
+
+    /// Highest fill over the last minute
+    pub max_frames: usize,
+
+    /// Lowest fill since the last reset
+    pub low_watermark: usize,
+
+    /// Highest fill since the last reset
+    pub high_watermark: usize,
+
+    /// Number of recorded fill levels over the last minute, per bucket of
+    /// `capacity_frames / FILL_HISTOGRAM_BUCKETS` frames (the last bucket
+    /// also counts a full buffer)
+    pub histogram: [u64; FILL_HISTOGRAM_BUCKETS],
+}
+
+/// Sample rate, in Hz, the meters of a [`TripleRingBuffer`] assume until
+/// [`TripleRingBuffer::with_sample_rate`] sets the real one.
+///
+/// The rate sets the meters' time constants, true-peak oversampling and
+/// K-weighting filters, so readings from a stream at another rate are
+/// skewed.
+pub const DEFAULT_SAMPLE_RATE: u32 = 48000;
+
+/// Triple ring buffer architecture for audio processing.
+///
+/// This architecture consists of:
+/// - Input buffer: Receives data from the capture device
+/// - Resample buffer: Holds data during sample rate conversion
+/// - Output buffer: Delivers data to the playback device
+///
+/// Its [`EffectChain`], followed by an optional fixed [`GainStage`], runs on
+/// the frames moved from the input to the resample buffer, and a
+/// [`LevelMeter`] and a [`LoudnessMeter`] measure the delivered frames.
+pub struct TripleRingBuffer {
+    /// Input buffer (from capture device/speaker)
+    pub ring_input: FrameRingBuffer<f32>,
+
+    /// Resample buffer (during sample rate conversion)
+    pub ring_resample: FrameRingBuffer<f32>,
+
+    /// Output buffer (to playback device/microphone)
+    pub ring_output: FrameRingBuffer<f32>,
+
+    /// Effects between the input and resample buffers
+    effects: EffectChain,
+
+    /// Gain after the effects, outside the removable chain
+    gain: Option<GainStage>,
+
+    /// Stage between the resample and output buffers (pass-through if unset)
+    stage: Option<Box<dyn ProcessingStage>>,
+
+    /// Drift-compensating buffer in front of playback, if enabled
+    jitter: Option<JitterBuffer>,
+
+    /// Levels of the delivered audio
+    meter: LevelMeter,
+
+    /// Loudness of the delivered audio
+    loudness: LoudnessMeter,
+
+    /// Delivered samples outside -1.0..=1.0
+    clipped_samples: u64,
+
+    /// Fill levels of the three buffers
+    input_history: FillHistory,
+    resample_history: FillHistory,
+    output_history: FillHistory,
+}
+
+impl TripleRingBuffer {
+    /// Creates a new mono triple ring buffer with the specified capacity.
+    pub fn new(buffer_size: usize) -> Self {
+        Self::with_channels(buffer_size, 1)
+    }
+
+    /// Creates a new triple ring buffer for interleaved audio.
+    ///
+    /// `buffer_size` is in samples and is divided into frames of `channels`.
+    /// The meters assume [`DEFAULT_SAMPLE_RATE`].
+    pub fn with_channels(buffer_size: usize, channels: usize) -> Self {
+        let channels = channels.max(1);
+        let frames = buffer_size.div_ceil(channels);
+        Self {
+            ring_input: FrameRingBuffer::new(frames, channels),
+            ring_resample: FrameRingBuffer::new(frames, channels),
+            ring_output: FrameRingBuffer::new(frames, channels),
+            effects: EffectChain::new(channels),
+            gain: None,
+            stage: None,
+            jitter: None,
+            meter: LevelMeter::new(channels, DEFAULT_SAMPLE_RATE),
+            loudness: LoudnessMeter::new(channels, DEFAULT_SAMPLE_RATE),
+            clipped_samples: 0,
+            input_history: FillHistory::new(frames.next_power_of_two()),
+            resample_history: FillHistory::new(frames.next_power_of_two()),
+            output_history: FillHistory::new(frames.next_power_of_two()),
+        }
+    }
+
+    /// Creates a triple ring buffer from a cable configuration.
+    ///
+    /// The overrun policy applies to the input buffer, where captured audio
+    /// enters, and the underrun policy to the output buffer, where playback
+    /// pulls from. The stage is an [`AudioProcessor`], which uses the
+    /// configured resampling quality only if it changes the rate.
+    /// Jitter-buffer mode is enabled if the configuration sets a positive,
+    /// finite jitter target.
+    pub fn from_config(config: &CableConfig) -> Self {
+        let mut triple = Self::with_channels(config.buffer_size, config.channels as usize)
+            .with_sample_rate(config.sample_rate);
+        triple.ring_input = triple.ring_input.with_overrun_policy(config.overrun_policy);
+        triple.ring_output = triple
+            .ring_output
+            .with_underrun_policy(config.underrun_policy);
+        triple.meter.set_ballistics(config.meter_ballistics);
+        triple.stage = Some(Box::new(
+            AudioProcessor::new(
+                config.sample_rate,
+                config.sample_rate,
+                triple.channels() as u16,
+                config.format,
+            )
+            .with_quality(config.resample_quality),
+        ));
+        match config.jitter_target_ms {
+            Some(target_ms) if target_ms.is_finite() && target_ms > 0.0 => {
+                let target_frames = (target_ms * config.sample_rate as f64 / 1000.0) as usize;
+                triple.enable_jitter_buffer(config.sample_rate, target_frames);
+            }
+            Some(target_ms) => {
+                log::warn!("Ignoring invalid jitter target of {} ms", target_ms);
+            }
+            None => {}
+        }
+        triple
+    }
+
+    /// Sets the sample rate, in Hz, of the delivered audio, which the level
+    /// and loudness meters need; their readings start over.
+    pub fn with_sample_rate(mut self, sample_rate: u32) -> Self {
+        let channels = self.channels();
+        self.meter =
+            LevelMeter::new(channels, sample_rate).with_ballistics(self.meter.ballistics());
+        self.loudness = LoudnessMeter::new(channels, sample_rate);
+        self
+    }
+
+    /// Returns the number of interleaved channels per frame.
+    pub fn channels(&self) -> usize {
+        self.ring_input.channels()
+    }
+
+    /// Sets the stage run between the resample and output buffers.
+    ///
+    /// Typically an [`crate::AudioProcessor`] or [`crate::audio::Resampler`]
+    /// converting to the output rate. Fails if the stage's input or output
+    /// channel count differs from the buffers'.
+    pub fn set_stage(&mut self, stage: impl ProcessingStage + 'static) -> Result<(), Error> {
+        let channels = self.channels();
+        if stage.input_channels() != channels || stage.output_channels() != channels {
+            return Err(Error::BufferError(format!(
+                "Stage converts {} to {} channels, buffers have {}",
+                stage.input_channels(),
+                stage.output_channels(),
+                channels
+            )));
+        }
+        self.stage = Some(Box::new(stage));
+        Ok(())
+    }
+
+    /// Returns the stage if it is of type `T`.
+    pub fn stage<T: ProcessingStage>(&self) -> Option<&T> {
+        let stage: &dyn Any = self.stage.as_deref()?;
+        stage.downcast_ref()
+    }
+
+    /// Mutable version of [`TripleRingBuffer::stage`].
+    pub fn stage_mut<T: ProcessingStage>(&mut self) -> Option<&mut T> {
+        let stage: &mut dyn Any = self.stage.as_deref_mut()?;
+        stage.downcast_mut()
+    }
+
+    /// Removes the stage, so samples pass through unchanged.
+    pub fn clear_stage(&mut self) {
+        self.stage = None;
+    }
+
+    /// Returns the effect chain run between the input and resample buffers.
+    pub fn effects(&self) -> &EffectChain {
+        &self.effects
+    }
+
+    /// Returns the effect chain for adding, removing or bypassing effects.
+    pub fn effects_mut(&mut self) -> &mut EffectChain {
+        &mut self.effects
+    }
+
+    /// Enables the fixed gain stage after the effect chain, starting at
+    /// `gain_db` on all channels without a ramp, and returns its controls.
+    /// A non-finite `gain_db` leaves the gain at 0 dB.
+    ///
+    /// Unlike an effect, the stage cannot be removed from the chain.
+    pub fn enable_gain(&mut self, sample_rate: u32, gain_db: f32) -> &Arc<GainControl> {
+        let control = Arc::new(GainControl::new(self.channels()));
+        control.set_gain_db(gain_db);
+        self.gain
+            .insert(GainStage::with_control(control, sample_rate))
+            .control()
+    }
+
+    /// Returns the controls of the gain stage, if enabled.
+    pub fn gain_control(&self) -> Option<&Arc<GainControl>> {
+        self.gain.as_ref().map(GainStage::control)
+    }
+
+    /// Enables jitter-buffer mode with the given target fill level.
+    ///
+    /// Playback is then read through a [`JitterBuffer`] that compensates
+    /// for clock drift between the writer and the reader.
+    pub fn enable_jitter_buffer(&mut self, sample_rate: u32, target_frames: usize) {
+        let capacity = self.ring_output.capacity_frames();
+        self.jitter = Some(JitterBuffer::new(
+            capacity,
+            self.channels(),
+            sample_rate,
+            target_frames,
+        ));
+    }
 
-/// Triple ring buffer architecture for audio processing.
-///
-/// This architecture consists of:
-/// - Input buffer: Receives data from the capture device
-/// - Resample buffer: Holds data during sample rate conversion
-/// - Output buffer: Delivers data to the playback device
-pub struct TripleRingBuffer {
-    /// Input buffer (from capture device/speaker)
-    pub ring_input: RingBuffer<f32>,
+    /// Returns the meter measuring the delivered audio.
+    pub fn meter(&self) -> &LevelMeter {
+        &self.meter
+    }
 
-    /// Resample buffer (during sample rate conversion)
-    pub ring_resample: RingBuffer<f32>,
+    /// Returns the meter, e.g. to change its ballistics.
+    pub fn meter_mut(&mut self) -> &mut LevelMeter {
+        &mut self.meter
+    }
 
-    /// Output buffer (to playback device/microphone)
-    pub ring_output: RingBuffer<f32>,
-}
+    /// Returns the loudness meter measuring the delivered audio.
+    pub fn loudness(&self) -> &LoudnessMeter {
+        &self.loudness
+    }
 
-impl TripleRingBuffer {
-    /// Creates a new triple ring buffer with the specified capacity.
-    pub fn new(buffer_size: usize) -> Self {
-        Self {
-            ring_input: RingBuffer::new(buffer_size),
-            ring_resample: RingBuffer::new(buffer_size),
-            ring_output: RingBuffer::new(buffer_size),
-        }
+    /// Restarts the loudness measurement, e.g. at the start of a programme.
+    pub fn reset_loudness(&mut self) {
+        self.loudness.reset();
+    }
+
+    /// Returns the jitter buffer, if jitter-buffer mode is enabled.
+    pub fn jitter_buffer(&self) -> Option<&JitterBuffer> {
+        self.jitter.as_ref()
     }
 
     /// Processes audio through the triple buffer pipeline.
     ///
     /// This method:
     /// 1. Writes input samples to the input buffer
-    /// 2. Reads from input buffer, processes, and writes to resample buffer
+    /// 2. Reads from input buffer, runs the effects and gain, and writes to
+    ///    resample buffer
     /// 3. Reads from resample buffer and writes to output buffer
+    ///
+    /// Input and output are interleaved; returns the number of frames
+    /// written to `output`.
     pub fn process(&mut self, input: &[f32], output: &mut [f32]) -> Result<usize, Error> {
         // Write input to input buffer
-        let written = self.ring_input.write(input);
+        self.ring_input.write(input);
+        self.input_history
+            .record(self.ring_input.available_frames());
+
+        // Transfer from input to resample through the effects
+        self.run_effects();
+        self.input_history
+            .record(self.ring_input.available_frames());
+        self.resample_history
+            .record(self.ring_resample.available_frames());
+
+        // Run the stage from resample to output
+        self.run_stage()?;
+        self.resample_history
+            .record(self.ring_resample.available_frames());
+        self.output_history
+            .record(self.ring_output.available_frames());
+
+        // Deliver from output
+        let read = match self.jitter.as_mut() {
+            Some(jitter) => {
+                jitter.ring.fill_from(&self.ring_output);
+                jitter.read(output)
+            }
+            None => Ok(self.ring_output.read(output)),
+        };
+        self.output_history
+            .record(self.ring_output.available_frames());
+        if let Ok(frames) = read {
+            let delivered = &output[..frames * self.channels()];
+            self.meter.process(delivered);
+            self.loudness.process(delivered);
+            self.clipped_samples += delivered
+                .iter()
+                .filter(|s| !(-1.0..=1.0).contains(*s))
+                .count() as u64;
+        }
+        read
+    }
+
+    /// Forgets the fill watermarks and history of all three buffers.
+    pub fn reset_fill_history(&self) {
+        self.input_history.reset();
+        self.resample_history.reset();
+        self.output_history.reset();
+    }
+
+    /// Moves frames from the input buffer into the resample buffer and runs
+    /// the effect chain and gain stage on them in place.
+    fn run_effects(&mut self) {
+        if self.effects.is_empty() && self.gain.is_none() {
+            // No intermediate copy
+            self.ring_resample.fill_from(&self.ring_input);
+            return;
+        }
+
+        let channels = self.ring_input.channels();
+        loop {
+            let (input, _) = self.ring_input.read_slices();
+            let (output, _) = self.ring_resample.write_slices();
+            let len = input.len().min(output.len());
+            if len == 0 {
+                return;
+            }
+
+            output[..len].copy_from_slice(&input[..len]);
+            self.effects.process(&mut output[..len]);
+            if let Some(gain) = self.gain.as_mut() {
+                gain.process(&mut output[..len]);
+            }
+            self.ring_resample.commit_write(len / channels);
+            self.ring_input.commit_read(len / channels);
+        }
+    }
+
+    /// Moves frames from the resample buffer through the stage into the
+    /// output buffer, working directly on the ring slices.
+    fn run_stage(&mut self) -> Result<(), Error> {
+        let Some(stage) = self.stage.as_mut() else {
+            self.ring_output.fill_from(&self.ring_resample);
+            return Ok(());
+        };
 
-        // Transfer from input to resample (simple pass-through for now)
-        let mut temp_buf = vec![0.0f32; written];
-        let read = self.ring_input.read(&mut temp_buf);
-        self.ring_resample.write(&temp_buf[..read]);
+        let channels = self.ring_resample.channels();
+        loop {
+            let (input, _) = self.ring_resample.read_slices();
+            let (output, _) = self.ring_output.write_slices();
+            if input.is_empty() || output.is_empty() {
+                return Ok(());
+            }
 
-        // Transfer from resample to output
-        let written_output = self.ring_output.read(output);
+            let (consumed, produced) = stage.process_stage(input, output)?;
+            self.ring_output.commit_write(produced / channels);
+            self.ring_resample.commit_read(consumed / channels);
 
-        Ok(written_output)
+            if consumed == 0 && produced == 0 {
+                return Ok(());
+            }
+        }
     }
 
     /// Clears all buffers.
@@ -162,17 +2324,38 @@ impl TripleRingBuffer {
         self.ring_input.clear();
         self.ring_resample.clear();
         self.ring_output.clear();
+        if let Some(jitter) = self.jitter.as_mut() {
+            jitter.clear();
+        }
+        AudioEffect::reset(&mut self.effects);
+        if let Some(gain) = self.gain.as_mut() {
+            gain.reset();
+        }
     }
 
     /// Returns statistics about buffer levels.
     pub fn stats(&self) -> BufferStats {
         BufferStats {
-            input_available: self.ring_input.available(),
-            input_free: self.ring_input.free_space(),
-            resample_available: self.ring_resample.available(),
-            resample_free: self.ring_resample.free_space(),
-            output_available: self.ring_output.available(),
-            output_free: self.ring_output.free_space(),
+            input_available: self.ring_input.available_frames(),
+            input_free: self.ring_input.free_frames(),
+            resample_available: self.ring_resample.available_frames(),
+            resample_free: self.ring_resample.free_frames(),
+            output_available: self.ring_output.available_frames(),
+            output_free: self.ring_output.free_frames(),
+            overruns: self.ring_input.overruns()
+                + self.ring_resample.overruns()
+                + self.ring_output.overruns(),
+            underruns: self.ring_input.underruns()
+                + self.ring_resample.underruns()
+                + self.ring_output.underruns()
+                + self.jitter.as_ref().map_or(0, JitterBuffer::underruns),
+            drift_ppm: self.jitter.as_ref().map_or(0.0, JitterBuffer::drift_ppm),
+            input_fill: self.input_history.stats(),
+            resample_fill: self.resample_history.stats(),
+            output_fill: self.output_history.stats(),
+            levels: self.meter.levels(),
+            loudness: self.loudness.reading(),
+            clipped_samples: self.clipped_samples,
         }
     }
 }
@@ -184,25 +2367,55 @@ impl Default for TripleRingBuffer {
 }
 
 /// Statistics about buffer levels.
+///
+/// Levels are counted in frames.
 #[derive(Debug, Clone)]
 pub struct BufferStats {
-    /// Number of samples available in input buffer
+    /// Number of frames available in input buffer
     pub input_available: usize,
 
-    /// Free space in input buffer
+    /// Free frames in input buffer
     pub input_free: usize,
 
-    /// Number of samples available in resample buffer
+    /// Number of frames available in resample buffer
     pub resample_available: usize,
 
-    /// Free space in resample buffer
+    /// Free frames in resample buffer
     pub resample_free: usize,
 
-    /// Number of samples available in output buffer
+    /// Number of frames available in output buffer
     pub output_available: usize,
 
-    /// Free space in output buffer
+    /// Free frames in output buffer
     pub output_free: usize,
+
+    /// Number of overrun events across all stages
+    pub overruns: u64,
+
+    /// Number of underrun events across all stages
+    pub underruns: u64,
+
+    /// Measured clock drift in ppm (0.0 unless jitter-buffer mode is on)
+    pub drift_ppm: f64,
+
+    /// Fill history of the input buffer
+    pub input_fill: FillStats,
+
+    /// Fill history of the resample buffer
+    pub resample_fill: FillStats,
+
+    /// Fill history of the output buffer
+    pub output_fill: FillStats,
+
+    /// Levels of the delivered audio, one entry per channel
+    pub levels: Vec<ChannelLevels>,
+
+    /// Loudness of the delivered audio
+    pub loudness: LoudnessReading,
+
+    /// Delivered samples outside -1.0..=1.0, which clip when converted to
+    /// an integer format
+    pub clipped_samples: u64,
 }
 
 #[cfg(test)]
@@ -260,9 +2473,846 @@ mod tests {
         let mut output = vec![0.0; 8];
 
         let processed = triple.process(&input, &mut output).unwrap();
-        assert_eq!(processed, 0); // No data in output yet
+        assert_eq!(processed, 4);
+        assert_eq!(&output[..4], &input[..]);
+
+        // Nothing left in the pipeline
+        let processed = triple.process(&[], &mut output).unwrap();
+        assert_eq!(processed, 0);
+        assert_eq!(triple.stats().resample_available, 0);
+    }
+
+    #[test]
+    fn test_triple_ring_buffer_counts_clipped_samples() {
+        let mut triple = TripleRingBuffer::new(64);
+        let mut output = [0.0f32; 4];
+        triple
+            .process(&[2.0, 0.5, -1.5, -1.0], &mut output)
+            .unwrap();
+        assert_eq!(triple.stats().clipped_samples, 2);
+    }
+
+    #[test]
+    fn test_triple_ring_buffer_fixed_gain() {
+        let mut triple = TripleRingBuffer::with_channels(64, 2);
+        assert!(triple.gain_control().is_none());
+        let control = Arc::clone(triple.enable_gain(48000, -6.0));
+
+        // The first block is already at the configured gain
+        let mut output = vec![0.0f32; 16];
+        assert_eq!(triple.process(&[1.0; 16], &mut output).unwrap(), 8);
+        let factor = 10f32.powf(-6.0 / 20.0);
+        assert!(output.iter().all(|s| (s - factor).abs() < 1e-6));
+
+        // The gain is not part of the removable chain
+        assert!(triple.effects().is_empty());
+        control.set_muted(true);
+        triple.clear_all();
+        assert_eq!(triple.process(&[1.0; 16], &mut output).unwrap(), 8);
+        assert!(output.iter().all(|&s| s == 0.0));
+        assert!(triple.gain_control().is_some_and(|c| c.is_muted()));
+    }
+
+    #[test]
+    fn test_triple_ring_buffer_meter_sample_rate() {
+        let triple = TripleRingBuffer::new(64);
+        assert_eq!(triple.loudness.sample_rate(), DEFAULT_SAMPLE_RATE);
+
+        let triple = TripleRingBuffer::new(64).with_sample_rate(44100);
+        assert_eq!(triple.loudness.sample_rate(), 44100);
+
+        let config = CableConfig {
+            sample_rate: 96000,
+            ..Default::default()
+        };
+        let triple = TripleRingBuffer::from_config(&config);
+        assert_eq!(triple.loudness.sample_rate(), 96000);
+    }
+
+    #[test]
+    fn test_triple_ring_buffer_runs_effects() {
+        struct Double;
+
+        impl AudioEffect for Double {
+            fn process(&mut self, block: &mut [f32]) {
+                block.iter_mut().for_each(|s| *s *= 2.0);
+            }
+        }
+
+        let mut triple = TripleRingBuffer::with_channels(64, 2);
+        triple.effects_mut().set_fade_frames(1);
+        let id = triple.effects_mut().push(Double);
+
+        // The second block wraps around the end of the input buffer
+        let mut output = vec![0.0f32; 48];
+        for _ in 0..2 {
+            let input: Vec<f32> = (0..48).map(|i| i as f32).collect();
+            assert_eq!(triple.process(&input, &mut output).unwrap(), 24);
+            assert!(output.iter().zip(&input).all(|(out, s)| *out == 2.0 * s));
+        }
+
+        triple.effects_mut().set_bypass(id, true);
+        triple.process(&[1.0; 8], &mut output).unwrap();
+        assert_eq!(output[..8], [1.0; 8]);
+    }
+
+    #[test]
+    fn test_triple_ring_buffer_converts_rate() {
+        use crate::audio::AudioProcessor;
+        use crate::AudioFormat;
+
+        let mut triple = TripleRingBuffer::with_channels(1024, 2);
+        triple
+            .set_stage(AudioProcessor::new(44100, 48000, 2, AudioFormat::F32LE))
+            .unwrap();
+
+        // One second of stereo input in 10 ms periods
+        let period = vec![0.25f32; 441 * 2];
+        let mut output = vec![0.0f32; 2048];
+        let mut frames_out = 0;
+        for _ in 0..100 {
+            frames_out += triple.process(&period, &mut output).unwrap();
+        }
+        frames_out += triple.process(&[], &mut output).unwrap();
+
+        // Everything but the final interpolation frame has come out at 48 kHz
+        assert!((47_998..=48_000).contains(&frames_out), "{}", frames_out);
+        assert!(output[..2].iter().all(|&s| (s - 0.25).abs() < 1e-6));
+    }
+
+    #[test]
+    fn test_triple_buffer_stage_from_config() {
+        use crate::audio::{AudioProcessor, ResampleQuality};
+
+        // The cable does not change the rate, so no engine is built for
+        // the preset
+        let config = CableConfig {
+            resample_quality: ResampleQuality::Medium,
+            ..Default::default()
+        };
+        let triple = TripleRingBuffer::from_config(&config);
+        let stage = triple.stage::<AudioProcessor>().unwrap();
+        assert!(!stage.needs_resampling());
+        assert_eq!(stage.quality(), ResampleQuality::Linear);
+
+        // Without rubato the preset falls back to linear interpolation
+        let processor = AudioProcessor::new(44100, 48000, 2, config.format)
+            .with_quality(config.resample_quality);
+        let expected = if cfg!(feature = "rubato") {
+            ResampleQuality::Medium
+        } else {
+            ResampleQuality::Linear
+        };
+        assert_eq!(processor.quality(), expected);
+    }
+
+    #[test]
+    fn test_triple_ring_buffer_rejects_stage_channels() {
+        use crate::audio::{AudioProcessor, ChannelMixer};
+        use crate::AudioFormat;
+
+        let mut triple = TripleRingBuffer::with_channels(1024, 2);
+        assert!(triple.set_stage(ChannelMixer::new(2, 1)).is_err());
+        let upmix =
+            AudioProcessor::new(48000, 48000, 2, AudioFormat::F32LE).with_output_channels(6);
+        assert!(triple.set_stage(upmix).is_err());
+        let mono = AudioProcessor::new(48000, 48000, 1, AudioFormat::F32LE);
+        assert!(triple.set_stage(mono).is_err());
+        assert!(triple.set_stage(ChannelMixer::new(2, 2)).is_ok());
+    }
+
+    #[test]
+    fn test_ring_buffer_slices_wraparound() {
+        let mut buffer = RingBuffer::<f32>::new(8);
+        buffer.write(&[0.0; 6]);
+        buffer.commit_read(6);
+
+        // Free region now wraps: 2 slots at the end, 6 at the start
+        let (first, second) = buffer.write_slices();
+        assert_eq!((first.len(), second.len()), (2, 6));
+        first.copy_from_slice(&[1.0, 2.0]);
+        second[..3].copy_from_slice(&[3.0, 4.0, 5.0]);
+        buffer.commit_write(5);
+        assert_eq!(buffer.available(), 5);
+
+        let (first, second) = buffer.read_slices();
+        assert_eq!(first, &[1.0, 2.0]);
+        assert_eq!(second, &[3.0, 4.0, 5.0]);
+        buffer.commit_read(3);
+
+        let mut output = [0.0; 4];
+        assert_eq!(buffer.read(&mut output), 2);
+        assert_eq!(&output[..2], &[4.0, 5.0]);
+    }
+
+    #[test]
+    fn test_overrun_policies() {
+        let mut buffer = RingBuffer::<f32>::new(4);
+        assert_eq!(buffer.write(&[1.0, 2.0, 3.0, 4.0, 5.0]), 4);
+        assert_eq!(buffer.overruns(), 1);
+
+        let mut buffer =
+            RingBuffer::<f32>::new(4).with_overrun_policy(OverrunPolicy::OverwriteOldest);
+        buffer.write(&[1.0, 2.0, 3.0]);
+        assert_eq!(buffer.write(&[4.0, 5.0, 6.0]), 3);
+        assert_eq!(buffer.overruns(), 1);
+        let mut output = [0.0; 4];
+        assert_eq!(buffer.read(&mut output), 4);
+        assert_eq!(output, [3.0, 4.0, 5.0, 6.0]);
+
+        // Longer than capacity: only the newest samples survive
+        assert_eq!(buffer.write(&[1.0, 2.0, 3.0, 4.0, 5.0, 6.0]), 6);
+        assert_eq!(buffer.read(&mut output), 4);
+        assert_eq!(output, [3.0, 4.0, 5.0, 6.0]);
+    }
+
+    #[test]
+    fn test_overrun_block_waits_for_consumer() {
+        use std::thread;
+
+        let (mut producer, mut consumer) = RingBuffer::<f32>::new(4)
+            .with_overrun_policy(OverrunPolicy::Block(Duration::from_secs(5)))
+            .split();
+
+        let reader = thread::spawn(move || {
+            let mut received = Vec::new();
+            let mut output = [0.0; 2];
+            while received.len() < 8 {
+                let read = consumer.read(&mut output);
+                received.extend_from_slice(&output[..read]);
+            }
+            received
+        });
+
+        let input: Vec<f32> = (0..8).map(|i| i as f32).collect();
+        assert_eq!(producer.write(&input), 8);
+        assert_eq!(producer.overruns(), 0);
+        assert_eq!(reader.join().unwrap(), input);
+    }
+
+    #[test]
+    fn test_underrun_policies() {
+        let mut output = [9.0; 6];
+
+        let mut buffer = RingBuffer::<f32>::new(8);
+        buffer.write(&[1.0, 2.0]);
+        assert_eq!(buffer.read(&mut output), 2);
+        assert_eq!(output, [1.0, 2.0, 9.0, 9.0, 9.0, 9.0]);
+        assert_eq!(buffer.underruns(), 1);
+
+        let mut buffer = RingBuffer::<f32>::new(8).with_underrun_policy(UnderrunPolicy::ZeroFill);
+        buffer.write(&[1.0, 2.0]);
+        buffer.read(&mut output);
+        assert_eq!(output, [1.0, 2.0, 0.0, 0.0, 0.0, 0.0]);
+
+        let mut buffer = RingBuffer::<f32>::new(8)
+            .with_underrun_policy(UnderrunPolicy::RepeatLastFrame)
+            .with_frame_len(2);
+        buffer.write(&[1.0, 2.0, 3.0, 4.0]);
+        buffer.read(&mut output);
+        assert_eq!(output, [1.0, 2.0, 3.0, 4.0, 3.0, 4.0]);
+        buffer.read(&mut output);
+        assert_eq!(output, [3.0, 4.0, 3.0, 4.0, 3.0, 4.0]);
+        assert_eq!(buffer.underruns(), 2);
+
+        let (mut producer, mut consumer) = RingBuffer::<f32>::new(8)
+            .with_underrun_policy(UnderrunPolicy::FadeToSilence)
+            .with_frame_len(2)
+            .split();
+        producer.write(&[1.0, 1.0]);
+        let mut output = [9.0; 10];
+        assert_eq!(consumer.read(&mut output), 2);
+        assert_eq!(
+            output,
+            [1.0, 1.0, 0.75, 0.75, 0.5, 0.5, 0.25, 0.25, 0.0, 0.0]
+        );
+        // A second read fades from the same frame
+        consumer.read(&mut output[..4]);
+        assert_eq!(&output[..4], &[0.5, 0.5, 0.0, 0.0]);
+        assert_eq!(consumer.underruns(), 2);
+    }
+
+    #[test]
+    fn test_ring_buffer_any_copy_type() {
+        // Sample types without a gain, such as raw bytes, still get a buffer
+        let mut buffer = RingBuffer::<u8>::new(4);
+        assert_eq!(buffer.write(&[1, 2, 3, 4, 5]), 4);
+        let mut output = [0u8; 2];
+        assert_eq!(buffer.read(&mut output), 2);
+        assert_eq!(output, [1, 2]);
+
+        let (mut producer, mut consumer) = buffer.split();
+        producer.write(&[7, 8]);
+        let mut output = [0u8; 6];
+        assert_eq!(consumer.read(&mut output), 4);
+        assert_eq!(output, [3, 4, 7, 8, 0, 0]);
+
+        let mut frames = FrameRingBuffer::<u8>::new(2, 2);
+        assert_eq!(frames.write(&[1, 2, 3]), 1);
+        assert_eq!(frames.available_frames(), 1);
+    }
+
+    #[test]
+    fn test_frame_buffer_never_splits_frames() {
+        let mut buffer = FrameRingBuffer::<f32>::new(4, 2);
+        assert_eq!(buffer.capacity_frames(), 4);
+
+        // Trailing half frame is ignored
+        assert_eq!(buffer.write(&[1.0, -1.0, 2.0, -2.0, 3.0]), 2);
+        assert_eq!(buffer.available_frames(), 2);
+
+        // Only three whole frames fit; the fourth is dropped, not halved
+        assert_eq!(buffer.write(&[3.0, -3.0, 4.0, -4.0, 5.0, -5.0]), 2);
+        assert_eq!(buffer.free_frames(), 0);
+        assert_eq!(buffer.overruns(), 1);
+
+        // An odd-sized output only receives whole frames
+        let mut output = [0.0; 3];
+        assert_eq!(buffer.read(&mut output), 1);
+        assert_eq!(output, [1.0, -1.0, 0.0]);
+
+        let mut output = [0.0; 6];
+        assert_eq!(buffer.read(&mut output), 3);
+        assert_eq!(output, [2.0, -2.0, 3.0, -3.0, 4.0, -4.0]);
+    }
+
+    #[test]
+    fn test_frame_buffer_slices_hold_whole_frames() {
+        // 4 frames of 6 channels: 24 samples, not a power of two
+        let mut buffer = FrameRingBuffer::<f32>::new(4, 6);
+        buffer.write(&[0.0; 18]);
+        buffer.commit_read(3);
+
+        let (first, second) = buffer.write_slices();
+        assert_eq!(first.len() % 6, 0);
+        assert_eq!(second.len() % 6, 0);
+        assert_eq!((first.len(), second.len()), (6, 18));
+        buffer.commit_write(2);
+
+        let (first, second) = buffer.read_slices();
+        assert_eq!((first.len(), second.len()), (6, 6));
+        assert_eq!(buffer.available_frames(), 2);
+    }
+
+    #[test]
+    fn test_jitter_buffer_tracks_drift() {
+        const RATE: u32 = 48_000;
+        const PERIOD: usize = 480;
+        const TARGET: usize = 2_400;
+
+        let mut jitter = JitterBuffer::new(8192, 1, RATE, TARGET);
+        let mut output = vec![0.0f32; PERIOD];
+
+        // The writer's clock runs 200 ppm fast; simulate five minutes
+        let writer_period = PERIOD as f64 * (1.0 + 200e-6);
+        let mut owed = 0.0;
+        let mut max_error = 0usize;
+        for i in 0..30_000 {
+            owed += writer_period;
+            let frames = owed as usize;
+            owed -= frames as f64;
+            jitter.write(&vec![0.5; frames]);
+            jitter.read(&mut output).unwrap();
+
+            if i > 20_000 {
+                max_error = max_error.max(jitter.fill_frames().abs_diff(TARGET));
+            }
+        }
+
+        assert!(
+            (jitter.drift_ppm() - 200.0).abs() < 20.0,
+            "{}",
+            jitter.drift_ppm()
+        );
+        assert!(max_error < PERIOD * 2, "{}", max_error);
+        assert_eq!(jitter.underruns(), 0);
+    }
+
+    #[test]
+    fn test_drift_estimator_holds_fill_level() {
+        const RATE: f64 = 8_000.0;
+        const PERIOD: usize = 80;
+
+        let mut ring = RingBuffer::<f32>::new(4096);
+        let mut resampler = VariableResampler::new(8_000, 8_000, 1);
+        let mut estimator = DriftEstimator::new();
+        let mut output = [0.0f32; PERIOD];
+        ring.write(&[0.25; 800]);
+
+        // The producer's clock runs 300 ppm fast; simulate ten minutes
+        let start = Instant::now();
+        let producer_period = PERIOD as f64 * (1.0 + 300e-6);
+        let mut owed = 0.0;
+        let mut max_error = 0usize;
+        for i in 0..60_000u32 {
+            owed += producer_period;
+            let frames = owed as usize;
+            owed -= frames as f64;
+            ring.write(&vec![0.25; frames]);
+
+            let mut produced = 0;
+            while produced < PERIOD {
+                let (input, _) = ring.read_slices();
+                let (consumed, made) = resampler
+                    .process_stage(input, &mut output[produced..])
+                    .unwrap();
+                ring.commit_read(consumed);
+                produced += made;
+                if made == 0 {
+                    break;
+                }
+            }
+            assert_eq!(produced, PERIOD);
+
+            let now = start + Duration::from_secs_f64(f64::from(i + 1) * PERIOD as f64 / RATE);
+            resampler.set_correction(estimator.observe(&ring, now));
+            if i > 30_000 {
+                max_error = max_error.max(ring.available().abs_diff(800));
+            }
+        }
+
+        assert!(
+            (estimator.drift_ppm() - 300.0).abs() < 10.0,
+            "{}",
+            estimator.drift_ppm()
+        );
+        assert!((resampler.correction() - 300e-6).abs() < 20e-6);
+        assert!(max_error < PERIOD * 2, "{}", max_error);
+    }
+
+    #[test]
+    fn test_jitter_buffer_primes_before_playing() {
+        let mut jitter = JitterBuffer::new(64, 2, 48_000, 8);
+        let mut output = [1.0f32; 8];
+
+        jitter.write(&[0.5; 8]);
+        assert_eq!(jitter.read(&mut output).unwrap(), 0);
+        assert_eq!(output, [0.0; 8]);
+
+        jitter.write(&[0.5; 8]);
+        assert_eq!(jitter.read(&mut output).unwrap(), 4);
+        assert!(output.iter().all(|&s| (s - 0.5).abs() < 1e-6));
+    }
+
+    #[test]
+    fn test_jitter_buffer_clear_resets_controller() {
+        let mut jitter = JitterBuffer::new(4096, 1, 48_000, 256);
+        let mut output = [0.0f32; 128];
+        // The writer runs ahead, so the controller winds up a correction
+        for _ in 0..200 {
+            jitter.write(&[0.5; 130]);
+            jitter.read(&mut output).unwrap();
+        }
+        assert!(jitter.drift_ppm() > 0.0);
+
+        jitter.clear();
+        assert_eq!(jitter.drift_ppm(), 0.0);
+        assert_eq!(jitter.fill_frames(), 0);
+    }
+
+    #[test]
+    fn test_triple_buffer_jitter_from_config() {
+        let config = CableConfig {
+            jitter_target_ms: Some(10.0),
+            ..Default::default()
+        };
+        let triple = TripleRingBuffer::from_config(&config);
+        let jitter = triple.jitter_buffer().unwrap();
+        assert_eq!(jitter.target_frames(), config.sample_rate as usize / 100);
+
+        let triple = TripleRingBuffer::from_config(&CableConfig::default());
+        assert!(triple.jitter_buffer().is_none());
+
+        for target_ms in [f64::INFINITY, f64::NAN, 0.0, -5.0] {
+            let config = CableConfig {
+                jitter_target_ms: Some(target_ms),
+                ..Default::default()
+            };
+            let triple = TripleRingBuffer::from_config(&config);
+            assert!(triple.jitter_buffer().is_none(), "{}", target_ms);
+        }
+
+        // Huge targets are capped instead of overflowing the capacity
+        let jitter = JitterBuffer::new(64, 2, 48_000, usize::MAX);
+        assert_eq!(jitter.target_frames(), 10 * 48_000);
+    }
+
+    #[test]
+    fn test_timestamped_next_timestamp() {
+        let mut buffer = TimestampedRingBuffer::new(16, 2, 1000);
+        let start = Instant::now();
+        assert_eq!(buffer.next_timestamp(), None);
+
+        assert_eq!(buffer.write(&[0.0; 8], start), 4);
+        let later = start + Duration::from_millis(100);
+        assert_eq!(buffer.write(&[0.0; 4], later), 2);
+
+        let next = buffer.next_timestamp().unwrap();
+        assert_eq!(next.frame, 0);
+        assert_eq!(next.captured_at, start);
+
+        // Three frames into the first block, at 1 ms per frame
+        let mut output = [0.0; 6];
+        assert_eq!(buffer.read(&mut output), 3);
+        let next = buffer.next_timestamp().unwrap();
+        assert_eq!(next.frame, 3);
+        assert_eq!(next.captured_at, start + Duration::from_millis(3));
+
+        // The first frame of the second block uses its own timestamp
+        assert_eq!(buffer.read(&mut output[..2]), 1);
+        let next = buffer.next_timestamp().unwrap();
+        assert_eq!(next.frame, 4);
+        assert_eq!(next.captured_at, later);
+        assert_eq!(
+            buffer.latency(later + Duration::from_millis(5)),
+            Some(Duration::from_millis(5))
+        );
+
+        assert_eq!(buffer.read(&mut output), 2);
+        assert_eq!(buffer.next_timestamp(), None);
+        assert_eq!(buffer.read_position(), buffer.write_position());
+    }
+
+    #[test]
+    fn test_timestamped_overwrite_oldest() {
+        let mut buffer = TimestampedRingBuffer::new(4, 1, 1000)
+            .with_overrun_policy(OverrunPolicy::OverwriteOldest);
+        let start = Instant::now();
+        buffer.write(&[1.0, 2.0, 3.0], start);
+        buffer.write(&[4.0, 5.0, 6.0], start + Duration::from_millis(10));
+
+        // Frames 0 and 1 were overwritten, so reading starts at frame 2
+        let next = buffer.next_timestamp().unwrap();
+        assert_eq!(next.frame, 2);
+        assert_eq!(next.captured_at, start + Duration::from_millis(2));
+    }
+
+    #[test]
+    fn test_timestamped_overwrite_longer_than_capacity() {
+        let mut buffer = TimestampedRingBuffer::new(4, 1, 1000)
+            .with_overrun_policy(OverrunPolicy::OverwriteOldest);
+        let start = Instant::now();
+        let block: Vec<f32> = (0..10).map(|i| i as f32).collect();
+        assert_eq!(buffer.write(&block, start), 10);
+
+        // Only the last four frames, captured 6 ms into the block, remain
+        let next = buffer.next_timestamp().unwrap();
+        assert_eq!(next.captured_at, start + Duration::from_millis(6));
+        assert_eq!(
+            buffer.latency(start + Duration::from_millis(10)),
+            Some(Duration::from_millis(4))
+        );
+        let mut output = [0.0; 4];
+        assert_eq!(buffer.read(&mut output), 4);
+        assert_eq!(output, [6.0, 7.0, 8.0, 9.0]);
+    }
+
+    #[test]
+    fn test_timestamped_overwrite_without_reader() {
+        let mut buffer = TimestampedRingBuffer::new(8, 2, 1000)
+            .with_overrun_policy(OverrunPolicy::OverwriteOldest);
+        let start = Instant::now();
+        for i in 0..10_000u64 {
+            let captured_at = start + Duration::from_millis(3 * i);
+            assert_eq!(buffer.write(&[0.0; 6], captured_at), 3);
+        }
+
+        // Only the blocks still in the buffer keep a timestamp
+        assert!(buffer.blocks.len() <= 4, "{}", buffer.blocks.len());
+        assert!(buffer.blocks.capacity() <= TIMESTAMP_QUEUE_CAPACITY);
+        let next = buffer.next_timestamp().unwrap();
+        assert_eq!(next.frame, 30_000 - 8);
+        assert_eq!(next.captured_at, start + Duration::from_millis(30_000 - 8));
+    }
+
+    #[test]
+    fn test_fill_history_watermarks_and_window() {
+        let history = FillHistory::new(16);
+        let start = history.start;
+
+        history.record_at(4, start);
+        history.record_at(12, start);
+        history.record_at(16, start + Duration::from_secs(15));
+
+        let stats = history.stats_at(start + Duration::from_secs(20));
+        assert_eq!((stats.min_frames, stats.max_frames), (4, 16));
+        assert_eq!((stats.low_watermark, stats.high_watermark), (4, 16));
+        assert_eq!(stats.histogram[2], 1);
+        assert_eq!(stats.histogram[6], 1);
+        assert_eq!(stats.histogram[FILL_HISTOGRAM_BUCKETS - 1], 1);
+
+        // A minute later only the second slot is still in the window
+        let stats = history.stats_at(start + Duration::from_secs(65));
+        assert_eq!((stats.min_frames, stats.max_frames), (16, 16));
+        assert_eq!(stats.histogram.iter().sum::<u64>(), 1);
+        assert_eq!((stats.low_watermark, stats.high_watermark), (4, 16));
+
+        // Reusing the first slot drops its old contents
+        history.record_at(8, start + Duration::from_secs(60));
+        let stats = history.stats_at(start + Duration::from_secs(65));
+        assert_eq!((stats.min_frames, stats.max_frames), (8, 16));
+
+        history.reset();
+        assert_eq!(
+            history.stats(),
+            FillStats {
+                capacity_frames: 16,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_fill_history_recorded_after_read_instant() {
+        let history = FillHistory::new(16);
+        let start = history.start;
+        let now = start + Duration::from_secs(30);
+
+        // The audio thread records after the reader took its instant
+        history.record_at(6, now + Duration::from_secs(FILL_SLOT_SECS));
+        let stats = history.stats_at(now);
+        assert_eq!((stats.min_frames, stats.max_frames), (6, 6));
+        assert_eq!(stats.histogram.iter().sum::<u64>(), 1);
+    }
+
+    #[test]
+    fn test_triple_buffer_fill_stats() {
+        let mut triple = TripleRingBuffer::new(8);
+        let mut output = [0.0; 2];
+        triple.process(&[0.0; 6], &mut output).unwrap();
+
+        let stats = triple.stats();
+        assert_eq!(stats.input_fill.high_watermark, 6);
+        assert_eq!(stats.input_fill.low_watermark, 0);
+        assert_eq!(stats.output_fill.max_frames, 6);
+        assert_eq!(stats.output_fill.min_frames, 4);
+
+        triple.reset_fill_history();
+        assert_eq!(triple.stats().output_fill.high_watermark, 0);
+    }
+
+    #[test]
+    fn test_broadcast_readers_are_independent() {
+        let mut writer = BroadcastRingBuffer::new(8);
+        let mut recorder = writer.subscribe();
+        let mut meter = writer.subscribe();
+        assert_eq!(writer.reader_count(), 2);
+
+        writer.write(&[1.0, 2.0, 3.0]);
+
+        let mut output = [0.0; 8];
+        assert_eq!(recorder.read(&mut output), 3);
+        assert_eq!(&output[..3], &[1.0, 2.0, 3.0]);
+        assert_eq!(meter.read(&mut output[..1]), 1);
+        assert_eq!(meter.available(), 2);
+
+        // A late subscriber only sees new data
+        let mut sender = writer.subscribe();
+        writer.write(&[4.0]);
+        assert_eq!(sender.read(&mut output), 1);
+        assert_eq!(output[0], 4.0);
+        assert_eq!(meter.read(&mut output), 3);
+        assert_eq!(&output[..3], &[2.0, 3.0, 4.0]);
+    }
+
+    #[test]
+    fn test_broadcast_slow_reader_is_skipped() {
+        let mut writer = BroadcastRingBuffer::new(4);
+        let mut slow = writer.subscribe();
+
+        let input: Vec<f32> = (0..10).map(|i| i as f32).collect();
+        assert_eq!(writer.write(&input), 10);
+
+        let mut output = [0.0; 8];
+        assert_eq!(slow.read(&mut output), 4);
+        assert_eq!(&output[..4], &[6.0, 7.0, 8.0, 9.0]);
+        assert_eq!(slow.skipped(), 6);
+        assert_eq!(slow.lag_events(), 1);
+    }
+
+    #[test]
+    fn test_broadcast_readers_across_threads() {
+        use std::thread;
+
+        const TOTAL: usize = 100_000;
+        let mut writer = BroadcastRingBuffer::new(1024);
+        let readers: Vec<_> = (0..3)
+            .map(|_| {
+                let mut reader = writer.subscribe();
+                thread::spawn(move || {
+                    let mut output = [0.0f32; 64];
+                    let mut last = -1.0f32;
+                    let mut seen = 0u64;
+                    while last < (TOTAL - 1) as f32 {
+                        let read = reader.read(&mut output);
+                        for &sample in &output[..read] {
+                            // Samples arrive in order; gaps only when skipped
+                            assert!(sample > last);
+                            last = sample;
+                        }
+                        seen += read as u64;
+                    }
+                    assert_eq!(seen + reader.skipped(), TOTAL as u64);
+                })
+            })
+            .collect();
+
+        let mut chunk = [0.0f32; 100];
+        for start in (0..TOTAL).step_by(chunk.len()) {
+            for (i, sample) in chunk.iter_mut().enumerate() {
+                *sample = (start + i) as f32;
+            }
+            writer.write(&chunk);
+        }
+
+        for reader in readers {
+            reader.join().unwrap();
+        }
+    }
+
+    /// Fills and drains any [`SampleBuffer`] through the trait only.
+    fn exercise_sample_buffer<B: SampleBuffer<f32>>(buffer: &mut B) {
+        let capacity = buffer.capacity();
+        let input: Vec<f32> = (0..capacity + 2).map(|i| i as f32).collect();
+
+        assert_eq!(buffer.write(&input), capacity);
+        assert_eq!(buffer.available(), capacity);
+        assert_eq!(buffer.free_space(), 0);
+
+        let mut output = vec![0.0; 3];
+        assert_eq!(buffer.read(&mut output), 3);
+        assert_eq!(output, [0.0, 1.0, 2.0]);
+
+        // Wraps around the end of the storage
+        assert_eq!(buffer.write(&[100.0, 101.0]), 2);
+        let mut output = vec![0.0; capacity];
+        assert_eq!(buffer.read(&mut output), capacity - 1);
+        assert_eq!(output[capacity - 3..capacity - 1], [100.0, 101.0]);
+
+        buffer.write(&[1.0]);
+        buffer.clear();
+        assert_eq!(buffer.available(), 0);
+    }
+
+    #[test]
+    fn test_sample_buffer_implementations_agree() {
+        let mut ring = RingBuffer::<f32>::new(8);
+        exercise_sample_buffer(&mut ring);
+        assert_eq!((ring.overruns(), ring.underruns()), (1, 1));
+
+        let mut heapless = HeaplessRingBuffer::<f32, 9>::new();
+        assert_eq!(SampleBuffer::capacity(&heapless), 8);
+        exercise_sample_buffer(&mut heapless);
+        assert_eq!((heapless.overruns(), heapless.underruns()), (1, 1));
+    }
+
+    #[test]
+    fn test_read_exact_timeout() {
+        let (mut producer, mut consumer) = RingBuffer::<f32>::new(8).split();
+
+        // Times out with what was available and counts no underrun
+        producer.write(&[1.0, 2.0]);
+        let mut output = [0.0; 4];
+        assert_eq!(
+            consumer.read_exact_timeout(&mut output, Duration::from_millis(10)),
+            2
+        );
+        assert_eq!(consumer.underruns(), 0);
+
+        let writer = thread::spawn(move || {
+            for i in 0..4 {
+                thread::sleep(Duration::from_millis(5));
+                producer.write(&[i as f32]);
+            }
+        });
+        assert_eq!(
+            consumer.read_exact_timeout(&mut output, Duration::from_secs(5)),
+            4
+        );
+        assert_eq!(output, [0.0, 1.0, 2.0, 3.0]);
+        writer.join().unwrap();
+    }
+
+    #[test]
+    fn test_async_read_write() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let (mut producer, mut consumer) = RingBuffer::<f32>::new(4).split();
+
+        runtime.block_on(async move {
+            let writer = tokio::spawn(async move {
+                let input: Vec<f32> = (0..64).map(|i| i as f32).collect();
+                // Waits for the reader to make room several times
+                producer.write_all_async(&input).await;
+            });
+
+            let mut output = vec![0.0f32; 64];
+            consumer.read_exact_async(&mut output).await;
+            writer.await.unwrap();
+
+            assert!(output.iter().enumerate().all(|(i, &s)| s == i as f32));
+            assert_eq!(consumer.underruns(), 0);
+        });
+    }
+
+    #[test]
+    fn test_split_producer_consumer_threads() {
+        use std::thread;
+
+        const TOTAL: usize = 200_000;
+        let (mut producer, mut consumer) = RingBuffer::<f32>::new(256).split();
+
+        let writer = thread::spawn(move || {
+            let mut next = 0usize;
+            let mut chunk = [0.0f32; 37];
+            while next < TOTAL {
+                let len = chunk.len().min(TOTAL - next);
+                for (i, sample) in chunk[..len].iter_mut().enumerate() {
+                    *sample = (next + i) as f32;
+                }
+                let written = producer.write(&chunk[..len]);
+                next += written;
+                if written == 0 {
+                    thread::yield_now();
+                }
+            }
+        });
+
+        let reader = thread::spawn(move || {
+            let mut expected = 0usize;
+            let mut chunk = [0.0f32; 53];
+            while expected < TOTAL {
+                let read = consumer.read(&mut chunk);
+                for &sample in &chunk[..read] {
+                    assert_eq!(sample, expected as f32);
+                    expected += 1;
+                }
+                if read == 0 {
+                    thread::yield_now();
+                }
+            }
+            consumer.available()
+        });
+
+        writer.join().unwrap();
+        assert_eq!(reader.join().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_split_halves_share_state() {
+        let (mut producer, mut consumer) = RingBuffer::<f32>::new(8).split();
+        assert_eq!(producer.capacity(), 8);
+
+        assert_eq!(producer.write(&[1.0; 10]), 8);
+        assert_eq!(consumer.available(), 8);
+        assert_eq!(producer.free_space(), 0);
+
+        let mut output = [0.0; 3];
+        assert_eq!(consumer.read(&mut output), 3);
+        assert_eq!(producer.free_space(), 3);
 
-        // Process again to flush through pipeline
-        let _ = triple.process(&[], &mut output).unwrap();
+        consumer.clear();
+        assert_eq!(producer.available(), 0);
     }
 }
@@ -0,0 +1,501 @@
+//! Ring buffer shared between processes through `/dev/shm`.
+
+use crate::{AudioFormat, CableConfig, Error};
+use memmap2::MmapMut;
+use std::fs::{self, OpenOptions};
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::PathBuf;
+use std::ptr;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Identifies a mapping created by [`SharedRingBuffer::create`] ("VACR").
+const SHM_MAGIC: u32 = 0x5641_4352;
+
+/// Layout version of [`ShmHeader`].
+const SHM_VERSION: u32 = 1;
+
+/// Directory that holds the shared mappings.
+const SHM_DIR: &str = "/dev/shm";
+
+/// Header at the start of the mapping.
+///
+/// The layout is fixed so that any process, not only this crate, can read
+/// the stream format and cursors.
+#[repr(C)]
+struct ShmHeader {
+    magic: u32,
+    version: u32,
+    sample_rate: u32,
+    channels: u32,
+    format: u32,
+    _reserved: u32,
+    /// Size of the data region in bytes (a whole number of frames)
+    capacity: u64,
+    /// Total bytes written since creation
+    write_pos: AtomicU64,
+    /// Total bytes consumed since creation
+    read_pos: AtomicU64,
+}
+
+const HEADER_LEN: usize = std::mem::size_of::<ShmHeader>();
+
+/// A byte ring buffer that lives in a named `/dev/shm` mapping.
+///
+/// The process that creates the buffer owns the name and removes it on
+/// drop; other processes attach with [`SharedRingBuffer::open`]. The ring
+/// holds raw interleaved samples in the format stored in its header, and
+/// reads and writes move whole frames only. Like [`RingBuffer`], it is
+/// single-producer, single-consumer: one process writes and one reads.
+///
+/// [`RingBuffer`]: crate::buffer::RingBuffer
+pub struct SharedRingBuffer {
+    /// Start of the mapping, taken from `_map` so writes through it are valid
+    base: *mut u8,
+    _map: MmapMut,
+    path: PathBuf,
+    owner: bool,
+    frame_bytes: usize,
+    /// Size of the data region in bytes, fixed when the mapping is attached
+    /// so that a peer rewriting the header cannot move it
+    capacity: usize,
+}
+
+impl SharedRingBuffer {
+    /// Creates a named shared buffer holding `capacity_frames` frames.
+    ///
+    /// The frame capacity is rounded up to the next power of 2. Fails if a
+    /// buffer with the same name already exists. The mapping is only
+    /// accessible to the current user.
+    pub fn create(name: &str, config: &CableConfig, capacity_frames: usize) -> Result<Self, Error> {
+        let path = shm_path(name)?;
+        let frame_bytes = config.format.bytes_per_sample() * config.channels.max(1) as usize;
+        let capacity = capacity_frames.max(1).next_power_of_two() * frame_bytes;
+
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .mode(0o600)
+            .open(&path)?;
+        file.set_len((HEADER_LEN + capacity) as u64)?;
+
+        // SAFETY: the file was just created by us and is not truncated while
+        // mapped; concurrent access goes through the atomic cursors.
+        let mut map = unsafe { MmapMut::map_mut(&file)? };
+        let buffer = Self {
+            base: map.as_mut_ptr(),
+            _map: map,
+            path,
+            owner: true,
+            frame_bytes,
+            capacity,
+        };
+
+        // SAFETY: the mapping is page-aligned and at least HEADER_LEN long.
+        unsafe {
+            ptr::write(
+                buffer.base as *mut ShmHeader,
+                ShmHeader {
+                    magic: SHM_MAGIC,
+                    version: SHM_VERSION,
+                    sample_rate: config.sample_rate,
+                    channels: config.channels.max(1) as u32,
+                    format: format_code(config.format),
+                    _reserved: 0,
+                    capacity: capacity as u64,
+                    write_pos: AtomicU64::new(0),
+                    read_pos: AtomicU64::new(0),
+                },
+            );
+        }
+
+        Ok(buffer)
+    }
+
+    /// Creates a shared buffer named after the cable's device name.
+    ///
+    /// The capacity is four times the configured buffer size.
+    pub fn from_config(config: &CableConfig) -> Result<Self, Error> {
+        let frames = config.buffer_size.div_ceil(config.channels.max(1) as usize);
+        Self::create(&Self::config_name(config), config, frames * 4)
+    }
+
+    /// Returns the name [`SharedRingBuffer::from_config`] uses for `config`.
+    pub fn config_name(config: &CableConfig) -> String {
+        let name: String = config
+            .device_name
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() {
+                    c.to_ascii_lowercase()
+                } else {
+                    '-'
+                }
+            })
+            .collect();
+        format!("virtual-audio-{name}")
+    }
+
+    /// Attaches to a shared buffer created by another process.
+    pub fn open(name: &str) -> Result<Self, Error> {
+        let path = shm_path(name)?;
+        let file = OpenOptions::new().read(true).write(true).open(&path)?;
+        let len = file.metadata()?.len() as usize;
+        if len < HEADER_LEN {
+            return Err(Error::BufferError(format!("{name}: mapping too small")));
+        }
+
+        // SAFETY: the creator never truncates the file while it exists;
+        // shared state is only accessed through the atomic cursors.
+        let mut map = unsafe { MmapMut::map_mut(&file)? };
+        let mut buffer = Self {
+            base: map.as_mut_ptr(),
+            _map: map,
+            path,
+            owner: false,
+            frame_bytes: 1,
+            capacity: 0,
+        };
+
+        let header = buffer.header();
+        if header.magic != SHM_MAGIC || header.version != SHM_VERSION {
+            return Err(Error::BufferError(format!(
+                "{name}: not a virtual audio ring buffer"
+            )));
+        }
+        let format = format_from_code(header.format)
+            .ok_or_else(|| Error::BufferError(format!("{name}: unknown sample format")))?;
+        let frame_bytes = format.bytes_per_sample() * header.channels as usize;
+        let capacity = header.capacity as usize;
+        if frame_bytes == 0
+            || capacity == 0
+            || !capacity.is_multiple_of(frame_bytes)
+            || !(capacity / frame_bytes).is_power_of_two()
+            || capacity > len - HEADER_LEN
+        {
+            return Err(Error::BufferError(format!("{name}: corrupt header")));
+        }
+
+        buffer.frame_bytes = frame_bytes;
+        buffer.capacity = capacity;
+        Ok(buffer)
+    }
+
+    /// Writes interleaved sample bytes into the buffer.
+    ///
+    /// A trailing partial frame in `bytes` is ignored. Returns the number of
+    /// bytes actually written, which is always a whole number of frames.
+    /// Nothing is written while the cursors are corrupt.
+    pub fn write(&mut self, bytes: &[u8]) -> usize {
+        let header = self.header();
+        let write_pos = header.write_pos.load(Ordering::Relaxed);
+        let read_pos = header.read_pos.load(Ordering::Acquire);
+        let Some(used) = self.used_bytes(write_pos, read_pos) else {
+            return 0;
+        };
+
+        let to_write = bytes.len().min(self.capacity - used);
+        let to_write = to_write - to_write % self.frame_bytes;
+        self.copy_in(write_pos, &bytes[..to_write]);
+
+        self.header()
+            .write_pos
+            .store(write_pos.wrapping_add(to_write as u64), Ordering::Release);
+        to_write
+    }
+
+    /// Reads interleaved sample bytes from the buffer.
+    ///
+    /// Returns the number of bytes actually read, which is always a whole
+    /// number of frames. The rest of `output` is left untouched. Nothing is
+    /// read while the cursors are corrupt.
+    pub fn read(&mut self, output: &mut [u8]) -> usize {
+        let header = self.header();
+        let read_pos = header.read_pos.load(Ordering::Relaxed);
+        let write_pos = header.write_pos.load(Ordering::Acquire);
+        let Some(used) = self.used_bytes(write_pos, read_pos) else {
+            return 0;
+        };
+
+        let to_read = output.len().min(used);
+        let to_read = to_read - to_read % self.frame_bytes;
+        self.copy_out(read_pos, &mut output[..to_read]);
+
+        self.header()
+            .read_pos
+            .store(read_pos.wrapping_add(to_read as u64), Ordering::Release);
+        to_read
+    }
+
+    /// Returns the number of frames available for reading.
+    pub fn available_frames(&self) -> usize {
+        self.used_frames().unwrap_or(0)
+    }
+
+    /// Returns the number of frames that can be written.
+    pub fn free_frames(&self) -> usize {
+        self.used_frames()
+            .map_or(0, |used| self.capacity_frames() - used)
+    }
+
+    /// Returns the total capacity of the buffer in frames.
+    pub fn capacity_frames(&self) -> usize {
+        self.capacity / self.frame_bytes
+    }
+
+    /// Returns `true` if the cursors in the header are inconsistent, e.g.
+    /// because the peer process crashed or wrote garbage. A corrupt buffer
+    /// neither reads nor writes until [`SharedRingBuffer::reset`].
+    pub fn is_corrupt(&self) -> bool {
+        self.used_frames().is_none()
+    }
+
+    /// Discards the buffered audio by moving the read cursor to the write
+    /// cursor, which also recovers a corrupt buffer.
+    ///
+    /// Must only be called while the peer is not reading.
+    pub fn reset(&mut self) {
+        let header = self.header();
+        let write_pos = header.write_pos.load(Ordering::Acquire);
+        header.read_pos.store(write_pos, Ordering::Release);
+    }
+
+    fn used_frames(&self) -> Option<usize> {
+        let header = self.header();
+        let write_pos = header.write_pos.load(Ordering::Acquire);
+        let read_pos = header.read_pos.load(Ordering::Acquire);
+        self.used_bytes(write_pos, read_pos)
+            .map(|used| used / self.frame_bytes)
+    }
+
+    /// Returns the number of buffered bytes, or `None` if the cursors are
+    /// further apart than the capacity or not frame-aligned.
+    fn used_bytes(&self, write_pos: u64, read_pos: u64) -> Option<usize> {
+        let used = write_pos.wrapping_sub(read_pos);
+        (used <= self.capacity as u64 && (used as usize).is_multiple_of(self.frame_bytes))
+            .then_some(used as usize)
+    }
+
+    /// Returns the sample rate stored in the header.
+    pub fn sample_rate(&self) -> u32 {
+        self.header().sample_rate
+    }
+
+    /// Returns the number of channels stored in the header.
+    pub fn channels(&self) -> u16 {
+        self.header().channels as u16
+    }
+
+    /// Returns the sample format stored in the header.
+    pub fn format(&self) -> AudioFormat {
+        format_from_code(self.header().format).unwrap_or(AudioFormat::F32LE)
+    }
+
+    /// Returns the size of one frame in bytes.
+    pub fn frame_bytes(&self) -> usize {
+        self.frame_bytes
+    }
+
+    /// Returns `true` if this process created the buffer.
+    pub fn is_owner(&self) -> bool {
+        self.owner
+    }
+
+    fn header(&self) -> &ShmHeader {
+        // SAFETY: the mapping starts with an initialized, page-aligned header.
+        unsafe { &*(self.base as *const ShmHeader) }
+    }
+
+    fn data(&self) -> *mut u8 {
+        // SAFETY: the data region directly follows the header.
+        unsafe { self.base.add(HEADER_LEN) }
+    }
+
+    /// Copies `bytes` into the ring starting at byte position `pos`.
+    fn copy_in(&self, pos: u64, bytes: &[u8]) {
+        let capacity = self.capacity;
+        let bytes = &bytes[..bytes.len().min(capacity)];
+        let start = (pos % capacity as u64) as usize;
+        let head = bytes.len().min(capacity - start);
+        // SAFETY: both regions are inside the data region and are free, so
+        // the reader does not access them until the cursor is published.
+        unsafe {
+            ptr::copy_nonoverlapping(bytes.as_ptr(), self.data().add(start), head);
+            ptr::copy_nonoverlapping(bytes[head..].as_ptr(), self.data(), bytes.len() - head);
+        }
+    }
+
+    /// Copies bytes out of the ring starting at byte position `pos`.
+    fn copy_out(&self, pos: u64, output: &mut [u8]) {
+        let capacity = self.capacity;
+        let len = output.len().min(capacity);
+        let output = &mut output[..len];
+        let start = (pos % capacity as u64) as usize;
+        let head = output.len().min(capacity - start);
+        // SAFETY: both regions are inside the data region and hold published
+        // samples, which the writer does not touch until they are consumed.
+        unsafe {
+            ptr::copy_nonoverlapping(self.data().add(start), output.as_mut_ptr(), head);
+            ptr::copy_nonoverlapping(
+                self.data(),
+                output[head..].as_mut_ptr(),
+                output.len() - head,
+            );
+        }
+    }
+}
+
+impl Drop for SharedRingBuffer {
+    fn drop(&mut self) {
+        if self.owner {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
+
+// SAFETY: `base` points into the owned mapping, which is only accessed
+// through `&mut self` or the atomic cursors.
+unsafe impl Send for SharedRingBuffer {}
+
+fn shm_path(name: &str) -> Result<PathBuf, Error> {
+    if name.is_empty() || name.contains('/') || name.starts_with('.') {
+        return Err(Error::BufferError(format!(
+            "invalid shared memory name: {name:?}"
+        )));
+    }
+    Ok(PathBuf::from(SHM_DIR).join(name))
+}
+
+/// Header format codes are indices into [`AudioFormat::ALL`], which only
+/// grows at the end.
+fn format_code(format: AudioFormat) -> u32 {
+    AudioFormat::ALL
+        .iter()
+        .position(|&known| known == format)
+        .unwrap_or(0) as u32
+}
+
+fn format_from_code(code: u32) -> Option<AudioFormat> {
+    AudioFormat::ALL.get(code as usize).copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unique_name(test: &str) -> String {
+        format!("virtual-audio-test-{test}-{}", std::process::id())
+    }
+
+    #[test]
+    fn test_shared_ring_buffer_attach() {
+        let name = unique_name("attach");
+        let config = CableConfig {
+            sample_rate: 44100,
+            channels: 2,
+            format: AudioFormat::S16LE,
+            ..Default::default()
+        };
+        let mut writer = SharedRingBuffer::create(&name, &config, 4).unwrap();
+        let mut reader = SharedRingBuffer::open(&name).unwrap();
+
+        assert!(writer.is_owner());
+        assert!(!reader.is_owner());
+        assert_eq!(reader.sample_rate(), 44100);
+        assert_eq!(reader.channels(), 2);
+        assert_eq!(reader.format(), AudioFormat::S16LE);
+        assert_eq!(reader.capacity_frames(), 4);
+
+        // Whole frames only: the trailing 2 bytes are ignored
+        let bytes: Vec<u8> = (0..14).collect();
+        assert_eq!(writer.write(&bytes), 12);
+        assert_eq!(reader.available_frames(), 3);
+
+        let mut output = [0u8; 8];
+        assert_eq!(reader.read(&mut output), 8);
+        assert_eq!(output, [0, 1, 2, 3, 4, 5, 6, 7]);
+
+        // Wraps around the end of the data region
+        assert_eq!(writer.write(&[20; 12]), 12);
+        let mut output = [0u8; 16];
+        assert_eq!(reader.read(&mut output), 16);
+        assert_eq!(&output[..4], &[8, 9, 10, 11]);
+        assert_eq!(&output[4..], &[20; 12]);
+
+        drop(writer);
+        assert!(SharedRingBuffer::open(&name).is_err());
+    }
+
+    #[test]
+    fn test_shared_ring_buffer_is_private() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let name = unique_name("private");
+        let _buffer = SharedRingBuffer::create(&name, &CableConfig::default(), 4).unwrap();
+        let mode = fs::metadata(shm_path(&name).unwrap())
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+
+    #[test]
+    fn test_shared_ring_buffer_corrupt_cursors() {
+        let name = unique_name("cursors");
+        let config = CableConfig {
+            channels: 1,
+            format: AudioFormat::S16LE,
+            ..Default::default()
+        };
+        let mut writer = SharedRingBuffer::create(&name, &config, 4).unwrap();
+        let mut reader = SharedRingBuffer::open(&name).unwrap();
+        assert_eq!(writer.write(&[1; 4]), 4);
+
+        // A peer moves its read cursor past the write cursor
+        reader.header().read_pos.store(100, Ordering::Release);
+        assert!(writer.is_corrupt());
+        assert_eq!(writer.write(&[2; 64]), 0);
+        assert_eq!(reader.read(&mut [0; 64]), 0);
+        assert_eq!(reader.available_frames(), 0);
+        assert_eq!(writer.free_frames(), 0);
+
+        // ...or far ahead of it
+        reader.header().read_pos.store(0, Ordering::Release);
+        writer.header().write_pos.store(u64::MAX, Ordering::Release);
+        assert_eq!(reader.read(&mut [0; 64]), 0);
+
+        writer.reset();
+        assert!(!reader.is_corrupt());
+        assert_eq!(writer.write(&[3; 64]), 8);
+        let mut output = [0; 64];
+        assert_eq!(reader.read(&mut output), 8);
+        assert_eq!(&output[..8], &[3; 8]);
+    }
+
+    #[test]
+    fn test_shared_ring_buffer_rejects_bad_capacity() {
+        let config = CableConfig {
+            channels: 1,
+            format: AudioFormat::S16LE,
+            ..Default::default()
+        };
+        for (test, capacity) in [("zero", 0), ("npot", 6)] {
+            let name = unique_name(test);
+            let buffer = SharedRingBuffer::create(&name, &config, 4).unwrap();
+            // SAFETY: no other handle is attached yet.
+            unsafe { (*(buffer.base as *mut ShmHeader)).capacity = capacity };
+            assert!(SharedRingBuffer::open(&name).is_err(), "{test}");
+        }
+    }
+
+    #[test]
+    fn test_shared_ring_buffer_rejects_bad_names() {
+        let config = CableConfig::default();
+        assert!(SharedRingBuffer::create("../escape", &config, 4).is_err());
+        assert!(SharedRingBuffer::open("").is_err());
+        assert_eq!(
+            SharedRingBuffer::config_name(&config),
+            "virtual-audio-virtual-audio-cable"
+        );
+    }
+}
@@ -0,0 +1,447 @@
+//! In-line audio effects.
+//!
+//! An [`AudioEffect`] processes interleaved blocks in place. Effects are
+//! run in order by an [`EffectChain`], which [`crate::TripleRingBuffer`]
+//! applies between its input and resample buffers. Effects can be added,
+//! removed, bypassed and reordered while audio is flowing: each change
+//! crossfades between the dry and the processed signal instead of switching
+//! abruptly.
+
+use std::any::Any;
+
+mod eq;
+
+pub use eq::{EqBand, FilterType, ParametricEq};
+
+/// Frames over which an effect is faded in or out by default.
+const DEFAULT_FADE_FRAMES: usize = 256;
+
+/// Frames of dry signal kept per crossfade pass.
+const SCRATCH_FRAMES: usize = 256;
+
+/// An effect that processes interleaved audio in place.
+///
+/// Effects run on the real-time path, so `process` must not allocate or
+/// block.
+pub trait AudioEffect: Any + Send {
+    /// Processes a block of interleaved samples in place.
+    ///
+    /// The block holds whole frames of the channel count the effect was
+    /// built for.
+    fn process(&mut self, block: &mut [f32]);
+
+    /// Clears internal state such as delay lines or filter history.
+    fn reset(&mut self) {}
+
+    /// Returns the delay the effect adds, in frames.
+    fn latency_frames(&self) -> usize {
+        0
+    }
+}
+
+/// Identifies an effect within an [`EffectChain`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct EffectId(u64);
+
+struct EffectSlot {
+    id: EffectId,
+    effect: Box<dyn AudioEffect>,
+    bypassed: bool,
+    /// Fading out before being taken out of the chain
+    removing: bool,
+    /// Fading out before being moved to this position
+    moving_to: Option<usize>,
+    /// Share of the processed signal, from 0.0 (dry) to 1.0 (wet)
+    mix: f32,
+}
+
+impl EffectSlot {
+    fn target_mix(&self) -> f32 {
+        if self.bypassed || self.removing || self.moving_to.is_some() {
+            0.0
+        } else {
+            1.0
+        }
+    }
+}
+
+/// An ordered list of effects applied one after another.
+///
+/// Adding, removing, bypassing or re-enabling an effect fades it in or out
+/// over a short crossfade, so the changes can be made while the chain is
+/// running. A removed effect stays in the chain until its fade-out is done
+/// and is then handed back through [`EffectChain::take_removed`], so it is
+/// never dropped on the audio thread. A moved effect is faded out where it
+/// is, moved, and faded back in from a clean state at its new position, so
+/// reordering effects that do not commute does not click either.
+pub struct EffectChain {
+    channels: usize,
+    fade_frames: usize,
+    slots: Vec<EffectSlot>,
+    removed: Vec<Box<dyn AudioEffect>>,
+    /// Dry copy of the block while an effect is fading
+    dry: Vec<f32>,
+    next_id: u64,
+}
+
+impl EffectChain {
+    /// Creates an empty chain for interleaved audio with `channels` channels.
+    pub fn new(channels: usize) -> Self {
+        let channels = channels.max(1);
+        Self {
+            channels,
+            fade_frames: DEFAULT_FADE_FRAMES,
+            slots: Vec::new(),
+            removed: Vec::new(),
+            dry: vec![0.0; SCRATCH_FRAMES * channels],
+            next_id: 0,
+        }
+    }
+
+    /// Returns the number of interleaved channels per frame.
+    pub fn channels(&self) -> usize {
+        self.channels
+    }
+
+    /// Sets the length of the crossfade used for changes, in frames.
+    pub fn set_fade_frames(&mut self, frames: usize) {
+        self.fade_frames = frames.max(1);
+    }
+
+    /// Returns the number of effects in the chain, including any still
+    /// fading out after removal.
+    pub fn len(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// Returns true if the chain holds no effects.
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+
+    /// Returns the effect ids in processing order.
+    pub fn ids(&self) -> impl Iterator<Item = EffectId> + '_ {
+        self.slots.iter().map(|slot| slot.id)
+    }
+
+    /// Appends an effect to the end of the chain and fades it in.
+    pub fn push(&mut self, effect: impl AudioEffect + 'static) -> EffectId {
+        self.insert(self.slots.len(), effect)
+    }
+
+    /// Inserts an effect at `index` (clamped to the chain length) and fades
+    /// it in.
+    pub fn insert(&mut self, index: usize, effect: impl AudioEffect + 'static) -> EffectId {
+        let id = EffectId(self.next_id);
+        self.next_id += 1;
+        let slot = EffectSlot {
+            id,
+            effect: Box::new(effect),
+            bypassed: false,
+            removing: false,
+            moving_to: None,
+            mix: 0.0,
+        };
+        self.slots.insert(index.min(self.slots.len()), slot);
+        id
+    }
+
+    /// Fades an effect out and removes it from the chain.
+    ///
+    /// Returns false if no such effect is in the chain.
+    pub fn remove(&mut self, id: EffectId) -> bool {
+        let Some(slot) = self.slot_mut(id) else {
+            return false;
+        };
+        slot.removing = true;
+        // Room for every pending removal, so retiring them on the audio
+        // thread never allocates
+        let pending = self.slots.iter().filter(|slot| slot.removing).count();
+        self.removed.reserve(pending);
+        true
+    }
+
+    /// Returns the effects whose removal has completed.
+    pub fn take_removed(&mut self) -> Vec<Box<dyn AudioEffect>> {
+        std::mem::take(&mut self.removed)
+    }
+
+    /// Moves an effect to `index` in the processing order (clamped to the
+    /// chain length).
+    ///
+    /// The effect first fades out at its current position; only then does
+    /// [`EffectChain::position`] report the new index. Returns false if no
+    /// such effect is in the chain.
+    pub fn move_to(&mut self, id: EffectId, index: usize) -> bool {
+        let Some(slot) = self.slot_mut(id) else {
+            return false;
+        };
+        slot.moving_to = Some(index);
+        true
+    }
+
+    /// Returns an effect of the chain as its concrete type, e.g. to change
+    /// its parameters.
+    ///
+    /// Returns `None` if no such effect is in the chain or it is not a `T`.
+    pub fn effect<T: AudioEffect>(&self, id: EffectId) -> Option<&T> {
+        let position = self.position(id)?;
+        let effect: &dyn Any = self.slots[position].effect.as_ref();
+        effect.downcast_ref()
+    }
+
+    /// Mutable version of [`EffectChain::effect`].
+    pub fn effect_mut<T: AudioEffect>(&mut self, id: EffectId) -> Option<&mut T> {
+        let effect: &mut dyn Any = self.slot_mut(id)?.effect.as_mut();
+        effect.downcast_mut()
+    }
+
+    /// Returns the position of an effect in the processing order.
+    pub fn position(&self, id: EffectId) -> Option<usize> {
+        self.slots.iter().position(|slot| slot.id == id)
+    }
+
+    /// Bypasses or re-enables an effect, fading between the dry and the
+    /// processed signal.
+    ///
+    /// A bypassed effect is not run and is reset once faded out. Returns
+    /// false if no such effect is in the chain.
+    pub fn set_bypass(&mut self, id: EffectId, bypassed: bool) -> bool {
+        let Some(slot) = self.slot_mut(id) else {
+            return false;
+        };
+        slot.bypassed = bypassed;
+        true
+    }
+
+    /// Returns whether an effect is bypassed, or `None` if it is not in the
+    /// chain.
+    pub fn is_bypassed(&self, id: EffectId) -> Option<bool> {
+        let position = self.position(id)?;
+        Some(self.slots[position].bypassed)
+    }
+
+    /// Returns the delay of the chain in frames, summed over the effects
+    /// that are neither bypassed nor being removed.
+    pub fn latency_frames(&self) -> usize {
+        self.slots
+            .iter()
+            .filter(|slot| slot.target_mix() > 0.0)
+            .map(|slot| slot.effect.latency_frames())
+            .sum()
+    }
+
+    /// Runs every effect over a block of interleaved samples in place.
+    ///
+    /// The block must hold whole frames.
+    pub fn process(&mut self, block: &mut [f32]) {
+        let channels = self.channels;
+        let step = 1.0 / self.fade_frames as f32;
+
+        for slot in &mut self.slots {
+            let target = slot.target_mix();
+            if slot.mix == target {
+                if target > 0.0 {
+                    slot.effect.process(block);
+                }
+                continue;
+            }
+
+            for chunk in block.chunks_mut(self.dry.len()) {
+                let dry = &mut self.dry[..chunk.len()];
+                dry.copy_from_slice(chunk);
+                slot.effect.process(chunk);
+                for (wet_frame, dry_frame) in chunk
+                    .chunks_exact_mut(channels)
+                    .zip(dry.chunks_exact(channels))
+                {
+                    slot.mix = if target > slot.mix {
+                        (slot.mix + step).min(target)
+                    } else {
+                        (slot.mix - step).max(target)
+                    };
+                    for (wet, &dry) in wet_frame.iter_mut().zip(dry_frame) {
+                        *wet = dry + (*wet - dry) * slot.mix;
+                    }
+                }
+            }
+
+            if slot.mix == 0.0 {
+                // Faded out: start from a clean state when re-enabled
+                slot.effect.reset();
+            }
+        }
+
+        self.retire();
+    }
+
+    /// Moves effects that finished fading out after removal to the removed
+    /// list, and those being reordered to their new position.
+    fn retire(&mut self) {
+        while let Some(index) = self
+            .slots
+            .iter()
+            .position(|slot| slot.removing && slot.mix == 0.0)
+        {
+            let slot = self.slots.remove(index);
+            self.removed.push(slot.effect);
+        }
+
+        while let Some(position) = self
+            .slots
+            .iter()
+            .position(|slot| slot.moving_to.is_some() && slot.mix == 0.0)
+        {
+            let mut slot = self.slots.remove(position);
+            let index = slot.moving_to.take().unwrap_or(position);
+            self.slots.insert(index.min(self.slots.len()), slot);
+        }
+    }
+
+    fn slot_mut(&mut self, id: EffectId) -> Option<&mut EffectSlot> {
+        self.slots.iter_mut().find(|slot| slot.id == id)
+    }
+}
+
+impl AudioEffect for EffectChain {
+    fn process(&mut self, block: &mut [f32]) {
+        EffectChain::process(self, block);
+    }
+
+    /// Resets every effect and completes any pending fades.
+    fn reset(&mut self) {
+        for slot in &mut self.slots {
+            slot.effect.reset();
+            slot.mix = slot.target_mix();
+        }
+        self.retire();
+    }
+
+    fn latency_frames(&self) -> usize {
+        EffectChain::latency_frames(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Gain(f32);
+
+    impl AudioEffect for Gain {
+        fn process(&mut self, block: &mut [f32]) {
+            block.iter_mut().for_each(|s| *s *= self.0);
+        }
+    }
+
+    struct Offset(f32);
+
+    impl AudioEffect for Offset {
+        fn process(&mut self, block: &mut [f32]) {
+            block.iter_mut().for_each(|s| *s += self.0);
+        }
+
+        fn latency_frames(&self) -> usize {
+            3
+        }
+    }
+
+    fn run(chain: &mut EffectChain, frames: usize) -> Vec<f32> {
+        let mut block = vec![1.0; frames * chain.channels()];
+        chain.process(&mut block);
+        block
+    }
+
+    #[test]
+    fn test_effects_run_in_order() {
+        let mut chain = EffectChain::new(2);
+        chain.set_fade_frames(1);
+        let gain = chain.push(Gain(2.0));
+        let offset = chain.push(Offset(1.0));
+        assert_eq!(chain.ids().collect::<Vec<_>>(), [gain, offset]);
+
+        assert_eq!(run(&mut chain, 4), [3.0; 8]);
+
+        assert!(chain.move_to(offset, 0));
+        run(&mut chain, 1);
+        assert_eq!(chain.position(offset), Some(0));
+        assert_eq!(run(&mut chain, 4), [4.0; 8]);
+        assert_eq!(chain.latency_frames(), 3);
+    }
+
+    #[test]
+    fn test_move_crossfades() {
+        let mut chain = EffectChain::new(1);
+        chain.set_fade_frames(8);
+        chain.push(Gain(2.0));
+        let offset = chain.push(Offset(1.0));
+        run(&mut chain, 16);
+
+        // The offset fades out after the gain, then back in before it
+        assert!(chain.move_to(offset, 0));
+        assert_eq!(chain.position(offset), Some(1));
+        let mut output = run(&mut chain, 16);
+        assert_eq!(chain.position(offset), Some(0));
+        output.extend(run(&mut chain, 16));
+        assert_eq!(output[0], 2.875);
+        assert_eq!(output[15], 2.0);
+        assert!(output.windows(2).all(|w| (w[0] - w[1]).abs() <= 0.25));
+        assert_eq!(output[31], 4.0);
+        assert_eq!(run(&mut chain, 4), [4.0; 4]);
+    }
+
+    #[test]
+    fn test_effect_downcast() {
+        let mut chain = EffectChain::new(1);
+        let id = chain.push(Gain(0.5));
+        assert!(chain.effect::<Offset>(id).is_none());
+        chain.effect_mut::<Gain>(id).unwrap().0 = 0.25;
+        assert_eq!(chain.effect::<Gain>(id).unwrap().0, 0.25);
+    }
+
+    #[test]
+    fn test_bypass_crossfades() {
+        let mut chain = EffectChain::new(1);
+        chain.set_fade_frames(100);
+        let mute = chain.push(Gain(0.0));
+
+        let fade_in = run(&mut chain, 300);
+        assert!(fade_in.windows(2).all(|w| (w[0] - w[1]).abs() <= 0.0101));
+        assert_eq!(fade_in[0], 0.99);
+        assert!(fade_in[110..].iter().all(|&s| s == 0.0));
+
+        assert!(chain.set_bypass(mute, true));
+        assert_eq!(chain.is_bypassed(mute), Some(true));
+        let fade_out = run(&mut chain, 300);
+        assert!(fade_out.windows(2).all(|w| (w[0] - w[1]).abs() <= 0.0101));
+        assert!(fade_out[110..].iter().all(|&s| s == 1.0));
+        assert_eq!(run(&mut chain, 10), [1.0; 10]);
+    }
+
+    #[test]
+    fn test_remove_after_fade_out() {
+        let mut chain = EffectChain::new(2);
+        chain.set_fade_frames(8);
+        let offset = chain.push(Offset(0.5));
+        chain.process(&mut [0.0; 32]);
+
+        assert!(chain.remove(offset));
+        assert_eq!(chain.latency_frames(), 0);
+        chain.process(&mut [0.0; 8]);
+        assert_eq!(chain.len(), 1);
+        assert!(chain.take_removed().is_empty());
+
+        chain.process(&mut [0.0; 8]);
+        assert!(chain.is_empty());
+        assert_eq!(chain.take_removed().len(), 1);
+        assert!(!chain.remove(offset));
+        assert_eq!(run(&mut chain, 4), [1.0; 8]);
+
+        // Several removals before the next block all have room reserved
+        let ids: Vec<_> = (0..3).map(|_| chain.push(Gain(1.0))).collect();
+        for &id in &ids {
+            chain.remove(id);
+        }
+        assert!(chain.removed.capacity() >= 3);
+    }
+}
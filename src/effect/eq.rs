@@ -0,0 +1,511 @@
+//! Multi-band parametric equalizer.
+
+use super::AudioEffect;
+use crate::audio::biquad::Biquad;
+
+use std::f64::consts::PI;
+
+/// Default time constant of parameter smoothing.
+const DEFAULT_SMOOTHING_MS: f32 = 20.0;
+
+/// Frames between coefficient updates while parameters are smoothed.
+const SMOOTHING_BLOCK_FRAMES: usize = 32;
+
+/// Response of an equalizer band, after the RBJ Audio EQ Cookbook.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterType {
+    /// Boost or cut around the frequency
+    Peaking,
+
+    /// Boost or cut below the frequency
+    LowShelf,
+
+    /// Boost or cut above the frequency
+    HighShelf,
+
+    /// Pass below the frequency (12 dB/octave)
+    LowPass,
+
+    /// Pass above the frequency (12 dB/octave)
+    HighPass,
+
+    /// Remove a narrow band around the frequency
+    Notch,
+
+    /// Pass a band around the frequency, 0 dB at its centre
+    BandPass,
+}
+
+/// Parameters of one equalizer band.
+///
+/// `gain_db` only affects peaking and shelf bands. For shelves, `q` sets
+/// the steepness of the transition; the default of 1/√2 gives the steepest
+/// shelf without overshoot.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EqBand {
+    /// Response of the band
+    pub filter_type: FilterType,
+
+    /// Centre, corner or shelf frequency in Hz
+    pub frequency_hz: f32,
+
+    /// Quality factor (bandwidth)
+    pub q: f32,
+
+    /// Boost (positive) or cut (negative) in dB
+    pub gain_db: f32,
+}
+
+impl EqBand {
+    /// Creates a band.
+    pub fn new(filter_type: FilterType, frequency_hz: f32, q: f32, gain_db: f32) -> Self {
+        Self {
+            filter_type,
+            frequency_hz,
+            q,
+            gain_db,
+        }
+    }
+
+    /// Creates a peaking band.
+    pub fn peaking(frequency_hz: f32, q: f32, gain_db: f32) -> Self {
+        Self::new(FilterType::Peaking, frequency_hz, q, gain_db)
+    }
+
+    /// Creates a low shelf.
+    pub fn low_shelf(frequency_hz: f32, gain_db: f32) -> Self {
+        Self::new(FilterType::LowShelf, frequency_hz, SHELF_Q, gain_db)
+    }
+
+    /// Creates a high shelf.
+    pub fn high_shelf(frequency_hz: f32, gain_db: f32) -> Self {
+        Self::new(FilterType::HighShelf, frequency_hz, SHELF_Q, gain_db)
+    }
+
+    /// Creates a low-pass band.
+    pub fn low_pass(frequency_hz: f32, q: f32) -> Self {
+        Self::new(FilterType::LowPass, frequency_hz, q, 0.0)
+    }
+
+    /// Creates a high-pass band, e.g. at 80 Hz to remove rumble and
+    /// proximity boom from a microphone.
+    pub fn high_pass(frequency_hz: f32, q: f32) -> Self {
+        Self::new(FilterType::HighPass, frequency_hz, q, 0.0)
+    }
+
+    /// Creates a notch, e.g. at 50 or 60 Hz with a high `q` to cut mains
+    /// hum.
+    pub fn notch(frequency_hz: f32, q: f32) -> Self {
+        Self::new(FilterType::Notch, frequency_hz, q, 0.0)
+    }
+
+    /// Creates a band-pass band.
+    pub fn band_pass(frequency_hz: f32, q: f32) -> Self {
+        Self::new(FilterType::BandPass, frequency_hz, q, 0.0)
+    }
+
+    /// Returns true if the frequency, Q and gain are all finite.
+    fn is_finite(&self) -> bool {
+        self.frequency_hz.is_finite() && self.q.is_finite() && self.gain_db.is_finite()
+    }
+
+    /// Returns normalized biquad coefficients for a sample rate.
+    fn coefficients(&self, sample_rate: u32) -> ([f64; 3], [f64; 2]) {
+        let rate = f64::from(sample_rate);
+        let frequency = f64::from(self.frequency_hz).clamp(1.0, 0.499 * rate);
+        let q = f64::from(self.q).max(0.01);
+        let w0 = 2.0 * PI * frequency / rate;
+        let (sin, cos) = w0.sin_cos();
+        let alpha = sin / (2.0 * q);
+        let a = 10f64.powf(f64::from(self.gain_db) / 40.0);
+        let shelf = 2.0 * a.sqrt() * alpha;
+
+        let (b, a) = match self.filter_type {
+            FilterType::Peaking => (
+                [1.0 + alpha * a, -2.0 * cos, 1.0 - alpha * a],
+                [1.0 + alpha / a, -2.0 * cos, 1.0 - alpha / a],
+            ),
+            FilterType::LowShelf => (
+                [
+                    a * ((a + 1.0) - (a - 1.0) * cos + shelf),
+                    2.0 * a * ((a - 1.0) - (a + 1.0) * cos),
+                    a * ((a + 1.0) - (a - 1.0) * cos - shelf),
+                ],
+                [
+                    (a + 1.0) + (a - 1.0) * cos + shelf,
+                    -2.0 * ((a - 1.0) + (a + 1.0) * cos),
+                    (a + 1.0) + (a - 1.0) * cos - shelf,
+                ],
+            ),
+            FilterType::HighShelf => (
+                [
+                    a * ((a + 1.0) + (a - 1.0) * cos + shelf),
+                    -2.0 * a * ((a - 1.0) + (a + 1.0) * cos),
+                    a * ((a + 1.0) + (a - 1.0) * cos - shelf),
+                ],
+                [
+                    (a + 1.0) - (a - 1.0) * cos + shelf,
+                    2.0 * ((a - 1.0) - (a + 1.0) * cos),
+                    (a + 1.0) - (a - 1.0) * cos - shelf,
+                ],
+            ),
+            FilterType::LowPass => (
+                [(1.0 - cos) / 2.0, 1.0 - cos, (1.0 - cos) / 2.0],
+                [1.0 + alpha, -2.0 * cos, 1.0 - alpha],
+            ),
+            FilterType::HighPass => (
+                [(1.0 + cos) / 2.0, -(1.0 + cos), (1.0 + cos) / 2.0],
+                [1.0 + alpha, -2.0 * cos, 1.0 - alpha],
+            ),
+            FilterType::Notch => (
+                [1.0, -2.0 * cos, 1.0],
+                [1.0 + alpha, -2.0 * cos, 1.0 - alpha],
+            ),
+            FilterType::BandPass => ([alpha, 0.0, -alpha], [1.0 + alpha, -2.0 * cos, 1.0 - alpha]),
+        };
+        let a0 = a[0];
+        ([b[0] / a0, b[1] / a0, b[2] / a0], [a[1] / a0, a[2] / a0])
+    }
+}
+
+/// Shelf slope without overshoot.
+const SHELF_Q: f32 = std::f32::consts::FRAC_1_SQRT_2;
+
+/// A band with its smoothing state and one filter per channel.
+#[derive(Debug, Clone)]
+struct BandSlot {
+    /// Channel the band applies to, or `None` for all
+    channel: Option<usize>,
+    target: EqBand,
+    current: EqBand,
+    filters: Vec<Biquad>,
+}
+
+impl BandSlot {
+    fn new(band: EqBand, channel: Option<usize>, channels: usize, sample_rate: u32) -> Self {
+        let (b, a) = band.coefficients(sample_rate);
+        Self {
+            channel,
+            target: band,
+            current: band,
+            filters: vec![Biquad::new(b, a); channels],
+        }
+    }
+
+    fn applies_to(&self, channel: usize) -> bool {
+        self.channel.is_none_or(|c| c == channel)
+    }
+
+    /// Moves the parameters a step towards the target and updates the
+    /// coefficients. Frequency and Q move on a log scale.
+    fn advance(&mut self, weight: f32, sample_rate: u32) {
+        if self.current == self.target {
+            return;
+        }
+        let (current, target) = (&mut self.current, self.target);
+        let log_step = |from: f32, to: f32| (from.ln() + (to.ln() - from.ln()) * weight).exp();
+
+        if current.filter_type != target.filter_type {
+            // A different response cannot be interpolated
+            *current = target;
+        } else {
+            current.frequency_hz = log_step(current.frequency_hz, target.frequency_hz);
+            current.q = log_step(current.q, target.q);
+            current.gain_db += (target.gain_db - current.gain_db) * weight;
+            let close = (current.frequency_hz / target.frequency_hz).ln().abs() < 1e-4
+                && (current.q / target.q).ln().abs() < 1e-4
+                && (current.gain_db - target.gain_db).abs() < 1e-3;
+            if close || !current.frequency_hz.is_finite() || !current.q.is_finite() {
+                *current = target;
+            }
+        }
+
+        let (b, a) = self.current.coefficients(sample_rate);
+        for filter in &mut self.filters {
+            filter.set_coefficients(b, a);
+        }
+    }
+}
+
+/// Parametric equalizer with any number of bands.
+///
+/// Each band applies to every channel or to a single one. Changes made with
+/// [`ParametricEq::set_band`] are smoothed: frequency, Q and gain glide to
+/// the new values, and the coefficients are recomputed every 32 frames, so
+/// sweeping a band does not click. Changing a band's [`FilterType`] takes
+/// effect at once.
+///
+/// Added to a cable's [`super::EffectChain`], the equalizer can be reached
+/// again through [`super::EffectChain::effect_mut`].
+#[derive(Debug, Clone)]
+pub struct ParametricEq {
+    channels: usize,
+    sample_rate: u32,
+    /// Weight of the target in each smoothing step
+    smoothing_weight: f32,
+    bands: Vec<BandSlot>,
+}
+
+impl ParametricEq {
+    /// Creates an equalizer without bands for interleaved audio.
+    pub fn new(channels: usize, sample_rate: u32) -> Self {
+        Self {
+            channels: channels.max(1),
+            sample_rate: sample_rate.max(1),
+            smoothing_weight: 1.0,
+            bands: Vec::new(),
+        }
+        .with_smoothing_ms(DEFAULT_SMOOTHING_MS)
+    }
+
+    /// Sets the time constant of parameter smoothing (0 disables it).
+    pub fn with_smoothing_ms(mut self, smoothing_ms: f32) -> Self {
+        let frames = smoothing_ms.max(0.0) * self.sample_rate as f32 / 1000.0;
+        self.smoothing_weight = if frames > 0.0 {
+            1.0 - (-(SMOOTHING_BLOCK_FRAMES as f32) / frames).exp()
+        } else {
+            1.0
+        };
+        self
+    }
+
+    /// Returns the number of interleaved channels per frame.
+    pub fn channels(&self) -> usize {
+        self.channels
+    }
+
+    /// Returns the number of bands.
+    pub fn len(&self) -> usize {
+        self.bands.len()
+    }
+
+    /// Returns true if the equalizer has no bands.
+    pub fn is_empty(&self) -> bool {
+        self.bands.is_empty()
+    }
+
+    /// Adds a band applied to every channel and returns its index.
+    ///
+    /// Returns `None` if a parameter of the band is not finite.
+    pub fn add_band(&mut self, band: EqBand) -> Option<usize> {
+        if !band.is_finite() {
+            return None;
+        }
+        self.bands
+            .push(BandSlot::new(band, None, self.channels, self.sample_rate));
+        Some(self.bands.len() - 1)
+    }
+
+    /// Adds a band applied to one channel only and returns its index.
+    ///
+    /// Returns `None` if the channel is out of range or a parameter of the
+    /// band is not finite.
+    pub fn add_channel_band(&mut self, channel: usize, band: EqBand) -> Option<usize> {
+        if channel >= self.channels || !band.is_finite() {
+            return None;
+        }
+        self.bands.push(BandSlot::new(
+            band,
+            Some(channel),
+            self.channels,
+            self.sample_rate,
+        ));
+        Some(self.bands.len() - 1)
+    }
+
+    /// Returns the target parameters of a band.
+    pub fn band(&self, index: usize) -> Option<EqBand> {
+        self.bands.get(index).map(|slot| slot.target)
+    }
+
+    /// Changes a band; the running audio glides to the new parameters.
+    ///
+    /// Returns false, leaving the band unchanged, if there is no band at
+    /// `index` or a parameter of `band` is not finite.
+    pub fn set_band(&mut self, index: usize, band: EqBand) -> bool {
+        let Some(slot) = self.bands.get_mut(index).filter(|_| band.is_finite()) else {
+            return false;
+        };
+        slot.target = band;
+        true
+    }
+
+    /// Removes a band, shifting the indices of later bands down by one.
+    pub fn remove_band(&mut self, index: usize) -> Option<EqBand> {
+        (index < self.bands.len()).then(|| self.bands.remove(index).target)
+    }
+
+    /// Returns the gain in dB of a channel at each of `frequencies` (in Hz),
+    /// for plotting. The response is that of the target parameters.
+    pub fn frequency_response(&self, channel: usize, frequencies: &[f32]) -> Vec<f32> {
+        let filters: Vec<Biquad> = self
+            .bands
+            .iter()
+            .filter(|slot| slot.applies_to(channel))
+            .map(|slot| {
+                let (b, a) = slot.target.coefficients(self.sample_rate);
+                Biquad::new(b, a)
+            })
+            .collect();
+
+        frequencies
+            .iter()
+            .map(|&frequency| {
+                let omega = 2.0 * PI * f64::from(frequency) / f64::from(self.sample_rate);
+                filters.iter().map(|f| f.response_db(omega)).sum::<f64>() as f32
+            })
+            .collect()
+    }
+
+    /// Filters a block of interleaved samples in place.
+    pub fn process(&mut self, block: &mut [f32]) {
+        let channels = self.channels;
+        for chunk in block.chunks_mut(SMOOTHING_BLOCK_FRAMES * channels) {
+            for slot in &mut self.bands {
+                slot.advance(self.smoothing_weight, self.sample_rate);
+                for (channel, filter) in slot.filters.iter_mut().enumerate() {
+                    if slot.channel.is_some_and(|c| c != channel) {
+                        continue;
+                    }
+                    for sample in chunk.iter_mut().skip(channel).step_by(channels) {
+                        *sample = filter.process(f64::from(*sample)) as f32;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Clears the filter history and jumps to the target parameters.
+    pub fn reset(&mut self) {
+        for slot in &mut self.bands {
+            slot.current = slot.target;
+            let (b, a) = slot.target.coefficients(self.sample_rate);
+            for filter in &mut slot.filters {
+                *filter = Biquad::new(b, a);
+            }
+        }
+    }
+}
+
+impl AudioEffect for ParametricEq {
+    fn process(&mut self, block: &mut [f32]) {
+        ParametricEq::process(self, block);
+    }
+
+    fn reset(&mut self) {
+        ParametricEq::reset(self);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::sine;
+
+    fn peak(samples: &[f32]) -> f32 {
+        samples.iter().fold(0.0, |max, s| max.max(s.abs()))
+    }
+
+    fn response(band: EqBand, frequency: f32) -> f32 {
+        let mut eq = ParametricEq::new(1, 48_000);
+        eq.add_band(band);
+        eq.frequency_response(0, &[frequency])[0]
+    }
+
+    #[test]
+    fn test_band_responses() {
+        let boost = EqBand::peaking(1000.0, 1.0, 6.0);
+        assert!((response(boost, 1000.0) - 6.0).abs() < 0.01);
+        assert!(response(boost, 20.0).abs() < 0.1);
+
+        let high_pass = EqBand::high_pass(80.0, std::f32::consts::FRAC_1_SQRT_2);
+        assert!((response(high_pass, 80.0) + 3.01).abs() < 0.05);
+        assert!(response(high_pass, 20.0) < -20.0);
+        let low_pass = EqBand::low_pass(5000.0, std::f32::consts::FRAC_1_SQRT_2);
+        assert!((response(low_pass, 5000.0) + 3.01).abs() < 0.05);
+        assert!(response(low_pass, 100.0).abs() < 0.01);
+
+        let low_shelf = EqBand::low_shelf(200.0, -6.0);
+        assert!((response(low_shelf, 20.0) + 6.0).abs() < 0.2);
+        assert!(response(low_shelf, 10_000.0).abs() < 0.1);
+        let high_shelf = EqBand::high_shelf(4000.0, 3.0);
+        assert!((response(high_shelf, 20_000.0) - 3.0).abs() < 0.2);
+        assert!(response(high_shelf, 100.0).abs() < 0.1);
+
+        assert!(response(EqBand::notch(50.0, 10.0), 50.0) < -60.0);
+        assert!(response(EqBand::notch(50.0, 10.0), 1000.0).abs() < 0.01);
+        assert!(response(EqBand::band_pass(1000.0, 2.0), 1000.0).abs() < 0.01);
+        assert!(response(EqBand::band_pass(1000.0, 2.0), 100.0) < -10.0);
+    }
+
+    #[test]
+    fn test_notch_removes_hum() {
+        let mut eq = ParametricEq::new(1, 48_000);
+        eq.add_band(EqBand::notch(50.0, 5.0));
+
+        let mut hum = sine(50.0, 0.5, 0.0, 48_000, 1);
+        eq.process(&mut hum);
+        assert!(peak(&hum[43_200..]) < 0.005, "{}", peak(&hum[43_200..]));
+
+        let mut voice = sine(1000.0, 0.5, 0.0, 48_000, 1);
+        eq.process(&mut voice);
+        assert!((peak(&voice[43_200..]) - 0.5).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_parameter_changes_are_smoothed() {
+        let mut eq = ParametricEq::new(1, 48_000);
+        let band = eq.add_band(EqBand::peaking(1000.0, 1.0, 0.0)).unwrap();
+        let mut signal = sine(1000.0, 0.1, 0.0, 48_000, 1);
+        eq.process(&mut signal[..4800]);
+
+        assert!(eq.set_band(band, EqBand::peaking(1000.0, 1.0, 12.0)));
+        eq.process(&mut signal[4800..]);
+
+        // The level rises over several 1 ms periods instead of jumping
+        let periods: Vec<f32> = signal[4800..].chunks(48).map(peak).collect();
+        assert!(periods[0] < 0.15, "{:?}", &periods[..4]);
+        assert!(periods.windows(2).all(|w| w[1] >= w[0] - 0.005));
+        assert!((periods.last().unwrap() - 0.398).abs() < 0.005);
+    }
+
+    #[test]
+    fn test_channel_bands() {
+        let mut eq = ParametricEq::new(2, 48_000);
+        assert_eq!(eq.add_channel_band(2, EqBand::notch(60.0, 10.0)), None);
+        let band = eq.add_channel_band(1, EqBand::peaking(500.0, 1.0, -6.0));
+        assert_eq!(band, Some(0));
+
+        assert_eq!(eq.frequency_response(0, &[500.0]), [0.0]);
+        assert!((eq.frequency_response(1, &[500.0])[0] + 6.0).abs() < 0.01);
+
+        let mut block = sine(500.0, 0.5, 0.0, 9600, 2);
+        eq.process(&mut block);
+        let tail = &block[9600..];
+        let left: Vec<f32> = tail.iter().step_by(2).copied().collect();
+        let right: Vec<f32> = tail.iter().skip(1).step_by(2).copied().collect();
+        assert!((peak(&left) - 0.5).abs() < 1e-3);
+        assert!((peak(&right) - 0.25).abs() < 0.01);
+
+        assert_eq!(eq.remove_band(0).map(|b| b.gain_db), Some(-6.0));
+        assert!(eq.is_empty());
+    }
+
+    #[test]
+    fn test_rejects_non_finite_parameters() {
+        let mut eq = ParametricEq::new(2, 48_000);
+        assert_eq!(eq.add_band(EqBand::peaking(f32::NAN, 1.0, 3.0)), None);
+        assert_eq!(
+            eq.add_channel_band(0, EqBand::high_pass(80.0, f32::INFINITY)),
+            None
+        );
+        let band = eq.add_band(EqBand::peaking(1000.0, 1.0, 3.0)).unwrap();
+
+        assert!(!eq.set_band(band, EqBand::peaking(1000.0, 1.0, f32::NAN)));
+        assert_eq!(eq.band(band), Some(EqBand::peaking(1000.0, 1.0, 3.0)));
+
+        let mut block = sine(1000.0, 0.5, 0.0, 4800, 2);
+        eq.process(&mut block);
+        assert!(block.iter().all(|s| s.is_finite()));
+    }
+}
@@ -17,6 +17,10 @@
 //!
 //! - `buffer`: Gestión de buffers circulares para transferencia de datos.
 //! - `audio`: Procesamiento de audio, remuestreo y conversión de formato.
+//! - `effect`: Efectos en línea y cadenas de efectos reconfigurables en tiempo real.
+//! - `meter`: Medición de nivel de pico, RMS y pico real por canal.
+//! - `sample`: Vistas tipadas de muestras codificadas, independientes de la alineación.
+//! - `simd`: Núcleos vectorizados de conversión y mezcla con detección de CPU en tiempo de ejecución.
 //! - `platform`: Implementaciones específicas para cada sistema operativo.
 //!
 //! ## Soporte de Plataformas
@@ -60,14 +64,35 @@
 // Re-export public modules
 pub mod audio;
 pub mod buffer;
+pub mod effect;
+pub mod meter;
+pub mod sample;
+pub mod simd;
+
+#[cfg(test)]
+mod test_util;
 
 // Platform-specific module
 mod platform;
 pub use platform::{AudioApplication, AudioOutput, VirtualCable, VirtualCableTrait};
 
 // Common error types
-pub use crate::audio::AudioProcessor;
-pub use crate::buffer::{RingBuffer, TripleRingBuffer};
+pub use crate::audio::{
+    AudioProcessor, ChannelMixer, DitherMode, GainControl, GainStage, LoudnessMeter,
+    LoudnessReading, Quantizer, ResampleQuality, StreamingResampler, VariableResampler,
+};
+pub use crate::buffer::{
+    BlockTimestamp, BroadcastReader, BroadcastRingBuffer, DriftEstimator, FillHistory, FillStats,
+    FrameRingBuffer, HeaplessRingBuffer, JitterBuffer, OverrunPolicy, RingBuffer, SampleBuffer,
+    TimestampedRingBuffer, TripleRingBuffer, UnderrunPolicy,
+};
+
+pub use crate::effect::{AudioEffect, EffectChain, EffectId, EqBand, FilterType, ParametricEq};
+pub use crate::meter::{ChannelLevels, LevelMeter, MeterBallistics};
+pub use crate::sample::{Sample, SampleSlice, SampleSliceMut};
+
+#[cfg(target_os = "linux")]
+pub use crate::buffer::SharedRingBuffer;
 
 use thiserror::Error;
 
@@ -110,6 +135,26 @@ pub struct CableConfig {
 
     /// Device name for the virtual cable
     pub device_name: String,
+
+    /// What happens when captured audio does not fit in the input buffer
+    pub overrun_policy: OverrunPolicy,
+
+    /// How playback is filled when the output buffer runs dry
+    pub underrun_policy: UnderrunPolicy,
+
+    /// Target latency of the drift-compensating jitter buffer, in
+    /// milliseconds (`None` disables jitter-buffer mode)
+    pub jitter_target_ms: Option<f64>,
+
+    /// Resampling quality preset (presets other than `Linear` need the
+    /// `rubato` feature); only applies when the cable resamples
+    pub resample_quality: ResampleQuality,
+
+    /// Initial gain applied to all channels, in dB
+    pub gain_db: f32,
+
+    /// Time behaviour of the level meters reported in `CableStats::levels`
+    pub meter_ballistics: MeterBallistics,
 }
 
 impl Default for CableConfig {
@@ -120,6 +165,12 @@ impl Default for CableConfig {
             buffer_size: 1024,
             format: AudioFormat::F32LE,
             device_name: "Virtual Audio Cable".to_string(),
+            overrun_policy: OverrunPolicy::DropNewest,
+            underrun_policy: UnderrunPolicy::ZeroFill,
+            jitter_target_ms: None,
+            resample_quality: ResampleQuality::Linear,
+            gain_db: 0.0,
+            meter_ballistics: MeterBallistics::default(),
         }
     }
 }
@@ -138,16 +189,74 @@ pub enum AudioFormat {
 
     /// 32-bit signed integer, little-endian
     S32LE,
+
+    /// 8-bit unsigned integer, centred on 128
+    U8,
+
+    /// 16-bit signed integer, big-endian
+    S16BE,
+
+    /// 24-bit signed integer, big-endian
+    S24BE,
+
+    /// 24-bit signed integer in the low bits of a 32-bit little-endian word
+    S24_32LE,
+
+    /// 24-bit signed integer in the low bits of a 32-bit big-endian word
+    S24_32BE,
+
+    /// 32-bit signed integer, big-endian
+    S32BE,
+
+    /// 32-bit floating point, big-endian
+    F32BE,
+
+    /// 64-bit floating point, little-endian
+    F64LE,
+
+    /// 64-bit floating point, big-endian
+    F64BE,
+
+    /// 8-bit G.711 A-law
+    ALaw,
+
+    /// 8-bit G.711 μ-law
+    MuLaw,
 }
 
 impl AudioFormat {
+    /// Every supported format. New formats are only ever appended.
+    pub const ALL: [AudioFormat; 15] = [
+        AudioFormat::F32LE,
+        AudioFormat::S16LE,
+        AudioFormat::S24LE,
+        AudioFormat::S32LE,
+        AudioFormat::U8,
+        AudioFormat::S16BE,
+        AudioFormat::S24BE,
+        AudioFormat::S24_32LE,
+        AudioFormat::S24_32BE,
+        AudioFormat::S32BE,
+        AudioFormat::F32BE,
+        AudioFormat::F64LE,
+        AudioFormat::F64BE,
+        AudioFormat::ALaw,
+        AudioFormat::MuLaw,
+    ];
+
     /// Returns the number of bytes per sample for this format
     pub fn bytes_per_sample(&self) -> usize {
         match self {
-            AudioFormat::F32LE => 4,
-            AudioFormat::S16LE => 2,
-            AudioFormat::S24LE => 3,
-            AudioFormat::S32LE => 4,
+            AudioFormat::U8 | AudioFormat::ALaw | AudioFormat::MuLaw => 1,
+            AudioFormat::S16LE | AudioFormat::S16BE => 2,
+            AudioFormat::S24LE | AudioFormat::S24BE => 3,
+            AudioFormat::S24_32LE
+            | AudioFormat::S24_32BE
+            | AudioFormat::S32LE
+            | AudioFormat::S32BE
+            | AudioFormat::F32LE
+            | AudioFormat::F32BE => 4,
+            AudioFormat::F64LE | AudioFormat::F64BE => 8,
         }
     }
 
@@ -158,6 +267,39 @@ impl AudioFormat {
             AudioFormat::S16LE => "S16LE",
             AudioFormat::S24LE => "S24LE",
             AudioFormat::S32LE => "S32LE",
+            AudioFormat::U8 => "U8",
+            AudioFormat::S16BE => "S16BE",
+            AudioFormat::S24BE => "S24BE",
+            AudioFormat::S24_32LE => "S24_32LE",
+            AudioFormat::S24_32BE => "S24_32BE",
+            AudioFormat::S32BE => "S32BE",
+            AudioFormat::F32BE => "F32BE",
+            AudioFormat::F64LE => "F64LE",
+            AudioFormat::F64BE => "F64BE",
+            AudioFormat::ALaw => "ALAW",
+            AudioFormat::MuLaw => "MULAW",
         }
     }
 }
+
+impl std::str::FromStr for AudioFormat {
+    type Err = Error;
+
+    /// Parses a format name, ignoring case.
+    ///
+    /// Accepts the names returned by [`AudioFormat::name`], plus short forms
+    /// without an endianness suffix for little-endian (`s16`, `f32`, ...),
+    /// `ulaw` for μ-law and `s24_32` for 24 bits in a 32-bit container.
+    fn from_str(s: &str) -> Result<Self> {
+        let name = s.to_ascii_uppercase();
+        let name = match name.as_str() {
+            "S16" | "S24" | "S32" | "F32" | "F64" | "S24_32" => name + "LE",
+            "ULAW" => "MULAW".to_string(),
+            _ => name,
+        };
+        AudioFormat::ALL
+            .into_iter()
+            .find(|format| format.name() == name)
+            .ok_or_else(|| Error::AudioError(format!("Unknown audio format: {}", s)))
+    }
+}
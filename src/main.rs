@@ -21,6 +21,14 @@ async fn main() -> Result<()> {
     // Parse command line arguments
     let args = parse_args();
 
+    // One-shot loudness measurement of a file
+    if let Some(path) = &args.measure {
+        let reading = virtual_audio_cable::audio::measure_file(path)?;
+        println!("Integrated: {:.1} LUFS", reading.integrated_lufs);
+        println!("Loudness range: {:.1} LU", reading.loudness_range_lu);
+        return Ok(());
+    }
+
     // Create configuration
     let config = CableConfig {
         sample_rate: args.sample_rate,
@@ -28,6 +36,9 @@ async fn main() -> Result<()> {
         buffer_size: args.buffer_size,
         format: args.format,
         device_name: args.device_name.clone(),
+        jitter_target_ms: args.jitter_target_ms,
+        gain_db: args.gain_db,
+        ..Default::default()
     };
 
     info!("Configuration:");
@@ -36,6 +47,12 @@ async fn main() -> Result<()> {
     info!("  Buffer Size: {} samples", config.buffer_size);
     info!("  Format: {}", config.format.name());
     info!("  Device Name: {}", config.device_name);
+    if let Some(target_ms) = config.jitter_target_ms {
+        info!("  Jitter Buffer: {:.1} ms", target_ms);
+    }
+    if config.gain_db != 0.0 {
+        info!("  Gain: {:+.1} dB", config.gain_db);
+    }
 
     // Create virtual cable
     let cable = Arc::new(std::sync::Mutex::new(VirtualCable::new(config.clone())?));
@@ -53,13 +70,31 @@ async fn main() -> Result<()> {
                 interval.tick().await;
                 let stats = cable_clone.lock().unwrap().get_stats();
                 info!(
-                    "Stats: running={}, samples={}, underruns={}, overruns={}, latency={:.2}ms, cpu={:.1}%",
+                    "Stats: running={}, samples={}, underruns={}, overruns={}, latency={:.2}ms, cpu={:.1}%, drift={:+.1}ppm, in_fill={}..{}, resample_fill={}..{}, out_fill={}..{}, clipped={}",
                     stats.is_running,
                     stats.samples_processed,
                     stats.underruns,
                     stats.overruns,
                     stats.latency_ms,
-                    stats.cpu_usage
+                    stats.cpu_usage,
+                    stats.drift_ppm,
+                    stats.input_fill.min_frames,
+                    stats.input_fill.max_frames,
+                    stats.resample_fill.min_frames,
+                    stats.resample_fill.max_frames,
+                    stats.output_fill.min_frames,
+                    stats.output_fill.max_frames,
+                    stats.clipped_samples
+                );
+                if !stats.levels.is_empty() {
+                    info!("Levels (dBFS): {}", format_levels(&stats.levels));
+                }
+                info!(
+                    "Loudness: M={:.1} S={:.1} I={:.1} LUFS, LRA={:.1} LU",
+                    stats.loudness.momentary_lufs,
+                    stats.loudness.short_term_lufs,
+                    stats.loudness.integrated_lufs,
+                    stats.loudness.loudness_range_lu
                 );
             }
         });
@@ -93,6 +128,21 @@ async fn main() -> Result<()> {
     Ok(())
 }
 
+/// Formats per-channel meter readings as `ch0 peak=.. rms=.. tp=..`.
+fn format_levels(levels: &[virtual_audio_cable::ChannelLevels]) -> String {
+    levels
+        .iter()
+        .enumerate()
+        .map(|(channel, level)| {
+            format!(
+                "ch{} peak={:.1} rms={:.1} tp={:.1}",
+                channel, level.peak_dbfs, level.rms_dbfs, level.true_peak_dbfs
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(" | ")
+}
+
 /// Command line arguments
 struct Args {
     sample_rate: u32,
@@ -100,7 +150,10 @@ struct Args {
     buffer_size: usize,
     format: virtual_audio_cable::AudioFormat,
     device_name: String,
+    jitter_target_ms: Option<f64>,
+    gain_db: f32,
     monitor: bool,
+    measure: Option<String>,
 }
 
 /// Parse command line arguments
@@ -112,7 +165,10 @@ fn parse_args() -> Args {
     let mut buffer_size = 1024;
     let mut format = virtual_audio_cable::AudioFormat::F32LE;
     let mut device_name = "Virtual Audio Cable".to_string();
+    let mut jitter_target_ms = None;
+    let mut gain_db = 0.0;
     let mut monitor = false;
+    let mut measure = None;
 
     let mut i = 1;
     while i < args.len() {
@@ -150,13 +206,10 @@ fn parse_args() -> Args {
             }
             "-f" | "--format" => {
                 if i + 1 < args.len() {
-                    format = match args[i + 1].as_str() {
-                        "f32" | "F32" => virtual_audio_cable::AudioFormat::F32LE,
-                        "s16" | "S16" => virtual_audio_cable::AudioFormat::S16LE,
-                        "s24" | "S24" => virtual_audio_cable::AudioFormat::S24LE,
-                        "s32" | "S32" => virtual_audio_cable::AudioFormat::S32LE,
-                        _ => {
-                            error!("Invalid format: {}", args[i + 1]);
+                    format = match args[i + 1].parse() {
+                        Ok(format) => format,
+                        Err(e) => {
+                            error!("Invalid format: {}", e);
                             std::process::exit(1);
                         }
                     };
@@ -175,6 +228,42 @@ fn parse_args() -> Args {
                     std::process::exit(1);
                 }
             }
+            "-j" | "--jitter" => {
+                if i + 1 < args.len() {
+                    let target_ms: f64 = args[i + 1].parse().expect("Invalid jitter target");
+                    if !target_ms.is_finite() || target_ms <= 0.0 {
+                        error!("Invalid jitter target: {}", args[i + 1]);
+                        std::process::exit(1);
+                    }
+                    jitter_target_ms = Some(target_ms);
+                    i += 2;
+                } else {
+                    error!("Missing value for {}", args[i]);
+                    std::process::exit(1);
+                }
+            }
+            "-g" | "--gain" => {
+                if i + 1 < args.len() {
+                    gain_db = args[i + 1].parse().expect("Invalid gain");
+                    if !f32::is_finite(gain_db) {
+                        error!("Invalid gain: {}", args[i + 1]);
+                        std::process::exit(1);
+                    }
+                    i += 2;
+                } else {
+                    error!("Missing value for {}", args[i]);
+                    std::process::exit(1);
+                }
+            }
+            "--measure" => {
+                if i + 1 < args.len() {
+                    measure = Some(args[i + 1].clone());
+                    i += 2;
+                } else {
+                    error!("Missing value for {}", args[i]);
+                    std::process::exit(1);
+                }
+            }
             "-m" | "--monitor" => {
                 monitor = true;
                 i += 1;
@@ -192,7 +281,10 @@ fn parse_args() -> Args {
         buffer_size,
         format,
         device_name,
+        jitter_target_ms,
+        gain_db,
         monitor,
+        measure,
     }
 }
 
@@ -208,15 +300,20 @@ fn print_help() {
     println!("  -r, --sample-rate RATE    Sample rate in Hz (default: 48000)");
     println!("  -c, --channels N          Number of channels (default: 2)");
     println!("  -b, --buffer SIZE         Buffer size in samples (default: 1024)");
-    println!("  -f, --format FORMAT       Audio format: f32, s16, s24, s32 (default: f32)");
+    println!("  -f, --format FORMAT       Audio format (default: f32): u8, s16, s24, s24_32,");
+    println!("                            s32, f32, f64 (append le/be), alaw, ulaw");
     println!("  -n, --name NAME          Device name (default: 'Virtual Audio Cable')");
+    println!("  -j, --jitter MS          Enable drift-compensating jitter buffer (target latency)");
+    println!("  -g, --gain DB            Gain applied to all channels (default: 0)");
     println!("  -m, --monitor            Monitor and print statistics");
+    println!("      --measure FILE       Print the loudness of a WAV file and exit");
     println!("  -h, --help               Show this help message");
     println!();
     println!("EXAMPLES:");
     println!("  virtual_audio");
     println!("  virtual_audio --sample-rate 44100 --monitor");
     println!("  virtual_audio -c 1 -b 2048 -f s16");
+    println!("  virtual_audio --measure episode.wav");
     println!();
     println!("PLATFORMS:");
     println!("  Linux: Uses PipeWire (user-space)");
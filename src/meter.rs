@@ -0,0 +1,300 @@
+//! Signal level metering.
+//!
+//! [`LevelMeter`] tracks the peak, RMS and true-peak level of every channel
+//! of an interleaved stream. [`crate::TripleRingBuffer`] runs one on the
+//! audio it delivers, and the readings are reported in
+//! `CableStats::levels`.
+
+/// Taps per phase of the true-peak interpolation filter.
+const TRUE_PEAK_TAPS: usize = 12;
+
+/// First two phases of the 4x oversampling filter from ITU-R BS.1770-4
+/// Annex 2. The other two phases are these in reverse order.
+const TRUE_PEAK_PHASES: [[f32; TRUE_PEAK_TAPS]; 2] = [
+    [
+        0.001_708_984_4,
+        0.010_986_328,
+        -0.019_653_32,
+        0.033_203_125,
+        -0.059_448_242,
+        0.137_329_1,
+        0.972_167_97,
+        -0.102_294_92,
+        0.047_607_42,
+        -0.026_611_328,
+        0.014_892_578,
+        -0.008_300_781,
+    ],
+    [
+        -0.029_174_805,
+        0.029_296_875,
+        -0.051_757_813,
+        0.089_111_33,
+        -0.166_503_9,
+        0.465_087_9,
+        0.779_785_16,
+        -0.200_317_38,
+        0.101_562_5,
+        -0.058_227_54,
+        0.033_081_055,
+        -0.018_920_898,
+    ],
+];
+
+/// Converts a linear amplitude to dBFS, giving negative infinity for
+/// silence.
+pub(crate) fn amplitude_to_dbfs(amplitude: f32) -> f32 {
+    20.0 * amplitude.log10()
+}
+
+/// Time behaviour of a [`LevelMeter`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MeterBallistics {
+    /// Integration time of the RMS reading, in milliseconds
+    pub rms_time_ms: f32,
+
+    /// How long a peak is held before it starts to fall, in milliseconds
+    pub peak_hold_ms: f32,
+
+    /// Fall rate of the peak and true-peak readings after the hold, in dB
+    /// per second
+    pub peak_decay_db_per_s: f32,
+}
+
+impl Default for MeterBallistics {
+    fn default() -> Self {
+        Self {
+            rms_time_ms: 300.0,
+            peak_hold_ms: 500.0,
+            peak_decay_db_per_s: 20.0,
+        }
+    }
+}
+
+/// Levels of one channel, in dBFS.
+///
+/// Silence reads as negative infinity. Readings above 0 dBFS mean the
+/// signal is clipping, or for the true peak that it will clip once
+/// converted to analogue or resampled.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ChannelLevels {
+    /// Held sample peak
+    pub peak_dbfs: f32,
+
+    /// Exponentially averaged RMS level
+    pub rms_dbfs: f32,
+
+    /// Held peak of the 4x oversampled signal (ITU-R BS.1770)
+    pub true_peak_dbfs: f32,
+}
+
+/// A peak detector with hold and exponential fall-back.
+#[derive(Debug, Clone, Copy, Default)]
+struct HeldPeak {
+    value: f32,
+    hold_remaining: usize,
+}
+
+impl HeldPeak {
+    fn update(&mut self, amplitude: f32, hold_samples: usize, decay: f32) {
+        if amplitude >= self.value {
+            self.value = amplitude;
+            self.hold_remaining = hold_samples;
+        } else if self.hold_remaining > 0 {
+            self.hold_remaining -= 1;
+        } else {
+            self.value = (self.value * decay).max(amplitude);
+        }
+    }
+}
+
+/// Interpolates a channel at four times its sample rate and reports the
+/// largest absolute value of each group of four interpolated samples.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct TruePeakDetector {
+    /// Most recent input samples, newest first
+    history: [f32; TRUE_PEAK_TAPS],
+}
+
+impl TruePeakDetector {
+    /// Feeds one sample and returns the peak of the interpolated samples
+    /// around it.
+    pub(crate) fn next(&mut self, sample: f32) -> f32 {
+        self.history.copy_within(..TRUE_PEAK_TAPS - 1, 1);
+        self.history[0] = sample;
+
+        let mut peak = 0.0f32;
+        for taps in &TRUE_PEAK_PHASES {
+            let forward: f32 = taps.iter().zip(&self.history).map(|(t, s)| t * s).sum();
+            let reverse: f32 = taps
+                .iter()
+                .rev()
+                .zip(&self.history)
+                .map(|(t, s)| t * s)
+                .sum();
+            peak = peak.max(forward.abs()).max(reverse.abs());
+        }
+        peak
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct ChannelMeter {
+    peak: HeldPeak,
+    true_peak: HeldPeak,
+    detector: TruePeakDetector,
+    mean_square: f32,
+}
+
+/// Per-channel peak, RMS and true-peak meter for interleaved audio.
+#[derive(Debug, Clone)]
+pub struct LevelMeter {
+    sample_rate: u32,
+    ballistics: MeterBallistics,
+    hold_samples: usize,
+    /// Peak fall-back factor per sample
+    decay: f32,
+    /// Weight of each new squared sample in the RMS average
+    rms_coefficient: f32,
+    channels: Vec<ChannelMeter>,
+}
+
+impl LevelMeter {
+    /// Creates a meter with the default ballistics.
+    pub fn new(channels: usize, sample_rate: u32) -> Self {
+        let mut meter = Self {
+            sample_rate: sample_rate.max(1),
+            ballistics: MeterBallistics::default(),
+            hold_samples: 0,
+            decay: 1.0,
+            rms_coefficient: 1.0,
+            channels: vec![ChannelMeter::default(); channels.max(1)],
+        };
+        meter.set_ballistics(MeterBallistics::default());
+        meter
+    }
+
+    /// Sets the ballistics.
+    pub fn with_ballistics(mut self, ballistics: MeterBallistics) -> Self {
+        self.set_ballistics(ballistics);
+        self
+    }
+
+    /// Changes the ballistics, keeping the current readings.
+    pub fn set_ballistics(&mut self, ballistics: MeterBallistics) {
+        let rate = self.sample_rate as f32;
+        self.ballistics = ballistics;
+        self.hold_samples = (ballistics.peak_hold_ms.max(0.0) * rate / 1000.0) as usize;
+        self.decay = 10f32.powf(-ballistics.peak_decay_db_per_s.max(0.0) / 20.0 / rate);
+        let rms_samples = ballistics.rms_time_ms.max(0.0) * rate / 1000.0;
+        self.rms_coefficient = 1.0 - (-1.0 / rms_samples.max(1.0)).exp();
+    }
+
+    /// Returns the ballistics.
+    pub fn ballistics(&self) -> MeterBallistics {
+        self.ballistics
+    }
+
+    /// Returns the number of interleaved channels per frame.
+    pub fn channels(&self) -> usize {
+        self.channels.len()
+    }
+
+    /// Measures a block of interleaved samples.
+    pub fn process(&mut self, block: &[f32]) {
+        let channels = self.channels.len();
+        for frame in block.chunks_exact(channels) {
+            for (meter, &sample) in self.channels.iter_mut().zip(frame) {
+                meter
+                    .peak
+                    .update(sample.abs(), self.hold_samples, self.decay);
+                let true_peak = meter.detector.next(sample);
+                meter
+                    .true_peak
+                    .update(true_peak, self.hold_samples, self.decay);
+                meter.mean_square += self.rms_coefficient * (sample * sample - meter.mean_square);
+            }
+        }
+    }
+
+    /// Returns the current levels of one channel.
+    pub fn channel_levels(&self, channel: usize) -> Option<ChannelLevels> {
+        let meter = self.channels.get(channel)?;
+        Some(ChannelLevels {
+            peak_dbfs: amplitude_to_dbfs(meter.peak.value),
+            rms_dbfs: amplitude_to_dbfs(meter.mean_square.sqrt()),
+            true_peak_dbfs: amplitude_to_dbfs(meter.true_peak.value),
+        })
+    }
+
+    /// Returns the current levels of every channel.
+    pub fn levels(&self) -> Vec<ChannelLevels> {
+        (0..self.channels.len())
+            .filter_map(|channel| self.channel_levels(channel))
+            .collect()
+    }
+
+    /// Drops all readings back to silence.
+    pub fn reset(&mut self) {
+        for meter in &mut self.channels {
+            *meter = ChannelMeter::default();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::sine;
+
+    #[test]
+    fn test_sine_levels() {
+        let mut meter = LevelMeter::new(1, 48_000);
+        meter.process(&sine(1000.0, 0.5, 0.0, 96_000, 1));
+        let levels = meter.channel_levels(0).unwrap();
+
+        let peak = amplitude_to_dbfs(0.5);
+        assert!((levels.peak_dbfs - peak).abs() < 0.01, "{:?}", levels);
+        assert!(
+            (levels.rms_dbfs - (peak - 3.01)).abs() < 0.05,
+            "{:?}",
+            levels
+        );
+        assert!((levels.true_peak_dbfs - peak).abs() < 0.05, "{:?}", levels);
+    }
+
+    #[test]
+    fn test_true_peak_between_samples() {
+        // At a quarter of the sample rate and 45 degrees the samples sit at
+        // 0.707 of the real peak
+        let mut meter = LevelMeter::new(1, 48_000);
+        meter.process(&sine(12_000.0, 1.0, std::f64::consts::FRAC_PI_4, 4800, 1));
+        let levels = meter.channel_levels(0).unwrap();
+
+        assert!((levels.peak_dbfs + 3.01).abs() < 0.01, "{:?}", levels);
+        assert!(levels.true_peak_dbfs.abs() < 0.1, "{:?}", levels);
+    }
+
+    #[test]
+    fn test_peak_hold_and_decay() {
+        let ballistics = MeterBallistics {
+            peak_hold_ms: 100.0,
+            peak_decay_db_per_s: 20.0,
+            ..Default::default()
+        };
+        let mut meter = LevelMeter::new(2, 48_000).with_ballistics(ballistics);
+        meter.process(&[1.0, 0.0]);
+        meter.process(&vec![0.0; 2 * 4800]);
+        let levels = meter.levels();
+        assert_eq!(levels[0].peak_dbfs, 0.0);
+        assert_eq!(levels[1].peak_dbfs, f32::NEG_INFINITY);
+
+        // One second after the hold the peak has fallen by 20 dB
+        meter.process(&vec![0.0; 2 * 48_000]);
+        let peak = meter.channel_levels(0).unwrap().peak_dbfs;
+        assert!((peak + 20.0).abs() < 0.1, "{}", peak);
+
+        meter.reset();
+        assert_eq!(meter.levels()[0].rms_dbfs, f32::NEG_INFINITY);
+    }
+}
@@ -4,8 +4,9 @@
 //! for Linux systems. It supports both PulseAudio (via pactl) and
 //! PipeWire for audio routing.
 
-use crate::audio::AudioProcessor;
+use crate::audio::GainControl;
 use crate::buffer::TripleRingBuffer;
+use crate::effect::EffectChain;
 use crate::platform::{CableStats, VirtualCableTrait};
 use crate::{CableConfig, Error};
 
@@ -21,13 +22,10 @@ pub struct LinuxVirtualCable {
     config: CableConfig,
     is_running: AtomicBool,
     triple_buffer: Arc<Mutex<TripleRingBuffer>>,
-    #[allow(dead_code)]
-    audio_processor: AudioProcessor,
+    gain: Arc<GainControl>,
 
     // Statistics
     samples_processed: AtomicU64,
-    underruns: AtomicU64,
-    overruns: AtomicU64,
 
     // PulseAudio state
     null_sink_id: Arc<Mutex<Option<String>>>,
@@ -36,23 +34,16 @@ pub struct LinuxVirtualCable {
 
 impl VirtualCableTrait for LinuxVirtualCable {
     fn new(config: CableConfig) -> Result<Self, Error> {
-        let triple_buffer = Arc::new(Mutex::new(TripleRingBuffer::new(config.buffer_size)));
-
-        let audio_processor = AudioProcessor::new(
-            config.sample_rate,
-            config.sample_rate,
-            config.channels,
-            config.format,
-        );
+        let mut triple = TripleRingBuffer::from_config(&config);
+        let gain = Arc::clone(triple.enable_gain(config.sample_rate, config.gain_db));
+        let triple_buffer = Arc::new(Mutex::new(triple));
 
         Ok(Self {
             config,
             is_running: AtomicBool::new(false),
             triple_buffer,
-            audio_processor,
+            gain,
             samples_processed: AtomicU64::new(0),
-            underruns: AtomicU64::new(0),
-            overruns: AtomicU64::new(0),
             null_sink_id: Arc::new(Mutex::new(None)),
             active_loopbacks: Arc::new(Mutex::new(Vec::new())),
         })
@@ -176,13 +167,21 @@ impl VirtualCableTrait for LinuxVirtualCable {
 
     /// Obtiene las estadísticas actuales de rendimiento y buffers.
     fn get_stats(&self) -> CableStats {
+        let buffer_stats = self.triple_buffer.lock().unwrap().stats();
         CableStats {
             is_running: self.is_running(),
             samples_processed: self.samples_processed.load(Ordering::Relaxed),
-            underruns: self.underruns.load(Ordering::Relaxed),
-            overruns: self.overruns.load(Ordering::Relaxed),
+            underruns: buffer_stats.underruns,
+            overruns: buffer_stats.overruns,
             latency_ms: self.calculate_latency(),
             cpu_usage: self.estimate_cpu_usage(),
+            drift_ppm: buffer_stats.drift_ppm,
+            input_fill: buffer_stats.input_fill,
+            resample_fill: buffer_stats.resample_fill,
+            output_fill: buffer_stats.output_fill,
+            levels: buffer_stats.levels,
+            loudness: buffer_stats.loudness,
+            clipped_samples: buffer_stats.clipped_samples,
         }
     }
 
@@ -433,19 +432,41 @@ impl VirtualCableTrait for LinuxVirtualCable {
 
 impl LinuxVirtualCable {
     /// Processes audio (wrapper for triple buffer).
+    ///
+    /// Returns the number of interleaved samples written to `output`.
     pub fn process_audio(&self, input: &[f32], output: &mut [f32]) -> Result<usize, Error> {
         if !self.is_running() {
             return Err(Error::PlatformError("Cable not running".into()));
         }
-        let processed = self.triple_buffer.lock().unwrap().process(input, output)?;
+        let frames = self.triple_buffer.lock().unwrap().process(input, output)?;
+        let processed = frames * self.config.channels as usize;
         self.samples_processed
             .fetch_add(processed as u64, Ordering::Relaxed);
         Ok(processed)
     }
 
+    /// Returns the gain, mute and polarity controls of the cable, which can
+    /// be changed from any thread while audio is flowing.
+    pub fn gain_control(&self) -> Arc<GainControl> {
+        Arc::clone(&self.gain)
+    }
+
+    /// Restarts the integrated loudness and loudness range measurement
+    /// reported in `CableStats::loudness`.
+    pub fn reset_loudness(&self) {
+        self.triple_buffer.lock().unwrap().reset_loudness();
+    }
+
+    /// Gives access to the cable's effect chain, e.g. to add, remove or
+    /// bypass effects while audio is flowing.
+    pub fn with_effects<R>(&self, f: impl FnOnce(&mut EffectChain) -> R) -> R {
+        f(self.triple_buffer.lock().unwrap().effects_mut())
+    }
+
     fn calculate_latency(&self) -> f64 {
-        let stats = self.triple_buffer.lock().unwrap().stats();
-        (stats.resample_available as f64 * 1000.0) / self.config.sample_rate as f64
+        let triple = self.triple_buffer.lock().unwrap();
+        let frames = triple.stats().resample_available + triple.effects().latency_frames();
+        (frames as f64 * 1000.0) / self.config.sample_rate as f64
     }
 
     fn estimate_cpu_usage(&self) -> f64 {
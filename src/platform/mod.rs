@@ -16,6 +16,9 @@ cfg_if::cfg_if! {
     }
 }
 
+use crate::audio::LoudnessReading;
+use crate::buffer::FillStats;
+use crate::meter::ChannelLevels;
 use crate::{CableConfig, Error};
 
 /// Información detallada sobre una aplicación que está emitiendo audio en el sistema.
@@ -107,6 +110,27 @@ pub struct CableStats {
 
     /// CPU usage percentage (0.0-100.0)
     pub cpu_usage: f64,
+
+    /// Measured clock drift between writer and reader in ppm
+    pub drift_ppm: f64,
+
+    /// Fill history of the capture-side buffer
+    pub input_fill: FillStats,
+
+    /// Fill history of the buffer between the effects and the resampler
+    pub resample_fill: FillStats,
+
+    /// Fill history of the playback-side buffer
+    pub output_fill: FillStats,
+
+    /// Levels of the delivered audio, one entry per channel
+    pub levels: Vec<ChannelLevels>,
+
+    /// Loudness of the delivered audio since the last reset
+    pub loudness: LoudnessReading,
+
+    /// Delivered samples that clip when converted to an integer format
+    pub clipped_samples: u64,
 }
 
 impl Default for CableStats {
@@ -118,6 +142,13 @@ impl Default for CableStats {
             overruns: 0,
             latency_ms: 0.0,
             cpu_usage: 0.0,
+            drift_ppm: 0.0,
+            input_fill: FillStats::default(),
+            resample_fill: FillStats::default(),
+            output_fill: FillStats::default(),
+            levels: Vec::new(),
+            loudness: LoudnessReading::default(),
+            clipped_samples: 0,
         }
     }
 }
@@ -101,6 +101,7 @@ impl VirtualCableTrait for WindowsVirtualCable {
             overruns: self.overruns.load(Ordering::Relaxed),
             latency_ms: self.calculate_latency(),
             cpu_usage: self.estimate_cpu_usage(),
+            ..Default::default()
         }
     }
 
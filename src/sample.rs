@@ -0,0 +1,415 @@
+//! Typed, alignment-independent views of encoded sample bytes.
+//!
+//! Each [`AudioFormat`] has a marker type implementing [`Sample`], and
+//! [`SampleSlice`] / [`SampleSliceMut`] view a byte buffer as samples of
+//! that format. Samples are decoded byte by byte with an explicit byte
+//! order, so the buffer may have any alignment and the result is the same on
+//! little- and big-endian hosts. A buffer that does not hold a whole number
+//! of samples (or frames) is rejected with an error instead of being
+//! silently truncated.
+
+use crate::audio::{g711, Quantizer};
+use crate::{AudioFormat, Error};
+use std::marker::PhantomData;
+
+/// An encoded sample format.
+///
+/// Implemented by one zero-sized marker type per [`AudioFormat`], e.g.
+/// [`S16LE`] or [`MuLaw`].
+pub trait Sample: Copy + Default + Send + Sync + 'static {
+    /// The runtime format tag of this encoding.
+    const FORMAT: AudioFormat;
+
+    /// Size of one encoded sample in bytes.
+    const SIZE: usize;
+
+    /// Decodes one sample from exactly [`Sample::SIZE`] bytes.
+    fn decode(bytes: &[u8]) -> f32;
+
+    /// Encodes one sample into exactly [`Sample::SIZE`] bytes.
+    ///
+    /// Integer encodings are rounded or dithered by `quantizer`, which also
+    /// counts clipped samples.
+    fn encode(sample: f32, quantizer: &mut Quantizer, bytes: &mut [u8]);
+}
+
+/// Copies the first `N` bytes of a sample into an array.
+fn array<const N: usize>(bytes: &[u8]) -> [u8; N] {
+    bytes[..N].try_into().expect("chunk holds a whole sample")
+}
+
+macro_rules! sample_formats {
+    ($(
+        $(#[$doc:meta])*
+        $name:ident, $size:expr,
+        decode |$bytes:ident| $decode:expr,
+        encode |$sample:ident, $quantizer:ident| $encode:expr;
+    )*) => {
+        $(
+            $(#[$doc])*
+            #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+            pub struct $name;
+
+            impl Sample for $name {
+                const FORMAT: AudioFormat = AudioFormat::$name;
+                const SIZE: usize = $size;
+
+                fn decode($bytes: &[u8]) -> f32 {
+                    $decode
+                }
+
+                #[allow(unused_variables)]
+                fn encode($sample: f32, $quantizer: &mut Quantizer, bytes: &mut [u8]) {
+                    bytes.copy_from_slice(&$encode);
+                }
+            }
+        )*
+
+        /// Evaluates the body with the given name bound to the marker type of a
+        /// runtime [`AudioFormat`].
+        macro_rules! with_sample_format {
+            ($format:expr, $F:ident => $body:expr) => {
+                match $format {
+                    $(AudioFormat::$name => {
+                        type $F = $crate::sample::$name;
+                        $body
+                    })*
+                }
+            };
+        }
+    };
+}
+
+sample_formats! {
+    /// 32-bit float, little-endian
+    F32LE, 4,
+    decode |b| f32::from_le_bytes(array(b)),
+    encode |s, q| s.to_le_bytes();
+
+    /// 16-bit signed integer, little-endian
+    S16LE, 2,
+    decode |b| i16::from_le_bytes(array(b)) as f32 / 32768.0,
+    encode |s, q| (q.quantize(s, 16) as i16).to_le_bytes();
+
+    /// 24-bit signed integer, little-endian
+    S24LE, 3,
+    decode |b| {
+        // Load into the top three bytes, then shift to sign-extend
+        (i32::from_le_bytes([0, b[0], b[1], b[2]]) >> 8) as f32 / 8388608.0
+    },
+    encode |s, q| {
+        let [b0, b1, b2, _] = q.quantize(s, 24).to_le_bytes();
+        [b0, b1, b2]
+    };
+
+    /// 32-bit signed integer, little-endian
+    S32LE, 4,
+    decode |b| i32::from_le_bytes(array(b)) as f32 / 2147483648.0,
+    encode |s, q| q.quantize(s, 32).to_le_bytes();
+
+    /// 8-bit unsigned integer, centred on 128
+    U8, 1,
+    decode |b| (b[0] as f32 - 128.0) / 128.0,
+    encode |s, q| [(q.quantize(s, 8) + 128) as u8];
+
+    /// 16-bit signed integer, big-endian
+    S16BE, 2,
+    decode |b| i16::from_be_bytes(array(b)) as f32 / 32768.0,
+    encode |s, q| (q.quantize(s, 16) as i16).to_be_bytes();
+
+    /// 24-bit signed integer, big-endian
+    S24BE, 3,
+    decode |b| (i32::from_be_bytes([b[0], b[1], b[2], 0]) >> 8) as f32 / 8388608.0,
+    encode |s, q| {
+        let [_, b1, b2, b3] = q.quantize(s, 24).to_be_bytes();
+        [b1, b2, b3]
+    };
+
+    /// 24-bit signed integer in a 32-bit little-endian word; the top byte
+    /// is ignored when decoding
+    S24_32LE, 4,
+    decode |b| (i32::from_le_bytes(array(b)) << 8 >> 8) as f32 / 8388608.0,
+    encode |s, q| q.quantize(s, 24).to_le_bytes();
+
+    /// 24-bit signed integer in a 32-bit big-endian word; the top byte is
+    /// ignored when decoding
+    S24_32BE, 4,
+    decode |b| (i32::from_be_bytes(array(b)) << 8 >> 8) as f32 / 8388608.0,
+    encode |s, q| q.quantize(s, 24).to_be_bytes();
+
+    /// 32-bit signed integer, big-endian
+    S32BE, 4,
+    decode |b| i32::from_be_bytes(array(b)) as f32 / 2147483648.0,
+    encode |s, q| q.quantize(s, 32).to_be_bytes();
+
+    /// 32-bit float, big-endian
+    F32BE, 4,
+    decode |b| f32::from_be_bytes(array(b)),
+    encode |s, q| s.to_be_bytes();
+
+    /// 64-bit float, little-endian
+    F64LE, 8,
+    decode |b| f64::from_le_bytes(array(b)) as f32,
+    encode |s, q| f64::from(s).to_le_bytes();
+
+    /// 64-bit float, big-endian
+    F64BE, 8,
+    decode |b| f64::from_be_bytes(array(b)) as f32,
+    encode |s, q| f64::from(s).to_be_bytes();
+
+    /// 8-bit G.711 A-law
+    ALaw, 1,
+    decode |b| g711::decode_alaw(b[0]) as f32 / 32768.0,
+    encode |s, q| [g711::encode_alaw(q.quantize(s, 16) as i16)];
+
+    /// 8-bit G.711 μ-law
+    MuLaw, 1,
+    decode |b| g711::decode_ulaw(b[0]) as f32 / 32768.0,
+    encode |s, q| [g711::encode_ulaw(q.quantize(s, 16) as i16)];
+}
+
+pub(crate) use with_sample_format;
+
+/// Checks that `len` bytes hold whole units of `unit` bytes.
+fn check_whole(format: AudioFormat, len: usize, unit: usize, what: &str) -> Result<(), Error> {
+    let trailing = len % unit.max(1);
+    if trailing == 0 {
+        return Ok(());
+    }
+    Err(Error::AudioError(format!(
+        "{} bytes of {} audio end with a partial {} ({} of {} bytes)",
+        len,
+        format.name(),
+        what,
+        trailing,
+        unit
+    )))
+}
+
+/// A read-only view of encoded samples.
+#[derive(Debug, Clone, Copy)]
+pub struct SampleSlice<'a, F> {
+    bytes: &'a [u8],
+    format: PhantomData<F>,
+}
+
+impl<'a, F: Sample> SampleSlice<'a, F> {
+    /// Views `bytes` as samples.
+    ///
+    /// Fails if the last sample is incomplete.
+    pub fn new(bytes: &'a [u8]) -> Result<Self, Error> {
+        check_whole(F::FORMAT, bytes.len(), F::SIZE, "sample")?;
+        Ok(Self::whole(bytes))
+    }
+
+    /// Views `bytes` as interleaved frames of `channels` samples.
+    ///
+    /// Fails if the last frame is incomplete.
+    pub fn from_frames(bytes: &'a [u8], channels: usize) -> Result<Self, Error> {
+        check_whole(F::FORMAT, bytes.len(), F::SIZE * channels, "frame")?;
+        Ok(Self::whole(bytes))
+    }
+
+    /// Splits `bytes` into a view of the whole samples and the leftover bytes
+    /// of an incomplete last sample.
+    ///
+    /// Useful for streams where a sample can straddle two reads.
+    pub fn split_whole(bytes: &'a [u8]) -> (Self, &'a [u8]) {
+        let (whole, rest) = bytes.split_at(bytes.len() - bytes.len() % F::SIZE);
+        (Self::whole(whole), rest)
+    }
+
+    fn whole(bytes: &'a [u8]) -> Self {
+        Self {
+            bytes,
+            format: PhantomData,
+        }
+    }
+
+    /// Returns the number of samples.
+    pub fn len(&self) -> usize {
+        self.bytes.len() / F::SIZE
+    }
+
+    /// Returns true if the view holds no samples.
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    /// Returns the encoded bytes.
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.bytes
+    }
+
+    /// Decodes the sample at `index`.
+    pub fn get(&self, index: usize) -> Option<f32> {
+        let start = index.checked_mul(F::SIZE)?;
+        self.bytes.get(start..start + F::SIZE).map(F::decode)
+    }
+
+    /// Returns an iterator decoding every sample.
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = f32> + 'a {
+        self.bytes.chunks_exact(F::SIZE).map(F::decode)
+    }
+
+    /// Decodes as many samples as fit into `output`.
+    ///
+    /// Returns the number of samples decoded.
+    pub fn decode_into(&self, output: &mut [f32]) -> usize {
+        let mut decoded = 0;
+        for (out, sample) in output.iter_mut().zip(self.iter()) {
+            *out = sample;
+            decoded += 1;
+        }
+        decoded
+    }
+
+    /// Decodes every sample into a new vector.
+    pub fn to_vec(&self) -> Vec<f32> {
+        self.iter().collect()
+    }
+}
+
+/// A writable view of encoded samples.
+#[derive(Debug)]
+pub struct SampleSliceMut<'a, F> {
+    bytes: &'a mut [u8],
+    format: PhantomData<F>,
+}
+
+impl<'a, F: Sample> SampleSliceMut<'a, F> {
+    /// Views `bytes` as samples.
+    ///
+    /// Fails if the last sample is incomplete.
+    pub fn new(bytes: &'a mut [u8]) -> Result<Self, Error> {
+        check_whole(F::FORMAT, bytes.len(), F::SIZE, "sample")?;
+        Ok(Self::whole(bytes))
+    }
+
+    /// Views `bytes` as interleaved frames of `channels` samples.
+    ///
+    /// Fails if the last frame is incomplete.
+    pub fn from_frames(bytes: &'a mut [u8], channels: usize) -> Result<Self, Error> {
+        check_whole(F::FORMAT, bytes.len(), F::SIZE * channels, "frame")?;
+        Ok(Self::whole(bytes))
+    }
+
+    /// Splits `bytes` into a view of the whole samples and the leftover bytes
+    /// of an incomplete last sample.
+    pub fn split_whole(bytes: &'a mut [u8]) -> (Self, &'a mut [u8]) {
+        let whole = bytes.len() - bytes.len() % F::SIZE;
+        let (whole, rest) = bytes.split_at_mut(whole);
+        (Self::whole(whole), rest)
+    }
+
+    fn whole(bytes: &'a mut [u8]) -> Self {
+        Self {
+            bytes,
+            format: PhantomData,
+        }
+    }
+
+    /// Returns the number of samples.
+    pub fn len(&self) -> usize {
+        self.bytes.len() / F::SIZE
+    }
+
+    /// Returns true if the view holds no samples.
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    /// Returns a read-only view of the same samples.
+    pub fn as_slice(&self) -> SampleSlice<'_, F> {
+        SampleSlice::whole(self.bytes)
+    }
+
+    /// Encodes `sample` at `index`.
+    ///
+    /// Returns false if `index` is out of range.
+    pub fn set(&mut self, index: usize, sample: f32, quantizer: &mut Quantizer) -> bool {
+        let Some(start) = index.checked_mul(F::SIZE) else {
+            return false;
+        };
+        match self.bytes.get_mut(start..start + F::SIZE) {
+            Some(bytes) => {
+                F::encode(sample, quantizer, bytes);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Encodes as many samples of `input` as fit.
+    ///
+    /// Returns the number of samples encoded.
+    pub fn encode_from(&mut self, input: &[f32], quantizer: &mut Quantizer) -> usize {
+        let mut encoded = 0;
+        for (bytes, &sample) in self.bytes.chunks_exact_mut(F::SIZE).zip(input) {
+            F::encode(sample, quantizer, bytes);
+            encoded += 1;
+        }
+        encoded
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DitherMode;
+
+    #[test]
+    fn test_sizes_match_formats() {
+        for format in AudioFormat::ALL {
+            let size = with_sample_format!(format, F => {
+                assert_eq!(F::FORMAT, format);
+                F::SIZE
+            });
+            assert_eq!(size, format.bytes_per_sample(), "{:?}", format);
+        }
+    }
+
+    #[test]
+    fn test_misaligned_view_decodes() {
+        // Offset by one byte so the samples are not 4-byte aligned
+        let mut storage = [0u8; 9];
+        storage[1..5].copy_from_slice(&0.25f32.to_le_bytes());
+        storage[5..9].copy_from_slice(&(-0.5f32).to_le_bytes());
+
+        let view = SampleSlice::<F32LE>::new(&storage[1..]).unwrap();
+        assert_eq!(view.len(), 2);
+        assert_eq!(view.get(1), Some(-0.5));
+        assert_eq!(view.get(2), None);
+        assert_eq!(view.to_vec(), [0.25, -0.5]);
+    }
+
+    #[test]
+    fn test_partial_samples_and_frames_are_errors() {
+        let bytes = [0u8; 7];
+        let error = SampleSlice::<S16LE>::new(&bytes).unwrap_err();
+        assert!(error.to_string().contains("partial sample"), "{}", error);
+        assert!(SampleSlice::<S16LE>::from_frames(&bytes[..6], 2).is_err());
+        assert_eq!(
+            SampleSlice::<S16LE>::from_frames(&bytes[..4], 2)
+                .unwrap()
+                .len(),
+            2
+        );
+
+        let (view, rest) = SampleSlice::<S24LE>::split_whole(&bytes);
+        assert_eq!(view.len(), 2);
+        assert_eq!(rest.len(), 1);
+    }
+
+    #[test]
+    fn test_encode_round_trip() {
+        let mut quantizer = Quantizer::new(DitherMode::Round, 1);
+        let mut bytes = [0u8; 6];
+        let mut view = SampleSliceMut::<S24BE>::new(&mut bytes).unwrap();
+        assert_eq!(view.encode_from(&[0.5, -0.25, 1.0], &mut quantizer), 2);
+        assert!(view.set(1, -1.0, &mut quantizer));
+        assert!(!view.set(2, 0.0, &mut quantizer));
+        assert_eq!(view.as_slice().to_vec(), [0.5, -1.0]);
+        assert_eq!(bytes, [0x40, 0x00, 0x00, 0x80, 0x00, 0x00]);
+    }
+}
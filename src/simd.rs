@@ -0,0 +1,581 @@
+//! Vectorized conversion and mixing kernels.
+//!
+//! Each kernel checks at runtime whether the CPU supports AVX2 and falls
+//! back to the portable implementation in [`scalar`] otherwise. Both paths
+//! give bit-identical results: conversions round to the nearest code with
+//! ties to even, saturate to the integer range, map NaN to zero and count
+//! samples outside -1.0..=1.0 as clipped, and the mixing kernels multiply
+//! and add in separate steps rather than with a fused multiply-add.
+//!
+//! Kernels process as many samples as fit in both slices.
+
+/// Instruction set used by the kernels on this CPU.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SimdLevel {
+    /// Portable scalar code
+    Scalar,
+
+    /// 256-bit AVX2 vectors (x86_64)
+    Avx2,
+}
+
+/// Returns the instruction set the kernels use on this CPU.
+pub fn simd_level() -> SimdLevel {
+    if has_avx2() {
+        SimdLevel::Avx2
+    } else {
+        SimdLevel::Scalar
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+fn has_avx2() -> bool {
+    is_x86_feature_detected!("avx2")
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+fn has_avx2() -> bool {
+    false
+}
+
+/// Signature of the float to integer kernels.
+pub type EncodeKernel = fn(&[f32], &mut [u8]) -> u64;
+
+/// Signature of the integer to float kernels.
+pub type DecodeKernel = fn(&[u8], &mut [f32]);
+
+/// Runs the AVX2 kernel if the CPU supports it, otherwise the scalar one.
+macro_rules! dispatch {
+    ($kernel:ident($($arg:expr),*)) => {{
+        #[cfg(target_arch = "x86_64")]
+        if has_avx2() {
+            // SAFETY: AVX2 support was checked just above
+            return unsafe { avx2::$kernel($($arg),*) };
+        }
+        scalar::$kernel($($arg),*)
+    }};
+}
+
+/// Converts samples to 16-bit little-endian bytes. Returns the number of
+/// clipped samples.
+pub fn f32_to_s16le(input: &[f32], output: &mut [u8]) -> u64 {
+    dispatch!(f32_to_s16le(input, output))
+}
+
+/// Converts samples to packed 24-bit little-endian bytes. Returns the number
+/// of clipped samples.
+pub fn f32_to_s24le(input: &[f32], output: &mut [u8]) -> u64 {
+    dispatch!(f32_to_s24le(input, output))
+}
+
+/// Converts samples to 32-bit little-endian bytes. Returns the number of
+/// clipped samples.
+pub fn f32_to_s32le(input: &[f32], output: &mut [u8]) -> u64 {
+    dispatch!(f32_to_s32le(input, output))
+}
+
+/// Converts 16-bit little-endian bytes to samples.
+pub fn s16le_to_f32(input: &[u8], output: &mut [f32]) {
+    dispatch!(s16le_to_f32(input, output))
+}
+
+/// Converts packed 24-bit little-endian bytes to samples.
+pub fn s24le_to_f32(input: &[u8], output: &mut [f32]) {
+    dispatch!(s24le_to_f32(input, output))
+}
+
+/// Converts 32-bit little-endian bytes to samples.
+pub fn s32le_to_f32(input: &[u8], output: &mut [f32]) {
+    dispatch!(s32le_to_f32(input, output))
+}
+
+/// Copies samples to 32-bit float little-endian bytes, a plain copy on
+/// little-endian hosts. Never clips; returns 0.
+pub fn f32_to_f32le(input: &[f32], output: &mut [u8]) -> u64 {
+    for (bytes, sample) in output.chunks_exact_mut(4).zip(input) {
+        bytes.copy_from_slice(&sample.to_le_bytes());
+    }
+    0
+}
+
+/// Copies 32-bit float little-endian bytes to samples, a plain copy on
+/// little-endian hosts.
+pub fn f32le_to_f32(input: &[u8], output: &mut [f32]) {
+    for (out, bytes) in output.iter_mut().zip(input.chunks_exact(4)) {
+        *out = f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+    }
+}
+
+/// Interleaves one slice per channel into `output`.
+pub fn interleave(inputs: &[&[f32]], output: &mut [f32]) {
+    dispatch!(interleave(inputs, output))
+}
+
+/// Splits interleaved `input` into one slice per channel.
+pub fn deinterleave(input: &[f32], outputs: &mut [&mut [f32]]) {
+    dispatch!(deinterleave(input, outputs))
+}
+
+/// Multiplies every sample by `gain`.
+pub fn apply_gain(samples: &mut [f32], gain: f32) {
+    dispatch!(apply_gain(samples, gain))
+}
+
+/// Adds `input` scaled by `gain` to `output`.
+pub fn mix_into(output: &mut [f32], input: &[f32], gain: f32) {
+    dispatch!(mix_into(output, input, gain))
+}
+
+/// Portable reference implementations of the kernels.
+pub mod scalar {
+    /// Rounds a sample to a code of the integer range `-scale..scale`.
+    #[inline]
+    fn quantize(sample: f32, scale: f32, max: i32) -> i32 {
+        let value = (sample.clamp(-1.0, 1.0) * scale).round_ties_even();
+        if value >= scale {
+            max
+        } else {
+            // NaN converts to zero
+            value as i32
+        }
+    }
+
+    #[inline]
+    fn is_clipped(sample: f32) -> bool {
+        !(-1.0..=1.0).contains(&sample)
+    }
+
+    /// See [`super::f32_to_s16le`].
+    pub fn f32_to_s16le(input: &[f32], output: &mut [u8]) -> u64 {
+        let mut clipped = 0;
+        for (bytes, &sample) in output.chunks_exact_mut(2).zip(input) {
+            clipped += u64::from(is_clipped(sample));
+            let code = quantize(sample, 32768.0, i16::MAX as i32) as i16;
+            bytes.copy_from_slice(&code.to_le_bytes());
+        }
+        clipped
+    }
+
+    /// See [`super::f32_to_s24le`].
+    pub fn f32_to_s24le(input: &[f32], output: &mut [u8]) -> u64 {
+        let mut clipped = 0;
+        for (bytes, &sample) in output.chunks_exact_mut(3).zip(input) {
+            clipped += u64::from(is_clipped(sample));
+            let code = quantize(sample, 8_388_608.0, 8_388_607);
+            bytes.copy_from_slice(&code.to_le_bytes()[..3]);
+        }
+        clipped
+    }
+
+    /// See [`super::f32_to_s32le`].
+    pub fn f32_to_s32le(input: &[f32], output: &mut [u8]) -> u64 {
+        let mut clipped = 0;
+        for (bytes, &sample) in output.chunks_exact_mut(4).zip(input) {
+            clipped += u64::from(is_clipped(sample));
+            let code = quantize(sample, 2_147_483_648.0, i32::MAX);
+            bytes.copy_from_slice(&code.to_le_bytes());
+        }
+        clipped
+    }
+
+    /// See [`super::s16le_to_f32`].
+    pub fn s16le_to_f32(input: &[u8], output: &mut [f32]) {
+        for (out, bytes) in output.iter_mut().zip(input.chunks_exact(2)) {
+            *out = i16::from_le_bytes([bytes[0], bytes[1]]) as f32 / 32768.0;
+        }
+    }
+
+    /// See [`super::s24le_to_f32`].
+    pub fn s24le_to_f32(input: &[u8], output: &mut [f32]) {
+        for (out, bytes) in output.iter_mut().zip(input.chunks_exact(3)) {
+            // Load into the top three bytes, then shift to sign-extend
+            let code = i32::from_le_bytes([0, bytes[0], bytes[1], bytes[2]]) >> 8;
+            *out = code as f32 / 8_388_608.0;
+        }
+    }
+
+    /// See [`super::s32le_to_f32`].
+    pub fn s32le_to_f32(input: &[u8], output: &mut [f32]) {
+        for (out, bytes) in output.iter_mut().zip(input.chunks_exact(4)) {
+            let code = i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+            *out = code as f32 / 2_147_483_648.0;
+        }
+    }
+
+    /// See [`super::interleave`].
+    pub fn interleave(inputs: &[&[f32]], output: &mut [f32]) {
+        let Some(frames) = inputs.iter().map(|input| input.len()).min() else {
+            return;
+        };
+        for (frame, out) in output
+            .chunks_exact_mut(inputs.len())
+            .take(frames)
+            .enumerate()
+        {
+            for (sample, input) in out.iter_mut().zip(inputs) {
+                *sample = input[frame];
+            }
+        }
+    }
+
+    /// See [`super::deinterleave`].
+    pub fn deinterleave(input: &[f32], outputs: &mut [&mut [f32]]) {
+        let Some(frames) = outputs.iter().map(|output| output.len()).min() else {
+            return;
+        };
+        for (frame, samples) in input.chunks_exact(outputs.len()).take(frames).enumerate() {
+            for (output, &sample) in outputs.iter_mut().zip(samples) {
+                output[frame] = sample;
+            }
+        }
+    }
+
+    /// See [`super::apply_gain`].
+    pub fn apply_gain(samples: &mut [f32], gain: f32) {
+        for sample in samples {
+            *sample *= gain;
+        }
+    }
+
+    /// See [`super::mix_into`].
+    pub fn mix_into(output: &mut [f32], input: &[f32], gain: f32) {
+        for (out, &sample) in output.iter_mut().zip(input) {
+            *out += sample * gain;
+        }
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+mod avx2 {
+    //! AVX2 kernels. Every function requires AVX2 support and handles the
+    //! tail that does not fill a whole vector with the scalar code.
+
+    use super::scalar;
+    use std::arch::x86_64::*;
+
+    const LANES: usize = 8;
+
+    /// Counts lanes outside -1.0..=1.0 (NaN included), zeroes NaN lanes
+    /// and clamps the rest.
+    #[inline]
+    #[target_feature(enable = "avx2")]
+    unsafe fn clamp(x: __m256, clipped: &mut u64) -> __m256 {
+        let (low, high) = (_mm256_set1_ps(-1.0), _mm256_set1_ps(1.0));
+        let inside = _mm256_and_ps(
+            _mm256_cmp_ps::<_CMP_GE_OQ>(x, low),
+            _mm256_cmp_ps::<_CMP_LE_OQ>(x, high),
+        );
+        *clipped += u64::from(LANES as u32 - _mm256_movemask_ps(inside).count_ones());
+        let x = _mm256_and_ps(x, _mm256_cmp_ps::<_CMP_ORD_Q>(x, x));
+        _mm256_min_ps(_mm256_max_ps(x, low), high)
+    }
+
+    /// Scales eight samples and rounds them to codes, ties to even.
+    #[inline]
+    #[target_feature(enable = "avx2")]
+    unsafe fn codes(input: *const f32, scale: f32, clipped: &mut u64) -> (__m256, __m256i) {
+        let scaled = _mm256_mul_ps(
+            clamp(_mm256_loadu_ps(input), clipped),
+            _mm256_set1_ps(scale),
+        );
+        (scaled, _mm256_cvtps_epi32(scaled))
+    }
+
+    #[target_feature(enable = "avx2")]
+    pub unsafe fn f32_to_s16le(input: &[f32], output: &mut [u8]) -> u64 {
+        let len = input.len().min(output.len() / 2);
+        let mut clipped = 0;
+        let mut i = 0;
+        while i + 2 * LANES <= len {
+            let (_, a) = codes(input.as_ptr().add(i), 32768.0, &mut clipped);
+            let (_, b) = codes(input.as_ptr().add(i + LANES), 32768.0, &mut clipped);
+            // Saturating pack works per 128-bit lane; restore the order
+            let packed = _mm256_permute4x64_epi64::<0b11_01_10_00>(_mm256_packs_epi32(a, b));
+            _mm256_storeu_si256(output.as_mut_ptr().add(i * 2) as *mut __m256i, packed);
+            i += 2 * LANES;
+        }
+        clipped + scalar::f32_to_s16le(&input[i..len], &mut output[i * 2..len * 2])
+    }
+
+    #[target_feature(enable = "avx2")]
+    pub unsafe fn f32_to_s24le(input: &[f32], output: &mut [u8]) -> u64 {
+        let len = input.len().min(output.len() / 3);
+        let mut clipped = 0;
+        let mut block = [0i32; LANES];
+        let mut i = 0;
+        while i + LANES <= len {
+            let (_, code) = codes(input.as_ptr().add(i), 8_388_608.0, &mut clipped);
+            let code = _mm256_min_epi32(code, _mm256_set1_epi32(8_388_607));
+            _mm256_storeu_si256(block.as_mut_ptr() as *mut __m256i, code);
+            for (bytes, code) in output[i * 3..(i + LANES) * 3]
+                .chunks_exact_mut(3)
+                .zip(block)
+            {
+                bytes.copy_from_slice(&code.to_le_bytes()[..3]);
+            }
+            i += LANES;
+        }
+        clipped + scalar::f32_to_s24le(&input[i..len], &mut output[i * 3..len * 3])
+    }
+
+    #[target_feature(enable = "avx2")]
+    pub unsafe fn f32_to_s32le(input: &[f32], output: &mut [u8]) -> u64 {
+        let len = input.len().min(output.len() / 4);
+        let scale = 2_147_483_648.0;
+        let mut clipped = 0;
+        let mut i = 0;
+        while i + LANES <= len {
+            let (scaled, code) = codes(input.as_ptr().add(i), scale, &mut clipped);
+            // 1.0 scales to 2^31, which the conversion cannot represent
+            let over =
+                _mm256_castps_si256(_mm256_cmp_ps::<_CMP_GE_OQ>(scaled, _mm256_set1_ps(scale)));
+            let code = _mm256_blendv_epi8(code, _mm256_set1_epi32(i32::MAX), over);
+            _mm256_storeu_si256(output.as_mut_ptr().add(i * 4) as *mut __m256i, code);
+            i += LANES;
+        }
+        clipped + scalar::f32_to_s32le(&input[i..len], &mut output[i * 4..len * 4])
+    }
+
+    #[target_feature(enable = "avx2")]
+    pub unsafe fn s16le_to_f32(input: &[u8], output: &mut [f32]) {
+        let len = output.len().min(input.len() / 2);
+        let scale = _mm256_set1_ps(1.0 / 32768.0);
+        let mut i = 0;
+        while i + LANES <= len {
+            let codes = _mm_loadu_si128(input.as_ptr().add(i * 2) as *const __m128i);
+            let samples = _mm256_cvtepi32_ps(_mm256_cvtepi16_epi32(codes));
+            _mm256_storeu_ps(output.as_mut_ptr().add(i), _mm256_mul_ps(samples, scale));
+            i += LANES;
+        }
+        scalar::s16le_to_f32(&input[i * 2..len * 2], &mut output[i..len]);
+    }
+
+    #[target_feature(enable = "avx2")]
+    pub unsafe fn s24le_to_f32(input: &[u8], output: &mut [f32]) {
+        let len = output.len().min(input.len() / 3);
+        let scale = _mm256_set1_ps(1.0 / 8_388_608.0);
+        // Bytes 0..12 of the load to the low lane, 12..24 to the high lane
+        let lanes = _mm256_setr_epi32(0, 1, 2, 0, 3, 4, 5, 0);
+        // Each sample to the top three bytes of its word
+        #[rustfmt::skip]
+        let spread = _mm256_setr_epi8(
+            -128, 0, 1, 2, -128, 3, 4, 5, -128, 6, 7, 8, -128, 9, 10, 11,
+            -128, 0, 1, 2, -128, 3, 4, 5, -128, 6, 7, 8, -128, 9, 10, 11,
+        );
+        let mut i = 0;
+        // The 32-byte load reads 8 bytes past the eight samples
+        while i + LANES <= len && i * 3 + 32 <= input.len() {
+            let bytes = _mm256_loadu_si256(input.as_ptr().add(i * 3) as *const __m256i);
+            let words = _mm256_shuffle_epi8(_mm256_permutevar8x32_epi32(bytes, lanes), spread);
+            let samples = _mm256_cvtepi32_ps(_mm256_srai_epi32::<8>(words));
+            _mm256_storeu_ps(output.as_mut_ptr().add(i), _mm256_mul_ps(samples, scale));
+            i += LANES;
+        }
+        scalar::s24le_to_f32(&input[i * 3..len * 3], &mut output[i..len]);
+    }
+
+    #[target_feature(enable = "avx2")]
+    pub unsafe fn s32le_to_f32(input: &[u8], output: &mut [f32]) {
+        let len = output.len().min(input.len() / 4);
+        let scale = _mm256_set1_ps(1.0 / 2_147_483_648.0);
+        let mut i = 0;
+        while i + LANES <= len {
+            let codes = _mm256_loadu_si256(input.as_ptr().add(i * 4) as *const __m256i);
+            let samples = _mm256_cvtepi32_ps(codes);
+            _mm256_storeu_ps(output.as_mut_ptr().add(i), _mm256_mul_ps(samples, scale));
+            i += LANES;
+        }
+        scalar::s32le_to_f32(&input[i * 4..len * 4], &mut output[i..len]);
+    }
+
+    #[target_feature(enable = "avx2")]
+    pub unsafe fn interleave(inputs: &[&[f32]], output: &mut [f32]) {
+        let [left, right] = inputs else {
+            return scalar::interleave(inputs, output);
+        };
+        let frames = left.len().min(right.len()).min(output.len() / 2);
+        let mut i = 0;
+        while i + LANES <= frames {
+            let l = _mm256_loadu_ps(left.as_ptr().add(i));
+            let r = _mm256_loadu_ps(right.as_ptr().add(i));
+            let (low, high) = (_mm256_unpacklo_ps(l, r), _mm256_unpackhi_ps(l, r));
+            let out = output.as_mut_ptr().add(i * 2);
+            _mm256_storeu_ps(out, _mm256_permute2f128_ps::<0x20>(low, high));
+            _mm256_storeu_ps(out.add(LANES), _mm256_permute2f128_ps::<0x31>(low, high));
+            i += LANES;
+        }
+        scalar::interleave(
+            &[&left[i..frames], &right[i..frames]],
+            &mut output[i * 2..frames * 2],
+        );
+    }
+
+    #[target_feature(enable = "avx2")]
+    pub unsafe fn deinterleave(input: &[f32], outputs: &mut [&mut [f32]]) {
+        let [left, right] = outputs else {
+            return scalar::deinterleave(input, outputs);
+        };
+        let frames = left.len().min(right.len()).min(input.len() / 2);
+        let mut i = 0;
+        while i + LANES <= frames {
+            let a = _mm256_loadu_ps(input.as_ptr().add(i * 2));
+            let b = _mm256_loadu_ps(input.as_ptr().add(i * 2 + LANES));
+            let first = _mm256_permute2f128_ps::<0x20>(a, b);
+            let second = _mm256_permute2f128_ps::<0x31>(a, b);
+            _mm256_storeu_ps(
+                left.as_mut_ptr().add(i),
+                _mm256_shuffle_ps::<0b10_00_10_00>(first, second),
+            );
+            _mm256_storeu_ps(
+                right.as_mut_ptr().add(i),
+                _mm256_shuffle_ps::<0b11_01_11_01>(first, second),
+            );
+            i += LANES;
+        }
+        scalar::deinterleave(
+            &input[i * 2..frames * 2],
+            &mut [&mut left[i..frames], &mut right[i..frames]],
+        );
+    }
+
+    #[target_feature(enable = "avx2")]
+    pub unsafe fn apply_gain(samples: &mut [f32], gain: f32) {
+        let factor = _mm256_set1_ps(gain);
+        let mut chunks = samples.chunks_exact_mut(LANES);
+        for chunk in &mut chunks {
+            let x = _mm256_loadu_ps(chunk.as_ptr());
+            _mm256_storeu_ps(chunk.as_mut_ptr(), _mm256_mul_ps(x, factor));
+        }
+        scalar::apply_gain(chunks.into_remainder(), gain);
+    }
+
+    #[target_feature(enable = "avx2")]
+    pub unsafe fn mix_into(output: &mut [f32], input: &[f32], gain: f32) {
+        let len = output.len().min(input.len());
+        let factor = _mm256_set1_ps(gain);
+        let mut i = 0;
+        while i + LANES <= len {
+            let scaled = _mm256_mul_ps(_mm256_loadu_ps(input.as_ptr().add(i)), factor);
+            let sum = _mm256_add_ps(_mm256_loadu_ps(output.as_ptr().add(i)), scaled);
+            _mm256_storeu_ps(output.as_mut_ptr().add(i), sum);
+            i += LANES;
+        }
+        scalar::mix_into(&mut output[i..len], &input[i..len], gain);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Samples covering the edge cases, with a length that leaves a tail.
+    fn test_signal() -> Vec<f32> {
+        let mut samples: Vec<f32> = (0..1021).map(|i| (i as f32 * 0.37).sin() * 1.2).collect();
+        samples[..12].copy_from_slice(&[
+            1.0,
+            -1.0,
+            0.5 / 32768.0,
+            1.5 / 32768.0,
+            -0.5 / 32768.0,
+            f32::NAN,
+            f32::INFINITY,
+            f32::NEG_INFINITY,
+            -0.0,
+            0.999_999_9,
+            -1.000_000_1,
+            0.5 / 8_388_608.0,
+        ]);
+        samples
+    }
+
+    #[test]
+    fn test_encoders_match_scalar() {
+        let input = test_signal();
+        let kernels: [(EncodeKernel, EncodeKernel, usize); 3] = [
+            (f32_to_s16le, scalar::f32_to_s16le, 2),
+            (f32_to_s24le, scalar::f32_to_s24le, 3),
+            (f32_to_s32le, scalar::f32_to_s32le, 4),
+        ];
+        for (kernel, reference, size) in kernels {
+            let mut fast = vec![0u8; input.len() * size];
+            let mut slow = vec![0u8; input.len() * size];
+            assert_eq!(kernel(&input, &mut fast), reference(&input, &mut slow));
+            assert_eq!(fast, slow, "{} byte samples", size);
+        }
+
+        let mut bytes = [0u8; 4];
+        f32_to_s16le(&[1.0, -1.0], &mut bytes);
+        assert_eq!(bytes, [0xFF, 0x7F, 0x00, 0x80]);
+    }
+
+    #[test]
+    fn test_decoders_match_scalar() {
+        let bytes: Vec<u8> = (0..4093u32).map(|i| (i * 131 % 251) as u8).collect();
+        let (mut fast, mut slow) = (vec![0.0f32; 1023], vec![0.0f32; 1023]);
+        let kernels: [(DecodeKernel, DecodeKernel); 3] = [
+            (s16le_to_f32, scalar::s16le_to_f32),
+            (s24le_to_f32, scalar::s24le_to_f32),
+            (s32le_to_f32, scalar::s32le_to_f32),
+        ];
+        for (kernel, reference) in kernels {
+            kernel(&bytes, &mut fast);
+            reference(&bytes, &mut slow);
+            assert_eq!(fast, slow);
+        }
+
+        // Negative 24-bit codes are sign-extended
+        let mut samples = [0.0f32; 2];
+        s24le_to_f32(&[0x00, 0x00, 0x80, 0xFF, 0xFF, 0x7F], &mut samples);
+        assert_eq!(samples, [-1.0, 8_388_607.0 / 8_388_608.0]);
+    }
+
+    #[test]
+    fn test_f32le_copies_round_trip() {
+        let input = test_signal();
+        let mut bytes = vec![0u8; input.len() * 4];
+        assert_eq!(f32_to_f32le(&input, &mut bytes), 0);
+        let mut output = vec![0.0f32; input.len()];
+        f32le_to_f32(&bytes, &mut output);
+        assert_eq!(
+            output.iter().map(|s| s.to_bits()).collect::<Vec<_>>(),
+            input.iter().map(|s| s.to_bits()).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_interleave_round_trip() {
+        let left: Vec<f32> = (0..37).map(|i| i as f32).collect();
+        let right: Vec<f32> = (0..37).map(|i| -(i as f32)).collect();
+        let mut interleaved = vec![0.0f32; 74];
+        interleave(&[&left, &right], &mut interleaved);
+        assert_eq!(interleaved[..6], [0.0, -0.0, 1.0, -1.0, 2.0, -2.0]);
+        assert_eq!(interleaved[72..], [36.0, -36.0]);
+
+        let (mut l, mut r) = (vec![0.0f32; 37], vec![0.0f32; 37]);
+        deinterleave(&interleaved, &mut [&mut l, &mut r]);
+        assert_eq!((l, r), (left.clone(), right));
+
+        // Other channel counts take the scalar path
+        let mut three = vec![0.0f32; 6];
+        interleave(&[&[1.0, 2.0], &[3.0, 4.0], &[5.0, 6.0]], &mut three);
+        assert_eq!(three, [1.0, 3.0, 5.0, 2.0, 4.0, 6.0]);
+    }
+
+    #[test]
+    fn test_gain_and_mix_match_scalar() {
+        let input = test_signal();
+        let (mut fast, mut slow) = (input.clone(), input.clone());
+        apply_gain(&mut fast, 0.3);
+        scalar::apply_gain(&mut slow, 0.3);
+        assert_eq!(
+            fast.iter().map(|s| s.to_bits()).collect::<Vec<_>>(),
+            slow.iter().map(|s| s.to_bits()).collect::<Vec<_>>()
+        );
+
+        let mut fast = vec![0.25f32; input.len()];
+        let mut slow = fast.clone();
+        mix_into(&mut fast, &input[12..], 0.7);
+        scalar::mix_into(&mut slow, &input[12..], 0.7);
+        assert_eq!(fast, slow);
+    }
+}
@@ -0,0 +1,23 @@
+//! Signals shared by the unit tests.
+
+use std::f64::consts::PI;
+
+/// Sample rate of the generated signals, in Hz.
+pub(crate) const SAMPLE_RATE: f64 = 48_000.0;
+
+/// Interleaved sine at `frequency` Hz with a peak of `amplitude`, starting
+/// at `phase` radians, on every one of `channels` channels.
+pub(crate) fn sine(
+    frequency: f64,
+    amplitude: f64,
+    phase: f64,
+    frames: usize,
+    channels: usize,
+) -> Vec<f32> {
+    (0..frames)
+        .flat_map(|i| {
+            let s = amplitude * (2.0 * PI * frequency * i as f64 / SAMPLE_RATE + phase).sin();
+            std::iter::repeat_n(s as f32, channels)
+        })
+        .collect()
+}